@@ -0,0 +1,35 @@
+use raytracer::cli;
+
+/// Shorthand for `raytracer view`, taking the same options.
+fn main() {
+    let args = std::iter::once("view".to_string()).chain(std::env::args().skip(1));
+
+    let options = match cli::parse(args)
+    {
+        Ok(cli::Command::View(options)) => options,
+        Ok(_) => {
+            print!("{}", cli::USAGE);
+            return;
+        },
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(2);
+        },
+    };
+
+    match cli::load_scene(&options)
+    {
+        Ok((scene, settings, warnings)) => {
+            for warning in warnings.iter()
+            {
+                eprintln!("warning: {}", warning);
+            }
+
+            raytracer::ui::view(scene, settings)
+        },
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        },
+    }
+}
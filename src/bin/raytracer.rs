@@ -0,0 +1,136 @@
+use raytracer::{
+    cli,
+    imageio,
+    render::{ self, RenderSettings },
+    integrator::Integrator,
+    Scene,
+};
+
+fn main() {
+    let command = match cli::parse(std::env::args().skip(1))
+    {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(2);
+        },
+    };
+
+    let result = match command
+    {
+        cli::Command::Render(options) => run_render(options),
+        cli::Command::View(options)   => run_view(options),
+        cli::Command::Info(options)   => run_info(options),
+        cli::Command::Help => {
+            print!("{}", cli::USAGE);
+            Ok(())
+        },
+    };
+
+    if let Err(err) = result
+    {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Loads the scene like `cli::load_scene`, printing its warnings.
+fn load_scene(options: &cli::Options) -> Result<(Scene, RenderSettings), String>
+{
+    let (scene, settings, warnings) = cli::load_scene(options)?;
+
+    for warning in warnings.iter()
+    {
+        eprintln!("warning: {}", warning);
+    }
+
+    Ok((scene, settings))
+}
+
+fn run_render(options: cli::Options) -> Result<(), String>
+{
+    let (mut scene, settings) = load_scene(&options)?;
+
+    let pixels = render::render(&mut scene, &settings, |sample| {
+        eprint!("\rsample {}/{}", sample, settings.samples);
+    });
+    eprintln!();
+
+    imageio::save(&options.output, settings.width, settings.height, &pixels)
+        .map_err(|err| format!("failed to write '{}': {}", options.output.display(), err))
+}
+
+#[cfg(feature = "viewer")]
+fn run_view(options: cli::Options) -> Result<(), String>
+{
+    let (scene, settings) = load_scene(&options)?;
+
+    raytracer::ui::view(scene, settings);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "viewer"))]
+fn run_view(_options: cli::Options) -> Result<(), String>
+{
+    Err("this build has no viewer, rebuild with the `viewer` feature enabled".to_string())
+}
+
+fn run_info(options: cli::Options) -> Result<(), String>
+{
+    let (scene, settings) = load_scene(&options)?;
+
+    match &options.scene
+    {
+        Some(path) => println!("scene:       {}", path.display()),
+        None       => println!("scene:       built-in demo scene"),
+    }
+    println!("objects:     {}", scene.objects().len());
+    println!("lights:      {}", scene.lights.len());
+    if let Some(atmosphere) = &scene.atmosphere
+    {
+        let (absorption, scattering) = (atmosphere.volume.absorption, atmosphere.volume.scattering);
+
+        println!("atmosphere:  absorption ({}, {}, {}), scattering ({}, {}, {}), anisotropy {}{}",
+            absorption.r, absorption.g, absorption.b,
+            scattering.r, scattering.g, scattering.b,
+            atmosphere.volume.anisotropy,
+            atmosphere.height.map_or(String::new(), |height| format!(", up to height {}", height)),
+        );
+    }
+    println!("camera:      position ({}, {}, {}), fov {:.1}",
+        scene.camera.pos.x, scene.camera.pos.y, scene.camera.pos.z,
+        scene.camera.tan_half_fov.atan().to_degrees() * 2.0,
+    );
+    println!("projection:  {}", scene.camera.projection);
+    if scene.camera.lens.aperture > 0.0
+    {
+        println!("lens:        aperture {}, focus distance {:.3}{}",
+            scene.camera.lens.aperture,
+            scene.camera.lens.focus_distance,
+            if scene.camera.lens.autofocus { " (autofocus)" } else { "" },
+        );
+    }
+    println!("resolution:  {}x{}", settings.width, settings.height);
+    println!("samples:     {}", settings.samples);
+    println!("bounces:     {}", settings.max_bounces);
+    match settings.seed
+    {
+        Some(seed) => println!("seed:        {}", seed),
+        None       => println!("seed:        random"),
+    }
+    println!("threads:     {}", settings.threads);
+    println!("sampler:     {}", settings.sampler);
+    println!("filter:      {}, radius {}", settings.filter, settings.filter.radius());
+    match settings.integrator
+    {
+        Integrator::AmbientOcclusion(ao) => println!("integrator:  ao, distance {}", ao.distance),
+        Integrator::PhotonMapping(mapper) => println!(
+            "integrator:  photon, {} photons per pass, radius {} shrinking with alpha {}",
+            mapper.photons, mapper.radius, mapper.alpha,
+        ),
+        integrator => println!("integrator:  {}", integrator),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use crate::{
+    RGB,
+    math::{ Vec3, Rng },
+};
+use super::{ BsdfSample, fresnel, microfacet::Ggx, same_hemisphere };
+
+use rand::Rng as _;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A metal, reflecting light off GGX distributed microfacets with the Fresnel reflectance of
+/// its complex index of refraction `eta + ik`.
+#[derive(Debug, Clone, Copy)]
+pub struct Conductor
+{
+    pub eta: RGB,
+    pub k: RGB,
+    /// 0 is a perfect mirror, 1 is very rough.
+    pub roughness: f32,
+}
+
+/// Measured metals, their indices of refraction sampled at about 650, 550 and 450 nm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metal
+{
+    Gold,
+    Copper,
+    Aluminium,
+    Silver,
+}
+
+impl Conductor
+{
+    pub fn new(eta: RGB, k: RGB, roughness: f32) -> Conductor
+    {
+        Conductor {
+            eta,
+            k,
+            roughness,
+        }
+    }
+
+    pub fn metal(metal: Metal, roughness: f32) -> Conductor
+    {
+        let (eta, k) = match metal
+        {
+            Metal::Gold      => (RGB::new(0.143, 0.374, 1.442), RGB::new(3.983, 2.385, 1.603)),
+            Metal::Copper    => (RGB::new(0.200, 0.924, 1.102), RGB::new(3.912, 2.452, 2.142)),
+            Metal::Aluminium => (RGB::new(1.657, 0.880, 0.521), RGB::new(9.224, 6.270, 4.837)),
+            Metal::Silver    => (RGB::new(0.155, 0.117, 0.138), RGB::new(4.828, 3.122, 2.147)),
+        };
+
+        Conductor::new(eta, k, roughness)
+    }
+
+    /// A conductor reflecting `color` head-on and white at grazing angles, using Gulbrandsen's
+    /// artist friendly mapping with a white edge tint.
+    pub fn from_color(color: RGB, roughness: f32) -> Conductor
+    {
+        let channel = |r: f32| {
+            let r = r.clamp(0.0, 0.99) as f64;
+
+            let eta = (1.0 - r) / (1.0 + r);
+            let k = ((r * (eta + 1.0).powi(2) - (eta - 1.0).powi(2)) / (1.0 - r)).max(0.0).sqrt();
+
+            (eta as f32, k as f32)
+        };
+
+        let (r, g, b) = (channel(color.r), channel(color.g), channel(color.b));
+
+        Conductor::new(RGB::new(r.0, g.0, b.0), RGB::new(r.1, g.1, b.1), roughness)
+    }
+
+    fn distribution(&self) -> Ggx
+    {
+        Ggx::from_roughness(self.roughness as f64)
+    }
+
+    pub fn is_specular(&self) -> bool
+    {
+        self.distribution().is_smooth()
+    }
+
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> RGB
+    {
+        let ggx = self.distribution();
+
+        if ggx.is_smooth() || !same_hemisphere(wo, wi) || wo.z <= 0.0
+        {
+            return RGB::black();
+        }
+
+        let wm = (wi + wo).normalized();
+        let f = fresnel::conductor(wo.dot(wm).abs(), self.eta, self.k);
+
+        f * (ggx.d(wm) * ggx.g(wo, wi) / (4.0 * wo.z * wi.z)) as f32
+    }
+
+    pub fn sample(&self, wo: Vec3, rng: &mut Rng) -> Option<BsdfSample>
+    {
+        let ggx = self.distribution();
+
+        if wo.z <= 0.0
+        {
+            return None;
+        }
+
+        if ggx.is_smooth()
+        {
+            return Some(BsdfSample {
+                wi: Vec3::new(-wo.x, -wo.y, wo.z),
+                weight: fresnel::conductor(wo.z, self.eta, self.k),
+                pdf: 1.0,
+                specular: true,
+            });
+        }
+
+        let wm = ggx.sample_visible(wo, (rng.gen(), rng.gen()));
+        let wi = -wo + wm * (2.0 * wo.dot(wm));
+
+        if wi.z <= 0.0
+        {
+            return None;
+        }
+
+        let f = fresnel::conductor(wo.dot(wm), self.eta, self.k);
+
+        // The masking of `wo` cancels against the visible normal density.
+        Some(BsdfSample {
+            wi,
+            weight: f * (ggx.g(wo, wi) / ggx.g1(wo)) as f32,
+            pdf: ggx.pdf_visible(wo, wm) / (4.0 * wo.dot(wm)),
+            specular: false,
+        })
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f64
+    {
+        let ggx = self.distribution();
+
+        if ggx.is_smooth() || !same_hemisphere(wo, wi) || wo.z <= 0.0
+        {
+            return 0.0;
+        }
+
+        let wm = (wi + wo).normalized();
+
+        ggx.pdf_visible(wo, wm) / (4.0 * wo.dot(wm).abs())
+    }
+}
+
+impl fmt::Display for Metal
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Metal::Gold      => write!(f, "gold"),
+            Metal::Copper    => write!(f, "copper"),
+            Metal::Aluminium => write!(f, "aluminium"),
+            Metal::Silver    => write!(f, "silver"),
+        }
+    }
+}
+
+impl FromStr for Metal
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Metal, String>
+    {
+        match s
+        {
+            "gold"      => Ok(Metal::Gold),
+            "copper"    => Ok(Metal::Copper),
+            "aluminium" | "aluminum" => Ok(Metal::Aluminium),
+            "silver"    => Ok(Metal::Silver),
+            _ => Err(format!("unknown metal '{}', expected one of gold, copper, aluminium or silver", s)),
+        }
+    }
+}
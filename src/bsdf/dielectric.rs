@@ -0,0 +1,197 @@
+use crate::{
+    RGB,
+    math::{ Vec3, Rng },
+};
+use super::{ BsdfSample, fresnel, microfacet::Ggx };
+
+use rand::Rng as _;
+
+/// The boundary of a transparent object like glass or water, reflecting or refracting light
+/// off GGX distributed microfacets (Walter et al., "Microfacet Models for Refraction through
+/// Rough Surfaces").
+///
+/// The normal points out of the object, `r_index` is relative to the medium outside.
+#[derive(Debug, Clone, Copy)]
+pub struct Dielectric
+{
+    pub r_index: f32,
+    /// 0 is clear, larger values give frosted glass.
+    pub roughness: f32,
+    /// Tint of the light passing through the surface.
+    pub color: RGB,
+}
+
+impl Dielectric
+{
+    pub fn new(r_index: f32, roughness: f32) -> Dielectric
+    {
+        Dielectric {
+            r_index,
+            roughness,
+            color: RGB::gray(1.0),
+        }
+    }
+
+    fn distribution(&self) -> Ggx
+    {
+        Ggx::from_roughness(self.roughness as f64)
+    }
+
+    pub fn is_specular(&self) -> bool
+    {
+        self.distribution().is_smooth()
+    }
+
+    /// The ratio of the indices on the far and the near side, as seen from `wo`.
+    fn eta(&self, wo: Vec3) -> f64
+    {
+        if wo.z > 0.0 { self.r_index as f64 } else { 1.0 / self.r_index as f64 }
+    }
+
+    /// The microfacet normal that scatters `wo` into `wi`, facing the outside, or `None` if
+    /// the pair is not possible off any microfacet.
+    fn half_vector(&self, wo: Vec3, wi: Vec3) -> Option<Vec3>
+    {
+        let reflect = wo.z * wi.z > 0.0;
+        let eta = if reflect { 1.0 } else { self.eta(wo) };
+
+        let wm = (wi * eta + wo).normalized();
+
+        if wm.z == 0.0 || wo.z == 0.0 || wi.z == 0.0
+        {
+            return None;
+        }
+
+        let wm = if wm.z < 0.0 { -wm } else { wm };
+
+        // Microfacets facing away from either direction contribute nothing.
+        if wm.dot(wi) * wi.z < 0.0 || wm.dot(wo) * wo.z < 0.0
+        {
+            return None;
+        }
+
+        Some(wm)
+    }
+
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> RGB
+    {
+        if self.is_specular()
+        {
+            return RGB::black();
+        }
+
+        let wm = match self.half_vector(wo, wi)
+        {
+            Some(wm) => wm,
+            None => return RGB::black(),
+        };
+
+        let ggx = self.distribution();
+        let eta = self.eta(wo);
+        let f = fresnel::dielectric(wo.dot(wm).abs(), 1.0, eta);
+
+        if wo.z * wi.z > 0.0
+        {
+            return RGB::gray((ggx.d(wm) * ggx.g(wo, wi) * f / (4.0 * wo.z * wi.z).abs()) as f32);
+        }
+
+        let denom = (wi.dot(wm) + wo.dot(wm) / eta).powi(2);
+        let value = ggx.d(wm) * ggx.g(wo, wi) * (1.0 - f)
+            * (wi.dot(wm) * wo.dot(wm) / (wi.z * wo.z * denom)).abs();
+
+        // Radiance is compressed into a smaller solid angle entering a denser medium.
+        self.color * (value / (eta * eta)) as f32
+    }
+
+    pub fn sample(&self, wo: Vec3, rng: &mut Rng) -> Option<BsdfSample>
+    {
+        let eta = self.eta(wo);
+
+        // A smooth surface is a single microfacet.
+        let wm = if self.is_specular()
+        {
+            Vec3::new(0.0, 0.0, 1.0)
+        }
+        else
+        {
+            self.distribution().sample_visible(wo, (rng.gen(), rng.gen()))
+        };
+
+        let f = fresnel::dielectric(wo.dot(wm).abs(), 1.0, eta);
+
+        let reflect = rng.gen::<f64>() < f;
+
+        let wi = if reflect
+        {
+            -wo + wm * (2.0 * wo.dot(wm))
+        }
+        else
+        {
+            let facing = if wo.dot(wm) < 0.0 { -wm } else { wm };
+
+            (-wo).refract(facing, 1.0 / eta)?
+        };
+
+        // Reflections must stay on the side they came from and refractions must cross over.
+        if wi.z == 0.0 || (wi.z * wo.z > 0.0) != reflect
+        {
+            return None;
+        }
+
+        if self.is_specular()
+        {
+            return Some(if reflect
+            {
+                BsdfSample { wi, weight: RGB::gray(1.0), pdf: f, specular: true }
+            }
+            else
+            {
+                BsdfSample { wi, weight: self.color / (eta * eta) as f32, pdf: 1.0 - f, specular: true }
+            });
+        }
+
+        let pdf = self.pdf(wo, wi);
+
+        if pdf <= 0.0
+        {
+            return None;
+        }
+
+        Some(BsdfSample {
+            wi,
+            weight: self.eval(wo, wi) * (wi.z.abs() / pdf) as f32,
+            pdf,
+            specular: false,
+        })
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f64
+    {
+        if self.is_specular()
+        {
+            return 0.0;
+        }
+
+        let wm = match self.half_vector(wo, wi)
+        {
+            Some(wm) => wm,
+            None => return 0.0,
+        };
+
+        let ggx = self.distribution();
+        let eta = self.eta(wo);
+        let f = fresnel::dielectric(wo.dot(wm).abs(), 1.0, eta);
+        let pdf_wm = ggx.pdf_visible(wo, wm);
+
+        if wo.z * wi.z > 0.0
+        {
+            pdf_wm / (4.0 * wo.dot(wm).abs()) * f
+        }
+        else
+        {
+            let denom = (wi.dot(wm) + wo.dot(wm) / eta).powi(2);
+
+            pdf_wm * wi.dot(wm).abs() / denom * (1.0 - f)
+        }
+    }
+}
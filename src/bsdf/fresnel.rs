@@ -0,0 +1,51 @@
+use crate::RGB;
+
+/// The fraction of unpolarized light reflected off a smooth interface between two dielectrics,
+/// for light arriving at `cos_i` from the side with index `eta_i`. One under total internal
+/// reflection.
+pub fn dielectric(cos_i: f64, eta_i: f64, eta_t: f64) -> f64
+{
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin_t = eta_i / eta_t * (1.0 - cos_i * cos_i).sqrt();
+
+    if sin_t >= 1.0
+    {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin_t * sin_t).sqrt();
+
+    let parallel = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+    let perpendicular = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+
+    (parallel * parallel + perpendicular * perpendicular) * 0.5
+}
+
+/// The reflectance of a conductor with complex index of refraction `eta + ik`, per channel,
+/// for light arriving from air at `cos_i`.
+pub fn conductor(cos_i: f64, eta: RGB, k: RGB) -> RGB
+{
+    let channel = |eta: f32, k: f32| conductor_channel(cos_i, eta as f64, k as f64) as f32;
+
+    RGB::new(channel(eta.r, k.r), channel(eta.g, k.g), channel(eta.b, k.b))
+}
+
+fn conductor_channel(cos_i: f64, eta: f64, k: f64) -> f64
+{
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2.0 * a * cos2.sqrt();
+    let perpendicular = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let parallel = perpendicular * (t3 - t4) / (t3 + t4);
+
+    (parallel + perpendicular) * 0.5
+}
@@ -0,0 +1,54 @@
+use crate::{
+    RGB,
+    math::{ Vec3, Rng },
+};
+use super::{ BsdfSample, same_hemisphere };
+
+use std::f64::consts::PI;
+
+/// An ideal diffuse reflector.
+#[derive(Debug, Clone, Copy)]
+pub struct Lambertian
+{
+    pub color: RGB,
+}
+
+impl Lambertian
+{
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> RGB
+    {
+        if !same_hemisphere(wo, wi)
+        {
+            return RGB::black();
+        }
+
+        self.color / PI as f32
+    }
+
+    pub fn sample(&self, wo: Vec3, rng: &mut Rng) -> Option<BsdfSample>
+    {
+        let mut wi = Vec3::random_cosine(rng);
+
+        if wo.z < 0.0
+        {
+            wi.z = -wi.z;
+        }
+
+        Some(BsdfSample {
+            wi,
+            weight: self.color,
+            pdf: wi.z.abs() / PI,
+            specular: false,
+        })
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f64
+    {
+        if !same_hemisphere(wo, wi)
+        {
+            return 0.0;
+        }
+
+        wi.z.abs() / PI
+    }
+}
@@ -0,0 +1,112 @@
+use crate::math::Vec3;
+
+use std::f64::consts::PI;
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals, in the local frame where the
+/// macro surface normal is +z.
+#[derive(Debug, Clone, Copy)]
+pub struct Ggx
+{
+    pub alpha: f64,
+}
+
+impl Ggx
+{
+    /// Perceptually linear roughness in `[0, 1]` is squared into the distribution's alpha.
+    pub fn from_roughness(roughness: f64) -> Ggx
+    {
+        Ggx {
+            alpha: (roughness * roughness).max(1e-4),
+        }
+    }
+
+    /// Surfaces this smooth are treated as perfect mirrors.
+    pub fn is_smooth(&self) -> bool
+    {
+        self.alpha < 1e-3
+    }
+
+    /// Density of microfacets with normal `wm`.
+    pub fn d(&self, wm: Vec3) -> f64
+    {
+        let cos2 = wm.z * wm.z;
+
+        if cos2 <= 0.0
+        {
+            return 0.0;
+        }
+
+        let a2 = self.alpha * self.alpha;
+        let denom = cos2 * (a2 - 1.0) + 1.0;
+
+        a2 / (PI * denom * denom)
+    }
+
+    fn lambda(&self, w: Vec3) -> f64
+    {
+        let cos2 = w.z * w.z;
+
+        if cos2 <= 0.0
+        {
+            return f64::INFINITY;
+        }
+
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) * 0.5
+    }
+
+    /// Smith's masking function, the fraction of microfacets visible from `w`.
+    pub fn g1(&self, w: Vec3) -> f64
+    {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height-correlated masking and shadowing.
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64
+    {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Picks a microfacet normal visible from `wo`, proportionally to its projected area
+    /// (Heitz, "Sampling the GGX Distribution of Visible Normals").
+    pub fn sample_visible(&self, wo: Vec3, u: (f64, f64)) -> Vec3
+    {
+        // Flip to the upper hemisphere and stretch into the configuration with alpha one.
+        let wo = if wo.z < 0.0 { -wo } else { wo };
+        let vh = Vec3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).normalized();
+
+        let len_sq = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if len_sq > 0.0
+        {
+            Vec3::new(-vh.y, vh.x, 0.0) / len_sq.sqrt()
+        }
+        else
+        {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        // A point on the disk, squeezed onto the visible half.
+        let r = u.0.sqrt();
+        let phi = 2.0 * PI * u.1;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+        let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        Vec3::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(1e-6)).normalized()
+    }
+
+    /// Density of `sample_visible` picking `wm` as seen from `wo`.
+    pub fn pdf_visible(&self, wo: Vec3, wm: Vec3) -> f64
+    {
+        if wo.z == 0.0
+        {
+            return 0.0;
+        }
+
+        self.g1(wo) / wo.z.abs() * self.d(wm) * wo.dot(wm).abs()
+    }
+}
@@ -0,0 +1,371 @@
+//! Scattering functions describing how surfaces reflect and transmit light.
+//!
+//! Every BSDF works in a local frame around the surface normal, `+z`, with both directions
+//! pointing away from the surface: `wo` towards the viewer and `wi` towards the light. `Bsdf`
+//! takes and returns world space directions.
+
+mod lambertian;
+mod conductor;
+mod dielectric;
+mod microfacet;
+mod principled;
+pub mod fresnel;
+
+pub use lambertian::Lambertian;
+pub use conductor::{ Conductor, Metal };
+pub use dielectric::Dielectric;
+pub use microfacet::Ggx;
+pub use principled::Principled;
+
+use crate::{
+    RGB,
+    math::{ Vec3, Rng },
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Bsdf
+{
+    Lambertian(Lambertian),
+    Conductor(Conductor),
+    Dielectric(Dielectric),
+    Principled(Principled),
+    /// No surface at all, light passes straight through. Marks the boundary of a volume.
+    Interface,
+}
+
+/// A direction picked by `Bsdf::sample`.
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfSample
+{
+    pub wi: Vec3,
+    /// The BSDF times the cosine of `wi` divided by `pdf`, what the light from `wi` is scaled by.
+    pub weight: RGB,
+    /// Density over solid angle, or the probability of picking a specular lobe.
+    pub pdf: f64,
+    /// Whether `wi` was picked from a perfectly sharp lobe, which `eval` and `pdf` never see.
+    pub specular: bool,
+}
+
+/// A local shading frame with the normal along `+z`.
+struct Frame
+{
+    tangent: Vec3,
+    bitangent: Vec3,
+    normal: Vec3,
+}
+
+impl Frame
+{
+    fn new(normal: Vec3) -> Frame
+    {
+        let (tangent, bitangent) = normal.orthonormal_basis();
+
+        Frame {
+            tangent,
+            bitangent,
+            normal,
+        }
+    }
+
+    fn to_local(&self, v: Vec3) -> Vec3
+    {
+        Vec3::new(v.dot(self.tangent), v.dot(self.bitangent), v.dot(self.normal))
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3
+    {
+        self.tangent * v.x + self.bitangent * v.y + self.normal * v.z
+    }
+}
+
+impl Bsdf
+{
+    /// Whether the BSDF only scatters into sharp lobes, so sampling lights directly is pointless.
+    pub fn is_specular(&self) -> bool
+    {
+        match self
+        {
+            Bsdf::Lambertian(_) => false,
+            Bsdf::Conductor(bsdf) => bsdf.is_specular(),
+            Bsdf::Dielectric(bsdf) => bsdf.is_specular(),
+            Bsdf::Principled(bsdf) => bsdf.is_specular(),
+            Bsdf::Interface => true,
+        }
+    }
+
+    /// Whether light can pass through the surface, making it the boundary of a medium.
+    pub fn is_transmissive(&self) -> bool
+    {
+        match self
+        {
+            Bsdf::Dielectric(_) | Bsdf::Interface => true,
+            Bsdf::Principled(bsdf) => bsdf.is_transmissive(),
+            _ => false,
+        }
+    }
+
+    /// The BSDF for light arriving from `wi` and leaving towards `wo`, around the surface's
+    /// outward `normal`.
+    pub fn eval(&self, wo: Vec3, wi: Vec3, normal: Vec3) -> RGB
+    {
+        let frame = self.frame(wo, normal);
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+
+        match self
+        {
+            Bsdf::Lambertian(bsdf) => bsdf.eval(wo, wi),
+            Bsdf::Conductor(bsdf) => bsdf.eval(wo, wi),
+            Bsdf::Dielectric(bsdf) => bsdf.eval(wo, wi),
+            Bsdf::Principled(bsdf) => bsdf.eval(wo, wi),
+            Bsdf::Interface => RGB::black(),
+        }
+    }
+
+    /// Picks a direction for light leaving towards `wo` to arrive from.
+    pub fn sample(&self, wo: Vec3, normal: Vec3, rng: &mut Rng) -> Option<BsdfSample>
+    {
+        let frame = self.frame(wo, normal);
+        let local = frame.to_local(wo);
+
+        let sample = match self
+        {
+            Bsdf::Lambertian(bsdf) => bsdf.sample(local, rng),
+            Bsdf::Conductor(bsdf) => bsdf.sample(local, rng),
+            Bsdf::Dielectric(bsdf) => bsdf.sample(local, rng),
+            Bsdf::Principled(bsdf) => bsdf.sample(local, rng),
+            Bsdf::Interface => Some(BsdfSample {
+                wi: -local,
+                weight: RGB::gray(1.0),
+                pdf: 1.0,
+                specular: true,
+            }),
+        }?;
+
+        Some(BsdfSample {
+            wi: frame.to_world(sample.wi).normalized(),
+            ..sample
+        })
+    }
+
+    /// The density of `sample` picking `wi`.
+    pub fn pdf(&self, wo: Vec3, wi: Vec3, normal: Vec3) -> f64
+    {
+        let frame = self.frame(wo, normal);
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+
+        match self
+        {
+            Bsdf::Lambertian(bsdf) => bsdf.pdf(wo, wi),
+            Bsdf::Conductor(bsdf) => bsdf.pdf(wo, wi),
+            Bsdf::Dielectric(bsdf) => bsdf.pdf(wo, wi),
+            Bsdf::Principled(bsdf) => bsdf.pdf(wo, wi),
+            Bsdf::Interface => 0.0,
+        }
+    }
+
+    /// What radiance is scaled by when it passes through the surface from `wi` to `wo`, the
+    /// squared ratio of the indices on the side of `wo` and of `wi`. `eval` and `sample` include
+    /// it, light traced from the lights is not scaled and divides it out again.
+    pub fn radiance_scale(&self, wo: Vec3, wi: Vec3, normal: Vec3) -> f32
+    {
+        match self.r_index()
+        {
+            Some(r_index) if (wo.dot(normal) > 0.0) != (wi.dot(normal) > 0.0) => {
+                let eta = if wo.dot(normal) > 0.0 { r_index } else { 1.0 / r_index };
+
+                1.0 / (eta * eta)
+            },
+            _ => 1.0,
+        }
+    }
+
+    /// The index of refraction of the inside of the surface, `None` for opaque surfaces.
+    pub fn r_index(&self) -> Option<f32>
+    {
+        match self
+        {
+            Bsdf::Dielectric(bsdf) => Some(bsdf.r_index),
+            Bsdf::Principled(bsdf) if bsdf.is_transmissive() => Some(bsdf.r_index),
+            _ => None,
+        }
+    }
+
+    /// The BSDF with its index of refraction made relative to a medium of index `r_index`
+    /// outside the surface.
+    pub fn relative_to(self, r_index: f32) -> Bsdf
+    {
+        match self
+        {
+            Bsdf::Dielectric(bsdf) => Bsdf::Dielectric(Dielectric {
+                r_index: bsdf.r_index / r_index,
+                ..bsdf
+            }),
+            Bsdf::Principled(bsdf) => Bsdf::Principled(Principled {
+                r_index: bsdf.r_index / r_index,
+                ..bsdf
+            }),
+            bsdf => bsdf,
+        }
+    }
+
+    /// The BSDF with its color replaced by `color`, as given by a texture. Conductors take it
+    /// as the color they reflect head-on.
+    pub fn with_color(self, color: RGB) -> Bsdf
+    {
+        match self
+        {
+            Bsdf::Lambertian(_) => Bsdf::Lambertian(Lambertian { color }),
+            Bsdf::Conductor(bsdf) => Bsdf::Conductor(Conductor::from_color(color, bsdf.roughness)),
+            Bsdf::Dielectric(bsdf) => Bsdf::Dielectric(Dielectric { color, ..bsdf }),
+            Bsdf::Principled(bsdf) => Bsdf::Principled(Principled { base_color: color, ..bsdf }),
+            Bsdf::Interface => Bsdf::Interface,
+        }
+    }
+
+    /// The BSDF with its roughness replaced, which diffuse surfaces and interfaces do not have.
+    pub fn with_roughness(self, roughness: f32) -> Bsdf
+    {
+        match self
+        {
+            Bsdf::Lambertian(bsdf) => Bsdf::Lambertian(bsdf),
+            Bsdf::Conductor(bsdf) => Bsdf::Conductor(Conductor { roughness, ..bsdf }),
+            Bsdf::Dielectric(bsdf) => Bsdf::Dielectric(Dielectric { roughness, ..bsdf }),
+            Bsdf::Principled(bsdf) => Bsdf::Principled(Principled { roughness, ..bsdf }),
+            Bsdf::Interface => Bsdf::Interface,
+        }
+    }
+
+    /// Opaque surfaces are two-sided, shading whichever side `wo` is on.
+    fn frame(&self, wo: Vec3, normal: Vec3) -> Frame
+    {
+        if !self.is_transmissive() && wo.dot(normal) < 0.0
+        {
+            Frame::new(-normal)
+        }
+        else
+        {
+            Frame::new(normal)
+        }
+    }
+}
+
+fn same_hemisphere(a: Vec3, b: Vec3) -> bool
+{
+    a.z * b.z > 0.0
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use rand::SeedableRng;
+
+    use std::f64::consts::PI;
+
+    /// BSDFs without perfectly sharp lobes, whose samples `eval` and `pdf` must agree with.
+    fn rough_bsdfs() -> Vec<Bsdf>
+    {
+        vec![
+            Bsdf::Lambertian(Lambertian { color: RGB::gray(0.8) }),
+            Bsdf::Conductor(Conductor::from_color(RGB::new(0.9, 0.6, 0.3), 0.3)),
+            Bsdf::Dielectric(Dielectric::new(1.5, 0.3)),
+            Bsdf::Principled(Principled { clearcoat: 1.0, clearcoat_roughness: 0.2, sheen: 0.5, ..Principled::default() }),
+            Bsdf::Principled(Principled { metallic: 0.5, roughness: 0.2, ..Principled::default() }),
+            Bsdf::Principled(Principled { transmission: 0.7, roughness: 0.4, ..Principled::default() }),
+        ]
+    }
+
+    /// Directions towards the viewer, from head-on to grazing and from inside.
+    fn views() -> Vec<Vec3>
+    {
+        vec![
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.5, 0.0, 0.8).normalized(),
+            Vec3::new(0.95, 0.1, 0.1).normalized(),
+            Vec3::new(0.3, -0.4, -0.6).normalized(),
+        ]
+    }
+
+    fn close(a: f64, b: f64) -> bool
+    {
+        (a - b).abs() <= 1e-3 * a.abs().max(b.abs()).max(1e-3)
+    }
+
+    #[test]
+    fn samples_agree_with_eval_and_pdf()
+    {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let mut rng = Rng::seed_from_u64(4);
+
+        for bsdf in rough_bsdfs()
+        {
+            for wo in views()
+            {
+                for _ in 0..1000
+                {
+                    let sample = match bsdf.sample(wo, normal, &mut rng)
+                    {
+                        Some(sample) => sample,
+                        None => continue,
+                    };
+
+                    assert!(!sample.specular, "{:?} picked a sharp lobe", bsdf);
+
+                    let pdf = bsdf.pdf(wo, sample.wi, normal);
+                    let weight = bsdf.eval(wo, sample.wi, normal) * (sample.wi.dot(normal).abs() / sample.pdf) as f32;
+
+                    assert!(close(sample.pdf, pdf), "{:?}: sampled pdf {} against {}", bsdf, sample.pdf, pdf);
+
+                    for (a, b) in [(weight.r, sample.weight.r), (weight.g, sample.weight.g), (weight.b, sample.weight.b)].iter()
+                    {
+                        assert!(close(*a as f64, *b as f64), "{:?}: weight {:?} against {:?}", bsdf, sample.weight, weight);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pdfs_are_normalized_and_energy_is_conserved()
+    {
+        const SAMPLES: usize = 20_000;
+        // Midpoint rule over the cosine of the polar angle and the azimuth, around the y axis
+        // so no lobe lies at a pole.
+        const GRID: (usize, usize) = (300, 600);
+
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let mut rng = Rng::seed_from_u64(5);
+
+        for bsdf in rough_bsdfs()
+        {
+            for wo in views().into_iter().filter(|wo| wo.z > 0.2)
+            {
+                let mut pdf = 0.0;
+
+                for i in 0..GRID.0
+                {
+                    let cos_theta = -1.0 + 2.0 * (i as f64 + 0.5) / GRID.0 as f64;
+                    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+                    for j in 0..GRID.1
+                    {
+                        let phi = 2.0 * PI * (j as f64 + 0.5) / GRID.1 as f64;
+                        let wi = Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+
+                        pdf += bsdf.pdf(wo, wi, normal) * 4.0 * PI / (GRID.0 * GRID.1) as f64;
+                    }
+                }
+
+                let albedo = (0..SAMPLES)
+                    .filter_map(|_| bsdf.sample(wo, normal, &mut rng))
+                    .map(|sample| sample.weight.luminance() as f64)
+                    .sum::<f64>() / SAMPLES as f64;
+
+                // Sampling fails for directions below the surface, so rough lobes lose some.
+                assert!(pdf > 0.8 && pdf < 1.02, "{:?} towards {:?}: pdf integrates to {}", bsdf, wo, pdf);
+                assert!(albedo < 1.02, "{:?} towards {:?}: reflects {}", bsdf, wo, albedo);
+            }
+        }
+    }
+}
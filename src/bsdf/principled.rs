@@ -0,0 +1,326 @@
+use crate::{
+    RGB,
+    math::{ Vec3, Rng },
+};
+use super::{ BsdfSample, Dielectric, microfacet::Ggx };
+
+use rand::Rng as _;
+
+use std::f64::consts::PI;
+
+/// The parameter set of Disney's principled BRDF and the materials modelled after it, as
+/// artists know it from other tools.
+///
+/// Every parameter but `base_color` and `r_index` goes from 0 to 1. The surface is a mix of a
+/// diffuse base with sheen, a specular layer, a clear coat on top and, for `transmission`, a
+/// rough glass interface. Metals replace everything but the coat with a specular layer tinted
+/// by the base color.
+#[derive(Debug, Clone, Copy)]
+pub struct Principled
+{
+    pub base_color: RGB,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Strength of the specular reflection of non-metals, 0.5 is a reflectance of 4%.
+    pub specular: f32,
+    /// Tints the specular reflection of non-metals towards the base color.
+    pub specular_tint: f32,
+    /// Soft velvet-like reflection at grazing angles, for cloth.
+    pub sheen: f32,
+    pub sheen_tint: f32,
+    /// A white, glossy layer on top, like the varnish on car paint.
+    pub clearcoat: f32,
+    pub clearcoat_roughness: f32,
+    /// How much of the non-metallic part lets light through instead of scattering it diffusely.
+    pub transmission: f32,
+    /// Index of refraction of the transmissive part.
+    pub r_index: f32,
+}
+
+impl Default for Principled
+{
+    fn default() -> Principled
+    {
+        Principled {
+            base_color: RGB::gray(0.8),
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            transmission: 0.0,
+            r_index: 1.5,
+        }
+    }
+}
+
+/// Selection probabilities of the lobes, in the order diffuse, specular, clear coat, glass.
+type LobeWeights = [f64; 4];
+
+impl Principled
+{
+    fn specular_ggx(&self) -> Ggx
+    {
+        Ggx::from_roughness(self.roughness as f64)
+    }
+
+    fn clearcoat_ggx(&self) -> Ggx
+    {
+        Ggx::from_roughness(self.clearcoat_roughness as f64)
+    }
+
+    fn glass(&self) -> Dielectric
+    {
+        Dielectric {
+            r_index: self.r_index,
+            roughness: self.roughness,
+            color: self.base_color,
+        }
+    }
+
+    /// How much of the surface is diffuse, specular and glass.
+    fn mix(&self) -> (f32, f32, f32)
+    {
+        let glass = (1.0 - self.metallic) * self.transmission;
+
+        ((1.0 - self.metallic) * (1.0 - self.transmission), 1.0 - glass, glass)
+    }
+
+    /// Head-on reflectance of the specular layer.
+    fn specular_f0(&self) -> RGB
+    {
+        let tint = mix_rgb(RGB::gray(1.0), normalized_hue(self.base_color), self.specular_tint);
+        let dielectric = tint * (0.08 * self.specular);
+
+        mix_rgb(dielectric, self.base_color, self.metallic)
+    }
+
+    fn lobe_weights(&self, wo: Vec3) -> LobeWeights
+    {
+        // Light that arrives from inside the object only ever meets the glass interface.
+        if wo.z < 0.0
+        {
+            return [0.0, 0.0, 0.0, 1.0];
+        }
+
+        let (diffuse, specular, glass) = self.mix();
+        let fresnel = schlick(self.specular_f0(), wo.z).luminance() as f64;
+
+        let weights = [
+            diffuse as f64 * (self.base_color.luminance() as f64 + self.sheen as f64 * 0.25) * (1.0 - fresnel),
+            specular as f64 * fresnel.max(0.05),
+            self.clearcoat as f64 * schlick(RGB::gray(0.04), wo.z).r as f64,
+            glass as f64,
+        ];
+
+        let sum: f64 = weights.iter().sum();
+
+        if sum <= 0.0
+        {
+            return [1.0, 0.0, 0.0, 0.0];
+        }
+
+        [weights[0] / sum, weights[1] / sum, weights[2] / sum, weights[3] / sum]
+    }
+
+    pub fn is_transmissive(&self) -> bool
+    {
+        self.mix().2 > 0.0
+    }
+
+    pub fn is_specular(&self) -> bool
+    {
+        let (diffuse, _, glass) = self.mix();
+
+        diffuse <= 0.0
+            && self.specular_ggx().is_smooth()
+            && (self.clearcoat <= 0.0 || self.clearcoat_ggx().is_smooth())
+            && (glass <= 0.0 || self.glass().is_specular())
+    }
+
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> RGB
+    {
+        let (diffuse, specular, glass) = self.mix();
+
+        if wo.z < 0.0
+        {
+            return self.glass().eval(wo, wi);
+        }
+
+        let mut f = self.glass().eval(wo, wi) * glass;
+
+        if wi.z <= 0.0
+        {
+            return f;
+        }
+
+        let wm = (wo + wi).normalized();
+        let cos_d = wi.dot(wm).clamp(0.0, 1.0);
+
+        if diffuse > 0.0
+        {
+            // Disney's diffuse, darkening smooth and brightening rough surfaces at grazing angles.
+            let fd90 = 0.5 + 2.0 * self.roughness as f64 * cos_d * cos_d;
+            let fd = (1.0 + (fd90 - 1.0) * (1.0 - wi.z).powi(5)) * (1.0 + (fd90 - 1.0) * (1.0 - wo.z).powi(5));
+
+            let sheen_color = mix_rgb(RGB::gray(1.0), normalized_hue(self.base_color), self.sheen_tint);
+            let sheen = sheen_color * (self.sheen * (1.0 - cos_d).powi(5) as f32);
+
+            f += (self.base_color * (fd / PI) as f32 + sheen) * diffuse;
+        }
+
+        f += microfacet_reflection(self.specular_ggx(), wo, wi, schlick(self.specular_f0(), cos_d)) * specular;
+
+        if self.clearcoat > 0.0
+        {
+            f += microfacet_reflection(self.clearcoat_ggx(), wo, wi, schlick(RGB::gray(0.04), cos_d)) * self.clearcoat;
+        }
+
+        f
+    }
+
+    pub fn sample(&self, wo: Vec3, rng: &mut Rng) -> Option<BsdfSample>
+    {
+        let weights = self.lobe_weights(wo);
+
+        let u = rng.gen::<f64>();
+        let lobe = if u < weights[0]
+        {
+            0
+        }
+        else if u < weights[0] + weights[1]
+        {
+            1
+        }
+        else if u < weights[0] + weights[1] + weights[2]
+        {
+            2
+        }
+        else
+        {
+            3
+        };
+
+        let (diffuse, specular, glass) = self.mix();
+
+        let wi = match lobe
+        {
+            0 => Vec3::random_cosine(rng),
+            1 | 2 => {
+                let (ggx, f0, scale) = if lobe == 1
+                {
+                    (self.specular_ggx(), self.specular_f0(), specular)
+                }
+                else
+                {
+                    (self.clearcoat_ggx(), RGB::gray(0.04), self.clearcoat)
+                };
+
+                if ggx.is_smooth()
+                {
+                    return Some(BsdfSample {
+                        wi: Vec3::new(-wo.x, -wo.y, wo.z),
+                        weight: schlick(f0, wo.z) * (scale as f64 / weights[lobe]) as f32,
+                        pdf: weights[lobe],
+                        specular: true,
+                    });
+                }
+
+                let wm = ggx.sample_visible(wo, (rng.gen(), rng.gen()));
+
+                -wo + wm * (2.0 * wo.dot(wm))
+            },
+            _ => {
+                let sample = self.glass().sample(wo, rng)?;
+
+                if sample.specular
+                {
+                    let scale = if wo.z < 0.0 { 1.0 } else { glass as f64 };
+
+                    return Some(BsdfSample {
+                        weight: sample.weight * (scale / weights[3]) as f32,
+                        pdf: sample.pdf * weights[3],
+                        ..sample
+                    });
+                }
+
+                sample.wi
+            },
+        };
+
+        // Every lobe could have picked `wi`, so weigh it by the whole mixture.
+        let pdf = self.pdf(wo, wi);
+
+        if pdf <= 0.0 || (diffuse <= 0.0 && lobe == 0)
+        {
+            return None;
+        }
+
+        Some(BsdfSample {
+            wi,
+            weight: self.eval(wo, wi) * (wi.z.abs() / pdf) as f32,
+            pdf,
+            specular: false,
+        })
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f64
+    {
+        let weights = self.lobe_weights(wo);
+        let mut pdf = weights[3] * self.glass().pdf(wo, wi);
+
+        if wo.z <= 0.0 || wi.z <= 0.0
+        {
+            return pdf;
+        }
+
+        let wm = (wo + wi).normalized();
+        let reflection_pdf = |ggx: Ggx| {
+            if ggx.is_smooth() { 0.0 } else { ggx.pdf_visible(wo, wm) / (4.0 * wo.dot(wm).abs()) }
+        };
+
+        pdf += weights[0] * wi.z / PI;
+        pdf += weights[1] * reflection_pdf(self.specular_ggx());
+        pdf += weights[2] * reflection_pdf(self.clearcoat_ggx());
+
+        pdf
+    }
+}
+
+/// GGX reflection off a microfacet layer with Fresnel reflectance `f`, zero for smooth layers
+/// which are only ever sampled.
+fn microfacet_reflection(ggx: Ggx, wo: Vec3, wi: Vec3, f: RGB) -> RGB
+{
+    if ggx.is_smooth()
+    {
+        return RGB::black();
+    }
+
+    let wm = (wo + wi).normalized();
+
+    f * (ggx.d(wm) * ggx.g(wo, wi) / (4.0 * wo.z * wi.z)) as f32
+}
+
+/// Schlick's approximation of the Fresnel reflectance.
+fn schlick(f0: RGB, cos: f64) -> RGB
+{
+    let t = (1.0 - cos.clamp(0.0, 1.0)).powi(5) as f32;
+
+    f0 + (RGB::gray(1.0) - f0) * t
+}
+
+fn mix_rgb(a: RGB, b: RGB, t: f32) -> RGB
+{
+    a * (1.0 - t) + b * t
+}
+
+/// The hue and saturation of `color` at a luminance of one.
+fn normalized_hue(color: RGB) -> RGB
+{
+    let luminance = color.luminance();
+
+    if luminance > 0.0 { color / luminance } else { RGB::gray(1.0) }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    Ray,
+    math::Vec3,
+};
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb
+{
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb
+{
+    pub fn new(min: Vec3, max: Vec3) -> Aabb
+    {
+        Aabb {
+            min,
+            max,
+        }
+    }
+
+    /// A box containing nothing, the identity for `union`.
+    pub fn empty() -> Aabb
+    {
+        Aabb {
+            min: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points(points: &[Vec3]) -> Aabb
+    {
+        points.iter().fold(Aabb::empty(), |aabb, &point| aabb.grow(point))
+    }
+
+    pub fn union(&self, other: Aabb) -> Aabb
+    {
+        Aabb {
+            min: Vec3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            max: Vec3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        }
+    }
+
+    pub fn grow(&self, point: Vec3) -> Aabb
+    {
+        self.union(Aabb::new(point, point))
+    }
+
+    pub fn center(&self) -> Vec3
+    {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extent(&self) -> Vec3
+    {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f64
+    {
+        let e = self.extent();
+
+        if e.x < 0.0 || e.y < 0.0 || e.z < 0.0
+        {
+            0.0
+        }
+        else
+        {
+            2.0 * (e.x * e.y + e.y * e.z + e.z * e.x)
+        }
+    }
+
+    /// Index of the axis the box is longest along, 0 for x, 1 for y and 2 for z.
+    pub fn longest_axis(&self) -> usize
+    {
+        let e = self.extent();
+
+        if e.x >= e.y && e.x >= e.z
+        {
+            0
+        }
+        else if e.y >= e.z
+        {
+            1
+        }
+        else
+        {
+            2
+        }
+    }
+
+    /// Slab test against a ray, `inv_dir` is the component-wise reciprocal of `ray.dir`.
+    pub fn hit(&self, ray: &Ray, inv_dir: Vec3, ray_range: (f64, f64)) -> bool
+    {
+        let (mut t_min, mut t_max) = ray_range;
+
+        for axis in 0..3
+        {
+            let t0 = (self.min[axis] - ray.origin[axis]) * inv_dir[axis];
+            let t1 = (self.max[axis] - ray.origin[axis]) * inv_dir[axis];
+            let (t0, t1) = if inv_dir[axis] < 0.0 { (t1, t0) } else { (t0, t1) };
+
+            // Written so that NaNs from 0 * inf leave the range untouched.
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+
+            if t_max < t_min
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
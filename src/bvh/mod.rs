@@ -0,0 +1,347 @@
+//! Bounding volume hierarchy over anything that can report an `Aabb`.
+//!
+//! The tree is built with the surface area heuristic over binned centroids and stored
+//! flattened in depth-first order, the first child of an interior node directly follows it.
+
+mod aabb;
+
+pub use aabb::Aabb;
+
+use crate::{
+    Ray,
+    HitRecord,
+    math::Vec3,
+};
+
+const BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+/// Number of interior nodes on the way down to any leaf at most. Deeper nodes become leaves
+/// however many primitives they hold, so the traversal stack cannot overflow.
+const MAX_DEPTH: usize = 64;
+/// Cost of traversing one node relative to intersecting one primitive.
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Debug, Clone, Default)]
+pub struct Bvh
+{
+    nodes: Vec<Node>,
+    /// Primitive indices, leaves reference contiguous ranges of this.
+    indices: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node
+{
+    bounds: Aabb,
+    /// Leaves: index of the first primitive in `indices`.
+    /// Interior nodes: index of the second child.
+    offset: usize,
+    /// Number of primitives, 0 for interior nodes.
+    count: usize,
+    /// The axis interior nodes were split along.
+    axis: usize,
+}
+
+struct BuildItem
+{
+    index: usize,
+    bounds: Aabb,
+    center: Vec3,
+}
+
+impl Bvh
+{
+    /// Builds a hierarchy over primitives with the given bounds, primitive `i` is `bounds[i]`.
+    pub fn build(bounds: &[Aabb]) -> Bvh
+    {
+        let mut items: Vec<BuildItem> = bounds
+            .iter()
+            .enumerate()
+            .map(|(index, &bounds)| BuildItem {
+                index,
+                bounds,
+                center: bounds.center(),
+            })
+            .collect();
+
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(bounds.len() * 2),
+            indices: Vec::with_capacity(bounds.len()),
+        };
+
+        if !items.is_empty()
+        {
+            bvh.build_node(&mut items, 0);
+        }
+
+        bvh
+    }
+
+    /// The bounds of everything in the hierarchy.
+    pub fn bounds(&self) -> Aabb
+    {
+        self.nodes.first().map(|node| node.bounds).unwrap_or_else(Aabb::empty)
+    }
+
+    fn build_node(&mut self, items: &mut [BuildItem], depth: usize) -> usize
+    {
+        let bounds = items.iter().fold(Aabb::empty(), |aabb, item| aabb.union(item.bounds));
+        let node_index = self.nodes.len();
+
+        self.nodes.push(Node {
+            bounds,
+            offset: 0,
+            count: 0,
+            axis: 0,
+        });
+
+        let split = if items.len() > 1 && depth < MAX_DEPTH { find_split(items, bounds) } else { None };
+
+        match split
+        {
+            Some((axis, mid)) => {
+                let (left, right) = items.split_at_mut(mid);
+
+                self.build_node(left, depth + 1);
+                let second = self.build_node(right, depth + 1);
+
+                self.nodes[node_index].offset = second;
+                self.nodes[node_index].axis = axis;
+            },
+            None => {
+                self.nodes[node_index].offset = self.indices.len();
+                self.nodes[node_index].count = items.len();
+
+                self.indices.extend(items.iter().map(|item| item.index));
+            },
+        }
+
+        node_index
+    }
+
+    /// Finds the closest hit along `ray`.
+    ///
+    /// `hit` is called with primitive indices whose bounds the ray passes through and the
+    /// range still worth searching, which shrinks as closer hits are found.
+    pub fn hit<'a, F>(&self, ray: Ray, ray_range: (f64, f64), mut hit: F) -> Option<HitRecord<'a>>
+    where F: FnMut(usize, Ray, (f64, f64)) -> Option<HitRecord<'a>>
+    {
+        let mut closest = None;
+        let mut range = ray_range;
+
+        self.traverse(ray, |index, current_range| {
+            range.1 = range.1.min(current_range.1);
+
+            if let Some(record) = hit(index, ray, range)
+            {
+                range.1 = record.offset;
+                closest = Some(record);
+            }
+
+            (range.1, false)
+        }, ray_range);
+
+        closest
+    }
+
+    /// Whether anything along `ray` within `ray_range` is hit, stopping at the first hit found.
+    pub fn any_hit<F>(&self, ray: Ray, ray_range: (f64, f64), mut hit: F) -> bool
+    where F: FnMut(usize, Ray, (f64, f64)) -> bool
+    {
+        let mut found = false;
+
+        self.traverse(ray, |index, range| {
+            found = hit(index, ray, range);
+
+            (range.1, found)
+        }, ray_range);
+
+        found
+    }
+
+    /// Walks the nodes along `ray`, nearest child first. `visit` returns the new far end of the
+    /// range and whether to stop.
+    fn traverse<F>(&self, ray: Ray, mut visit: F, ray_range: (f64, f64))
+    where F: FnMut(usize, (f64, f64)) -> (f64, bool)
+    {
+        if self.nodes.is_empty()
+        {
+            return;
+        }
+
+        let inv_dir = Vec3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+        let dir_negative = [ray.dir.x < 0.0, ray.dir.y < 0.0, ray.dir.z < 0.0];
+
+        let mut range = ray_range;
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut current = 0;
+
+        loop
+        {
+            let node = &self.nodes[current];
+
+            if node.bounds.hit(&ray, inv_dir, range)
+            {
+                if node.count > 0
+                {
+                    for &index in &self.indices[node.offset..node.offset + node.count]
+                    {
+                        let (far, stop) = visit(index, range);
+
+                        if stop
+                        {
+                            return;
+                        }
+
+                        range.1 = far;
+                    }
+                }
+                else
+                {
+                    // Visit the child on the side the ray comes from first.
+                    if dir_negative[node.axis]
+                    {
+                        stack[stack_len] = current + 1;
+                        current = node.offset;
+                    }
+                    else
+                    {
+                        stack[stack_len] = node.offset;
+                        current += 1;
+                    }
+
+                    stack_len += 1;
+                    continue;
+                }
+            }
+
+            if stack_len == 0
+            {
+                return;
+            }
+
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+    }
+}
+
+/// Picks the cheapest split by the surface area heuristic, partitioning `items` so that the
+/// first `mid` go to the left child. `None` means a leaf is cheaper.
+fn find_split(items: &mut [BuildItem], bounds: Aabb) -> Option<(usize, usize)>
+{
+    let centroid_bounds = items.iter().fold(Aabb::empty(), |aabb, item| aabb.grow(item.center));
+    let axis = centroid_bounds.longest_axis();
+    let axis_min = centroid_bounds.min[axis];
+    let axis_extent = centroid_bounds.extent()[axis];
+
+    if axis_extent <= f64::EPSILON
+    {
+        // All centroids coincide, split in the middle if the leaf would be too big.
+        return if items.len() > MAX_LEAF_SIZE { Some((axis, items.len() / 2)) } else { None };
+    }
+
+    let bin_of = |center: Vec3| {
+        (((center[axis] - axis_min) / axis_extent * BINS as f64) as usize).min(BINS - 1)
+    };
+
+    let mut bin_bounds = [Aabb::empty(); BINS];
+    let mut bin_counts = [0; BINS];
+
+    for item in items.iter()
+    {
+        let bin = bin_of(item.center);
+
+        bin_bounds[bin] = bin_bounds[bin].union(item.bounds);
+        bin_counts[bin] += 1;
+    }
+
+    // Cost of splitting after each bin, sweeping from both sides.
+    let mut costs = [0.0; BINS - 1];
+
+    let mut left_bounds = Aabb::empty();
+    let mut left_count = 0;
+
+    for i in 0..BINS - 1
+    {
+        left_bounds = left_bounds.union(bin_bounds[i]);
+        left_count += bin_counts[i];
+        costs[i] = left_bounds.surface_area() * left_count as f64;
+    }
+
+    let mut right_bounds = Aabb::empty();
+    let mut right_count = 0;
+
+    for i in (1..BINS).rev()
+    {
+        right_bounds = right_bounds.union(bin_bounds[i]);
+        right_count += bin_counts[i];
+        costs[i - 1] += right_bounds.surface_area() * right_count as f64;
+    }
+
+    let (best_bin, best_cost) = costs
+        .iter()
+        .enumerate()
+        .fold((0, f64::INFINITY), |best, (i, &cost)| if cost < best.1 { (i, cost) } else { best });
+
+    let split_cost = TRAVERSAL_COST + best_cost / bounds.surface_area().max(f64::EPSILON);
+    let leaf_cost = items.len() as f64;
+
+    if items.len() <= MAX_LEAF_SIZE && split_cost >= leaf_cost
+    {
+        return None;
+    }
+
+    let mut mid = 0;
+
+    for i in 0..items.len()
+    {
+        if bin_of(items[i].center) <= best_bin
+        {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+
+    if mid == 0 || mid == items.len()
+    {
+        mid = items.len() / 2;
+    }
+
+    Some((axis, mid))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn deep_trees_fit_the_traversal_stack()
+    {
+        // Every box is twice the size of the one before, which the surface area heuristic
+        // peels off one at a time, into a tree far deeper than its size suggests.
+        let bounds: Vec<Aabb> = (0..300)
+            .map(|i| {
+                let corner = 2f64.powi(i);
+
+                Aabb::new(Vec3::new(corner, corner, corner), Vec3::new(corner, corner, corner) * 1.1)
+            })
+            .collect();
+
+        let bvh = Bvh::build(&bounds);
+
+        // Along the diagonal from the small boxes up, so the larger sibling of every node on
+        // the way down waits on the stack.
+        let ray = Ray::new(Vec3::zero(), Vec3::new(1.0, 1.0, 1.0).normalized());
+        let mut visited = vec![false; bounds.len()];
+
+        bvh.any_hit(ray, (0.0, f64::INFINITY), |index, _, _| {
+            visited[index] = true;
+            false
+        });
+
+        assert!(visited.iter().all(|&visited| visited));
+    }
+}
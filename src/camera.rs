@@ -2,9 +2,15 @@ use crate::math::{
     Vec3,
     Mat3,
     Quaternion,
+    Rng,
 };
 use crate::Ray;
 
+use rand::Rng as _;
+
+use std::f64::consts::PI;
+use std::fmt;
+
 #[derive(Debug)]
 pub struct Camera
 {
@@ -14,6 +20,192 @@ pub struct Camera
     pub width: usize,
     pub height: usize,
     pub aspect: f64,
+    pub lens: Lens,
+    pub projection: Projection,
+}
+
+/// How directions around the camera are mapped onto the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection
+{
+    /// A regular pinhole projection with the camera's field of view.
+    Perspective,
+    /// Parallel rays, `height` is the height of the view in scene units.
+    Orthographic { height: f64 },
+    /// A round image of the hemisphere in front of the camera, or more. `fov` is the angle
+    /// across the image circle in radians, the circle fits the shorter side of the image.
+    Fisheye { fov: f64, mapping: FisheyeMapping },
+    /// Longitude along the image width and latitude along its height, covering every direction.
+    /// Usually rendered at a 2:1 aspect ratio.
+    Equirectangular,
+    /// The six faces of a cube around the camera in a 3×2 grid, right, left and up on top
+    /// and down, forward and back below. Usually rendered at a 3:2 aspect ratio.
+    Cubemap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FisheyeMapping
+{
+    /// Distance from the image center is proportional to the angle off the view direction.
+    Equidistant,
+    /// Equal areas on the image cover equal solid angles.
+    Equisolid,
+}
+
+impl fmt::Display for Projection
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Projection::Perspective => write!(f, "perspective"),
+            Projection::Orthographic { height } => write!(f, "orthographic, height {}", height),
+            Projection::Fisheye { fov, mapping } => {
+                let mapping = match mapping
+                {
+                    FisheyeMapping::Equidistant => "equidistant",
+                    FisheyeMapping::Equisolid => "equisolid",
+                };
+
+                write!(f, "fisheye, {} over {:.1} degrees", mapping, fov.to_degrees())
+            },
+            Projection::Equirectangular => write!(f, "equirectangular"),
+            Projection::Cubemap => write!(f, "cubemap"),
+        }
+    }
+}
+
+/// Forward, right and up in camera space for every cubemap face, in image order.
+const CUBE_FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3 { x:  1.0, y:  0.0, z:  0.0 }, Vec3 { x:  0.0, y: 0.0, z: -1.0 }, Vec3 { x: 0.0, y: 1.0, z:  0.0 }),
+    (Vec3 { x: -1.0, y:  0.0, z:  0.0 }, Vec3 { x:  0.0, y: 0.0, z:  1.0 }, Vec3 { x: 0.0, y: 1.0, z:  0.0 }),
+    (Vec3 { x:  0.0, y:  1.0, z:  0.0 }, Vec3 { x:  1.0, y: 0.0, z:  0.0 }, Vec3 { x: 0.0, y: 0.0, z: -1.0 }),
+    (Vec3 { x:  0.0, y: -1.0, z:  0.0 }, Vec3 { x:  1.0, y: 0.0, z:  0.0 }, Vec3 { x: 0.0, y: 0.0, z:  1.0 }),
+    (Vec3 { x:  0.0, y:  0.0, z:  1.0 }, Vec3 { x:  1.0, y: 0.0, z:  0.0 }, Vec3 { x: 0.0, y: 1.0, z:  0.0 }),
+    (Vec3 { x:  0.0, y:  0.0, z: -1.0 }, Vec3 { x: -1.0, y: 0.0, z:  0.0 }, Vec3 { x: 0.0, y: 1.0, z:  0.0 }),
+];
+
+/// A thin lens in front of the film, the camera is a pinhole while `aperture` is zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lens
+{
+    /// Radius of the aperture, in scene units.
+    pub aperture: f64,
+    /// Distance along the view direction to the plane that is in focus.
+    pub focus_distance: f64,
+    /// Number of aperture blades, giving polygonal bokeh. Fewer than three is a circular aperture.
+    pub blades: u32,
+    /// Rotation of the aperture polygon in radians.
+    pub blade_rotation: f64,
+    /// Whether `Scene::new` focuses on whatever is in the center of the image.
+    pub autofocus: bool,
+}
+
+impl Lens
+{
+    pub fn pinhole() -> Lens
+    {
+        Lens {
+            aperture: 0.0,
+            focus_distance: 1.0,
+            blades: 0,
+            blade_rotation: 0.0,
+            autofocus: false,
+        }
+    }
+
+    /// A uniformly distributed point on the aperture, scaled to a radius of one.
+    pub fn sample_aperture(&self, rng: &mut Rng) -> (f64, f64)
+    {
+        if self.blades < 3
+        {
+            return concentric_disk(rng.gen(), rng.gen());
+        }
+
+        // Pick one of the triangles between the center and two neighbouring corners,
+        // then a uniform point within it.
+        let wedge = 2.0 * PI / self.blades as f64;
+        let blade = rng.gen_range(0, self.blades) as f64;
+
+        let a0 = self.blade_rotation + blade * wedge;
+        let a1 = a0 + wedge;
+
+        let (mut u, mut v) = (rng.gen::<f64>(), rng.gen::<f64>());
+
+        if u + v > 1.0
+        {
+            u = 1.0 - u;
+            v = 1.0 - v;
+        }
+
+        (
+            u * a0.cos() + v * a1.cos(),
+            u * a0.sin() + v * a1.sin(),
+        )
+    }
+
+    /// The area of the aperture `sample_aperture` picks points on, in scene units.
+    pub fn area(&self) -> f64
+    {
+        let r2 = self.aperture * self.aperture;
+
+        if self.blades < 3
+        {
+            PI * r2
+        }
+        else
+        {
+            0.5 * self.blades as f64 * r2 * (2.0 * PI / self.blades as f64).sin()
+        }
+    }
+}
+
+/// A connection from a point in the scene to the camera, picked by `Camera::sample_importance`.
+#[derive(Debug, Clone, Copy)]
+pub struct ImportanceSample
+{
+    /// Where on the film the point shows up, in pixels from the top left corner.
+    pub x: f64,
+    pub y: f64,
+    /// The point on the lens the connection ends at.
+    pub lens_point: Vec3,
+    /// Unit vector from the point towards `lens_point`.
+    pub direction: Vec3,
+    pub distance: f64,
+    /// The camera's sensitivity to light arriving along the connection.
+    pub importance: f64,
+    /// Density over solid angle, as seen from the point, of picking `lens_point`.
+    pub pdf: f64,
+}
+
+impl Default for Lens
+{
+    fn default() -> Lens
+    {
+        Lens::pinhole()
+    }
+}
+
+/// Shirley and Chiu's area preserving mapping from the unit square to the unit disk.
+fn concentric_disk(u: f64, v: f64) -> (f64, f64)
+{
+    let (x, y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+
+    if x == 0.0 && y == 0.0
+    {
+        return (0.0, 0.0);
+    }
+
+    let (r, theta) = if x.abs() > y.abs()
+    {
+        (x, PI / 4.0 * (y / x))
+    }
+    else
+    {
+        (y, PI / 2.0 - PI / 4.0 * (x / y))
+    };
+
+    (r * theta.cos(), r * theta.sin())
 }
 
 impl Camera
@@ -26,7 +218,9 @@ impl Camera
             tan_half_fov: (fov.to_radians() * 0.5).tan(),
             width,
             height,
-            aspect: width as f64 / height as f64
+            aspect: width as f64 / height as f64,
+            lens: Lens::pinhole(),
+            projection: Projection::Perspective,
         }
     }
 
@@ -45,9 +239,27 @@ impl Camera
             width,
             height,
             aspect: width as f64 / height as f64,
+            lens: Lens::pinhole(),
+            projection: Projection::Perspective,
         }
     }
 
+    pub fn with_lens(self, lens: Lens) -> Camera
+    {
+        Camera { lens, ..self }
+    }
+
+    pub fn with_projection(self, projection: Projection) -> Camera
+    {
+        Camera { projection, ..self }
+    }
+
+    /// The direction the camera looks in.
+    pub fn forward(&self) -> Vec3
+    {
+        self.rot * Vec3::new(0.0, 0.0, 1.0)
+    }
+
     pub fn width(&self) -> usize
     {
         self.width
@@ -65,56 +277,222 @@ impl Camera
         self.aspect = w_h.0 as f64 / w_h.1 as f64;
     }
 
-    pub fn rays(&self) -> Vec<Ray>
+    /// The ray through a point on the film, in pixels from the top left corner of the image.
+    /// Pixel `(x, y)` covers `[x, x + 1) × [y, y + 1)`.
+    ///
+    /// `None` for points outside the image circle of a fisheye projection.
+    pub fn ray_through(&self, x: f64, y: f64) -> Option<Ray>
     {
-        let look_base  = self.rot * Vec3::new(0.0, 0.0, 1.0);
-        let look_right = self.rot * Vec3::new(self.tan_half_fov * self.aspect, 0.0, 0.0);
-        let look_down  = self.rot * Vec3::new(0.0, -self.tan_half_fov, 0.0);
+        let (w, h) = (self.width as f64, self.height as f64);
+
+        // In [-1, 1], right and down.
+        let u = x / w * 2.0 - 1.0;
+        let v = y / h * 2.0 - 1.0;
+
+        let local = match self.projection
+        {
+            Projection::Perspective => {
+                Vec3::new(u * self.tan_half_fov * self.aspect, -v * self.tan_half_fov, 1.0).normalized()
+            },
+            Projection::Orthographic { height } => {
+                let offset = Vec3::new(u * height * 0.5 * self.aspect, -v * height * 0.5, 0.0);
+
+                return Some(Ray::new(self.pos + self.rot * offset, self.forward()));
+            },
+            Projection::Fisheye { fov, mapping } => {
+                // The image circle fills the shorter side.
+                let scale = w.min(h) * 0.5;
+                let (px, py) = ((x - w * 0.5) / scale, (y - h * 0.5) / scale);
+                let r = (px * px + py * py).sqrt();
+
+                if r > 1.0
+                {
+                    return None;
+                }
+
+                let theta = match mapping
+                {
+                    FisheyeMapping::Equidistant => r * fov * 0.5,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (fov * 0.25).sin()).asin(),
+                };
+                let phi = py.atan2(px);
+
+                Vec3::new(theta.sin() * phi.cos(), -theta.sin() * phi.sin(), theta.cos())
+            },
+            Projection::Equirectangular => {
+                let longitude = u * PI;
+                let latitude = -v * PI * 0.5;
+
+                Vec3::new(
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    latitude.cos() * longitude.cos(),
+                )
+            },
+            Projection::Cubemap => {
+                // Three faces wide and two high: +x, -x, +y on top and -y, +z, -z below.
+                let column = ((x / w * 3.0) as usize).min(2);
+                let row = ((y / h * 2.0) as usize).min(1);
+
+                let a = (x / w * 3.0 - column as f64) * 2.0 - 1.0;
+                let b = (y / h * 2.0 - row as f64) * 2.0 - 1.0;
 
-        let look_right_step = look_right / (self.width  - 1) as f64 * 2.0;
-        let look_down_step  = look_down  / (self.height - 1) as f64 * 2.0;
+                let (forward, right, up) = CUBE_FACES[column + row * 3];
 
-        let upper_left = look_base - look_right - look_down;
+                (forward + right * a - up * b).normalized()
+            },
+        };
 
-        let mut rays = Vec::with_capacity(self.width * self.height);
+        Some(Ray::new(self.pos, self.rot * local))
+    }
+
+    /// Like `ray_through`, but starting from a random point on the lens. The rays through
+    /// a film point all meet on the focus plane.
+    ///
+    /// Panoramic projections have no lens and are always sharp.
+    pub fn sample_ray(&self, x: f64, y: f64, rng: &mut Rng) -> Option<Ray>
+    {
+        let pinhole = self.ray_through(x, y)?;
+
+        let has_lens = match self.projection
+        {
+            Projection::Perspective | Projection::Orthographic { .. } => self.lens.aperture > 0.0,
+            _ => false,
+        };
 
-        for y in 0..self.height
+        if !has_lens
         {
-            for x in 0..self.width
-            {
-                // TODO optimize
-                rays.push(Ray::new(
-                    self.pos,
-                    upper_left + look_right_step * x as f64 + look_down_step * y as f64
-                ));
-            }
+            return Some(pinhole);
         }
 
-        rays
+        let focus = pinhole.point_at_dist(self.lens.focus_distance / pinhole.dir.dot(self.forward()));
+
+        let (lx, ly) = self.lens.sample_aperture(rng);
+        let origin = pinhole.origin
+            + self.rot * Vec3::new(lx * self.lens.aperture, ly * self.lens.aperture, 0.0);
+
+        Some(Ray::new(origin, (focus - origin).normalized()))
     }
 
-    pub fn line_rays(&self, y: usize) -> Vec<Ray>
+    /// Whether light found by tracing paths from the lights can be projected onto the film,
+    /// which only perspective projections support.
+    pub fn can_project(&self) -> bool
     {
-        let look_base  = self.rot * Vec3::new(0.0, 0.0, 1.0);
-        let look_right = self.rot * Vec3::new(self.tan_half_fov * self.aspect, 0.0, 0.0);
-        let look_down  = self.rot * Vec3::new(0.0, -self.tan_half_fov, 0.0);
+        self.projection == Projection::Perspective
+    }
 
-        let look_right_step = look_right / (self.width  - 1) as f64 * 2.0;
-        let look_down_step  = look_down  / (self.height - 1) as f64 * 2.0;
+    /// The area of the lens, one for a pinhole so importance stays finite.
+    fn lens_area(&self) -> f64
+    {
+        if self.lens.aperture > 0.0 { self.lens.area() } else { 1.0 }
+    }
+
+    /// The area of the image on a plane one unit in front of a pinhole.
+    fn film_area(&self) -> f64
+    {
+        4.0 * self.tan_half_fov * self.tan_half_fov * self.aspect
+    }
 
-        let upper_left = look_base - look_right - look_down;
+    /// Where on the film a ray leaving the lens shows up, with the camera's importance for
+    /// it. `None` if it does not reach the film or the projection is not perspective.
+    pub fn importance(&self, ray: Ray) -> Option<(f64, f64, f64)>
+    {
+        if !self.can_project()
+        {
+            return None;
+        }
 
-        let mut rays = Vec::with_capacity(self.width);
+        let inverse = self.rot.conjugate();
+        let dir = inverse * ray.dir;
+        let cos_theta = dir.z;
 
-        for x in 0..self.width
+        if cos_theta <= 0.0
         {
-            // TODO optimize
-            rays.push(Ray::new(
-                self.pos,
-                upper_left + look_right_step * x as f64 + look_down_step * y as f64
-            ));
+            return None;
         }
 
-        rays
+        // Follow the ray to the plane in focus and back through the pinhole.
+        let focus = if self.lens.aperture > 0.0 { self.lens.focus_distance } else { 1.0 };
+        let point = inverse * (ray.origin - self.pos) + dir * (focus / cos_theta);
+
+        let u = point.x / (point.z * self.tan_half_fov * self.aspect);
+        let v = -point.y / (point.z * self.tan_half_fov);
+
+        let x = (u + 1.0) * 0.5 * self.width as f64;
+        let y = (v + 1.0) * 0.5 * self.height as f64;
+
+        if !(0.0..self.width as f64).contains(&x) || !(0.0..self.height as f64).contains(&y)
+        {
+            return None;
+        }
+
+        let importance = 1.0 / (self.film_area() * self.lens_area() * cos_theta.powi(4));
+
+        Some((x, y, importance))
     }
-}
\ No newline at end of file
+
+    /// The density over solid angle of `sample_ray` picking direction `dir`, for perspective
+    /// projections.
+    pub fn pdf_direction(&self, dir: Vec3) -> f64
+    {
+        let cos_theta = dir.dot(self.forward());
+
+        if !self.can_project() || cos_theta <= 0.0
+        {
+            return 0.0;
+        }
+
+        1.0 / (self.film_area() * cos_theta.powi(3))
+    }
+
+    /// Picks a point on the lens to connect `point` to, for perspective projections.
+    pub fn sample_importance(&self, point: Vec3, rng: &mut Rng) -> Option<ImportanceSample>
+    {
+        let lens_point = if self.lens.aperture > 0.0
+        {
+            let (lx, ly) = self.lens.sample_aperture(rng);
+
+            self.pos + self.rot * Vec3::new(lx * self.lens.aperture, ly * self.lens.aperture, 0.0)
+        }
+        else
+        {
+            self.pos
+        };
+
+        let to_lens = lens_point - point;
+        let distance = to_lens.length();
+        let direction = to_lens / distance;
+        let cos_lens = direction.dot(self.forward()).abs();
+
+        if distance == 0.0 || cos_lens == 0.0
+        {
+            return None;
+        }
+
+        let (x, y, importance) = self.importance(Ray::new(lens_point, -direction))?;
+
+        Some(ImportanceSample {
+            x,
+            y,
+            lens_point,
+            direction,
+            distance,
+            importance,
+            pdf: distance * distance / (cos_lens * self.lens_area()),
+        })
+    }
+
+    /// Rays through the center of every pixel, row by row from the top.
+    pub fn rays(&self) -> Vec<Option<Ray>>
+    {
+        (0..self.height).flat_map(|y| self.line_rays(y)).collect()
+    }
+
+    /// Rays through the center of every pixel on line `y`.
+    pub fn line_rays(&self, y: usize) -> Vec<Option<Ray>>
+    {
+        (0..self.width)
+            .map(|x| self.ray_through(x as f64 + 0.5, y as f64 + 0.5))
+            .collect()
+    }
+}
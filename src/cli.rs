@@ -0,0 +1,295 @@
+use crate::Scene;
+use crate::render::RenderSettings;
+use crate::scene_file::{ SceneDesc, RenderDesc };
+
+use std::fmt;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: raytracer <COMMAND> [OPTIONS]
+
+Commands:
+    render    Render the scene to an image file without opening a window
+    view      Render the scene progressively in a window
+    info      Print information about the scene and the render settings
+    help      Print this message
+
+Options:
+    -s, --scene <FILE>       Scene file to load (defaults to the built-in demo scene)
+    -o, --output <FILE>      Output image, .png, .ppm or .pfm [render only, default: render.png]
+    -W, --width <PIXELS>     Image width [default: 640]
+    -H, --height <PIXELS>    Image height [default: 480]
+                             Render options override the scene file's [render] table
+    -n, --samples <COUNT>    Samples per pixel, progressive passes in the viewer [default: 16]
+    -b, --bounces <COUNT>    Maximum number of bounces per path [default: 10]
+        --seed <SEED>        Seed for the random number generator [default: random]
+    -j, --threads <COUNT>    Number of worker threads [default: number of cores]
+        --sampler <KIND>     Sub-pixel sample placement: center, random or stratified
+                             [default: stratified]
+        --filter <KIND>      Reconstruction filter: box, tent, gaussian, mitchell or
+                             blackman-harris [default: box]
+        --filter-radius <R>  Filter radius in pixels [default: depends on the filter]
+        --integrator <KIND>  How light is computed: path, bdpt, photon, whitted, ao, or one
+                             of the debug views normals, depth, uv, material or bounces
+                             [default: path]
+                             whitted ignores glossy reflections
+        --ao-distance <D>    How far away surfaces still occlude with the ao integrator
+                             [default: 1]
+        --photons <COUNT>    Photons sent per pass with the photon integrator
+                             [default: 100000]
+        --photon-radius <R>  Radius photons are gathered in on the first pass [default: 0.05]
+        --photon-alpha <A>   Between 0 and 1, how slowly the gather radius shrinks with every
+                             pass [default: 0.7]
+    -h, --help               Print this message
+";
+
+#[derive(Debug)]
+pub enum Command
+{
+    Render(Options),
+    View(Options),
+    Info(Options),
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Options
+{
+    pub scene: Option<PathBuf>,
+    pub output: PathBuf,
+    /// Render settings given on the command line, these override the scene file's.
+    pub render: RenderDesc,
+}
+
+#[derive(Debug)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Parses the arguments following the program name.
+pub fn parse<I>(args: I) -> Result<Command, CliError>
+where I: IntoIterator<Item = String>
+{
+    let mut args = args.into_iter();
+
+    let command = match args.next()
+    {
+        Some(command) => command,
+        None => return Err(CliError(format!("no command given\n\n{}", USAGE))),
+    };
+
+    let wrap: fn(Options) -> Command = match command.as_str()
+    {
+        "render" => Command::Render,
+        "view"   => Command::View,
+        "info"   => Command::Info,
+        "help" | "-h" | "--help" => return Ok(Command::Help),
+        _ => return Err(CliError(format!(
+            "unknown command '{}', expected one of render, view, info or help",
+            command,
+        ))),
+    };
+
+    let mut options = Options {
+        scene: None,
+        output: PathBuf::from("render.png"),
+        render: RenderDesc::default(),
+    };
+
+    while let Some(arg) = args.next()
+    {
+        // Accept both `--name value` and `--name=value`.
+        let (name, inline_value) = match arg.find('=')
+        {
+            Some(i) if arg.starts_with("--") => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let mut value = || {
+            inline_value.clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| CliError(format!("missing value for '{}'", name)))
+        };
+
+        match name.as_str()
+        {
+            "-s" | "--scene"   => options.scene = Some(PathBuf::from(value()?)),
+            "-o" | "--output"  => options.output = PathBuf::from(value()?),
+            "-W" | "--width"   => options.render.width = Some(parse_count(&name, &value()?, 2)?),
+            "-H" | "--height"  => options.render.height = Some(parse_count(&name, &value()?, 2)?),
+            "-n" | "--samples" => options.render.samples = Some(parse_count(&name, &value()?, 1)?),
+            "-b" | "--bounces" => options.render.bounces = Some(parse_count(&name, &value()?, 1)?),
+            "-j" | "--threads" => options.render.threads = Some(parse_count(&name, &value()?, 1)?),
+            "--seed" => {
+                let seed = value()?;
+
+                options.render.seed = Some(seed.parse().map_err(|_| CliError(format!(
+                    "invalid value '{}' for '{}': expected an unsigned integer",
+                    seed, name,
+                )))?);
+            },
+            "--sampler" => options.render.sampler = Some(value()?.parse().map_err(CliError)?),
+            "--filter"  => options.render.filter = Some(value()?.parse().map_err(CliError)?),
+            "--filter-radius" => options.render.filter_radius = Some(parse_positive(&name, &value()?)?),
+            "--integrator" => options.render.integrator = Some(value()?.parse().map_err(CliError)?),
+            "--ao-distance" => options.render.ao_distance = Some(parse_positive(&name, &value()?)?),
+            "--photons" => options.render.photons = Some(parse_count(&name, &value()?, 1)?),
+            "--photon-radius" => options.render.photon_radius = Some(parse_positive(&name, &value()?)?),
+            "--photon-alpha" => {
+                let alpha = value()?;
+
+                options.render.photon_alpha = match alpha.parse::<f64>()
+                {
+                    Ok(a) if a > 0.0 && a <= 1.0 => Some(a),
+                    _ => return Err(CliError(format!(
+                        "invalid value '{}' for '{}': expected a number above 0 and at most 1",
+                        alpha, name,
+                    ))),
+                };
+            },
+            "-h" | "--help" => return Ok(Command::Help),
+            _ => return Err(CliError(format!("unknown option '{}'\n\n{}", arg, USAGE))),
+        }
+    }
+
+    if command == "render"
+    {
+        let extension = options.output.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match extension.as_deref()
+        {
+            Some("png") | Some("ppm") | Some("pfm") => (),
+            _ => return Err(CliError(format!(
+                "unsupported output format '{}': expected a .png, .ppm or .pfm file",
+                options.output.display(),
+            ))),
+        }
+    }
+
+    Ok(wrap(options))
+}
+
+/// Loads the scene given on the command line, or the demo scene, along with the render
+/// settings from the scene file and the command line and the warnings building it gave.
+pub fn load_scene(options: &Options) -> Result<(Scene, RenderSettings, Vec<String>), String>
+{
+    let desc = match &options.scene
+    {
+        Some(path) => SceneDesc::load(path).map_err(|err| err.to_string())?,
+        None => SceneDesc::demo(),
+    };
+
+    let mut settings = RenderSettings::default();
+    desc.render.apply(&mut settings);
+    options.render.apply(&mut settings);
+
+    let (scene, warnings) = desc.build_with_warnings().map_err(|err| err.to_string())?;
+
+    Ok((scene, settings, warnings))
+}
+
+fn parse_count<T>(name: &str, value: &str, min: T) -> Result<T, CliError>
+where T: std::str::FromStr + PartialOrd + fmt::Display
+{
+    match value.parse::<T>()
+    {
+        Ok(count) if count >= min => Ok(count),
+        _ => Err(CliError(format!(
+            "invalid value '{}' for '{}': expected an integer of at least {}",
+            value, name, min,
+        ))),
+    }
+}
+
+fn parse_positive(name: &str, value: &str) -> Result<f64, CliError>
+{
+    match value.parse::<f64>()
+    {
+        Ok(x) if x > 0.0 && x.is_finite() => Ok(x),
+        _ => Err(CliError(format!(
+            "invalid value '{}' for '{}': expected a positive number",
+            value, name,
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn parse_args(args: &str) -> Result<Command, CliError>
+    {
+        parse(args.split_whitespace().map(str::to_string))
+    }
+
+    fn options(args: &str) -> Options
+    {
+        match parse_args(args)
+        {
+            Ok(Command::Render(options)) | Ok(Command::View(options)) | Ok(Command::Info(options)) => options,
+            Ok(command) => panic!("expected options from '{}', got {:?}", args, command),
+            Err(err) => panic!("'{}' failed: {}", args, err),
+        }
+    }
+
+    fn error(args: &str) -> String
+    {
+        match parse_args(args)
+        {
+            Err(err) => err.to_string(),
+            Ok(command) => panic!("expected '{}' to fail, got {:?}", args, command),
+        }
+    }
+
+    #[test]
+    fn options_are_parsed_in_both_forms()
+    {
+        let options = options("render -s scene.toml --output=out.pfm -W 320 --height=200 -n 4 --seed 7 --photon-alpha 0.5");
+
+        assert_eq!(options.scene, Some(PathBuf::from("scene.toml")));
+        assert_eq!(options.output, PathBuf::from("out.pfm"));
+        assert_eq!((options.render.width, options.render.height), (Some(320), Some(200)));
+        assert_eq!(options.render.samples, Some(4));
+        assert_eq!(options.render.seed, Some(7));
+        assert_eq!(options.render.photon_alpha, Some(0.5));
+    }
+
+    #[test]
+    fn values_are_checked_against_their_bounds()
+    {
+        assert!(error("render -W 1").contains("at least 2"));
+        assert!(error("render --samples 0").contains("at least 1"));
+        assert!(error("render --bounces -1").contains("at least 1"));
+        assert!(error("render --filter-radius 0").contains("positive"));
+        assert!(error("render --ao-distance inf").contains("positive"));
+        assert!(error("render --photon-alpha 1.5").contains("at most 1"));
+        assert!(error("render --seed x").contains("unsigned integer"));
+        assert!(error("render --integrator magic").contains("magic"));
+    }
+
+    #[test]
+    fn commands_and_outputs_are_checked()
+    {
+        assert!(matches!(parse_args("help"), Ok(Command::Help)));
+        assert!(matches!(parse_args("view --help"), Ok(Command::Help)));
+        assert!(error("").contains("no command"));
+        assert!(error("draw").contains("unknown command"));
+        assert!(error("render --fast").contains("unknown option"));
+        assert!(error("render --scene").contains("missing value"));
+        assert!(error("render -o image.jpg").contains("unsupported output format"));
+
+        // Only rendering writes the output.
+        options("view -o image.jpg");
+    }
+}
@@ -1,6 +1,7 @@
 use std::ops;
 
-#[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RGB
 {
     pub r: f32,
@@ -35,12 +36,23 @@ impl RGB
         }
     }
 
+    pub fn is_black(&self) -> bool
+    {
+        self.r <= 0.0 && self.g <= 0.0 && self.b <= 0.0
+    }
+
+    /// Relative luminance of linear Rec. 709 primaries.
+    pub fn luminance(&self) -> f32
+    {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn clamp(&self) -> RGB
     {
         RGB {
-            r: self.r.min(1.0).max(0.0),
-            g: self.g.min(1.0).max(0.0),
-            b: self.b.min(1.0).max(0.0),
+            r: self.r.clamp(0.0, 1.0),
+            g: self.g.clamp(0.0, 1.0),
+            b: self.b.clamp(0.0, 1.0),
         }
     }
 
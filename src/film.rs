@@ -0,0 +1,187 @@
+use crate::{
+    RGB,
+    filter::Filter,
+};
+
+/// A color sample at a position on the film, in pixels from the top left corner.
+#[derive(Debug, Clone, Copy)]
+pub struct FilmSample
+{
+    pub x: f64,
+    pub y: f64,
+    pub color: RGB,
+}
+
+/// Accumulates samples into pixels, weighting each by the reconstruction filter.
+///
+/// Light traced from the lights lands on the film at random positions instead, and is splatted
+/// into the pixel it hits without filtering. Splats are averaged over all camera samples taken,
+/// since every camera sample traces one path from the lights.
+#[derive(Debug, Clone)]
+pub struct Film
+{
+    width: usize,
+    height: usize,
+    filter: Filter,
+    /// Weighted color sums, three per pixel.
+    sums: Vec<f64>,
+    weights: Vec<f64>,
+    /// Splatted color sums, three per pixel.
+    splats: Vec<f64>,
+    /// The number of samples added so far.
+    samples: u64,
+}
+
+impl Film
+{
+    pub fn new(width: usize, height: usize, filter: Filter) -> Film
+    {
+        Film {
+            width,
+            height,
+            filter,
+            sums: vec![0.0; width * height * 3],
+            weights: vec![0.0; width * height],
+            splats: vec![0.0; width * height * 3],
+            samples: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize
+    {
+        self.width
+    }
+
+    pub fn height(&self) -> usize
+    {
+        self.height
+    }
+
+    pub fn filter(&self) -> Filter
+    {
+        self.filter
+    }
+
+    /// Adds a sample to every pixel whose center is within the filter's radius.
+    pub fn add_sample(&mut self, sample: FilmSample)
+    {
+        let radius = self.filter.radius();
+
+        self.samples += 1;
+
+        // Pixel `x` has its center at `x + 0.5`.
+        let x0 = (sample.x - 0.5 - radius).ceil().max(0.0) as usize;
+        let x1 = ((sample.x - 0.5 + radius).floor() as isize).min(self.width as isize - 1);
+        let y0 = (sample.y - 0.5 - radius).ceil().max(0.0) as usize;
+        let y1 = ((sample.y - 0.5 + radius).floor() as isize).min(self.height as isize - 1);
+
+        if x1 < 0 || y1 < 0
+        {
+            return;
+        }
+
+        for y in y0..=y1 as usize
+        {
+            for x in x0..=x1 as usize
+            {
+                let weight = self.filter.eval(sample.x - (x as f64 + 0.5), sample.y - (y as f64 + 0.5));
+
+                if weight == 0.0
+                {
+                    continue;
+                }
+
+                let i = x + y * self.width;
+
+                self.sums[i * 3]     += sample.color.r as f64 * weight;
+                self.sums[i * 3 + 1] += sample.color.g as f64 * weight;
+                self.sums[i * 3 + 2] += sample.color.b as f64 * weight;
+                self.weights[i] += weight;
+            }
+        }
+    }
+
+    pub fn add_samples(&mut self, samples: &[FilmSample])
+    {
+        for &sample in samples
+        {
+            self.add_sample(sample);
+        }
+    }
+
+    /// Adds light to the pixel containing the sample's position, if it is on the film.
+    pub fn add_splat(&mut self, splat: FilmSample)
+    {
+        if !(0.0..self.width as f64).contains(&splat.x) || !(0.0..self.height as f64).contains(&splat.y)
+        {
+            return;
+        }
+
+        let i = splat.x as usize + splat.y as usize * self.width;
+
+        self.splats[i * 3]     += splat.color.r as f64;
+        self.splats[i * 3 + 1] += splat.color.g as f64;
+        self.splats[i * 3 + 2] += splat.color.b as f64;
+    }
+
+    pub fn add_splats(&mut self, splats: &[FilmSample])
+    {
+        for &splat in splats
+        {
+            self.add_splat(splat);
+        }
+    }
+
+    /// The filtered color of a pixel plus the light splatted onto it, black until a sample has
+    /// reached it.
+    pub fn pixel(&self, x: usize, y: usize) -> RGB
+    {
+        let i = x + y * self.width;
+        let weight = self.weights[i];
+
+        // Filters with negative lobes can leave pixels with a tiny total weight.
+        let filtered = if weight.abs() < 1e-9
+        {
+            RGB::black()
+        }
+        else
+        {
+            RGB::new(
+                (self.sums[i * 3] / weight) as f32,
+                (self.sums[i * 3 + 1] / weight) as f32,
+                (self.sums[i * 3 + 2] / weight) as f32,
+            )
+        };
+
+        if self.samples == 0
+        {
+            return filtered;
+        }
+
+        let scale = (self.width * self.height) as f64 / self.samples as f64;
+
+        filtered + RGB::new(
+            (self.splats[i * 3] * scale) as f32,
+            (self.splats[i * 3 + 1] * scale) as f32,
+            (self.splats[i * 3 + 2] * scale) as f32,
+        )
+    }
+
+    /// The filtered linear RGB values, three `f32`s per pixel, row by row from the top.
+    pub fn pixels(&self) -> Vec<f32>
+    {
+        let mut pixels = Vec::with_capacity(self.width * self.height * 3);
+
+        for y in 0..self.height
+        {
+            for x in 0..self.width
+            {
+                let color = self.pixel(x, y);
+
+                pixels.extend_from_slice(&[color.r, color.g, color.b]);
+            }
+        }
+
+        pixels
+    }
+}
@@ -0,0 +1,145 @@
+//! Pixel reconstruction filters.
+//!
+//! Every filter is separable, `eval(x, y) = eval_1d(x) * eval_1d(y)`, with `x` and `y` the
+//! offset in pixels from a sample to the pixel center.
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter
+{
+    Box { radius: f64 },
+    Tent { radius: f64 },
+    Gaussian { radius: f64, alpha: f64 },
+    /// Mitchell-Netravali with the `b` and `c` parameters, 1/3 each is the usual choice.
+    Mitchell { radius: f64, b: f64, c: f64 },
+    BlackmanHarris { radius: f64 },
+}
+
+impl Default for Filter
+{
+    fn default() -> Filter
+    {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+impl Filter
+{
+    /// The filter of the given kind with its usual parameters.
+    pub fn with_defaults(kind: &str) -> Option<Filter>
+    {
+        match kind
+        {
+            "box"             => Some(Filter::Box { radius: 0.5 }),
+            "tent"            => Some(Filter::Tent { radius: 1.0 }),
+            "gaussian"        => Some(Filter::Gaussian { radius: 1.5, alpha: 2.0 }),
+            "mitchell"        => Some(Filter::Mitchell { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 }),
+            "blackman-harris" => Some(Filter::BlackmanHarris { radius: 2.0 }),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Filter::Box { .. }            => "box",
+            Filter::Tent { .. }           => "tent",
+            Filter::Gaussian { .. }       => "gaussian",
+            Filter::Mitchell { .. }       => "mitchell",
+            Filter::BlackmanHarris { .. } => "blackman-harris",
+        }
+    }
+
+    /// How far from a pixel center, in pixels, samples still contribute to it.
+    pub fn radius(&self) -> f64
+    {
+        match *self
+        {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::BlackmanHarris { radius } => radius,
+        }
+    }
+
+    pub fn with_radius(self, radius: f64) -> Filter
+    {
+        match self
+        {
+            Filter::Box { .. }                => Filter::Box { radius },
+            Filter::Tent { .. }               => Filter::Tent { radius },
+            Filter::Gaussian { alpha, .. }    => Filter::Gaussian { radius, alpha },
+            Filter::Mitchell { b, c, .. }     => Filter::Mitchell { radius, b, c },
+            Filter::BlackmanHarris { .. }     => Filter::BlackmanHarris { radius },
+        }
+    }
+
+    pub fn eval(&self, x: f64, y: f64) -> f64
+    {
+        self.eval_1d(x) * self.eval_1d(y)
+    }
+
+    fn eval_1d(&self, x: f64) -> f64
+    {
+        let x = x.abs();
+
+        if x > self.radius()
+        {
+            return 0.0;
+        }
+
+        match *self
+        {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { radius } => radius - x,
+            Filter::Gaussian { radius, alpha } => {
+                // Shifted down so the filter reaches zero at its radius.
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            },
+            Filter::Mitchell { radius, b, c } => {
+                let x = 2.0 * x / radius;
+
+                if x > 1.0
+                {
+                    ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+                }
+                else
+                {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b)) / 6.0
+                }
+            },
+            Filter::BlackmanHarris { radius } => {
+                let t = 2.0 * PI * (0.5 + x / (2.0 * radius));
+
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+            },
+        }
+    }
+}
+
+impl fmt::Display for Filter
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Filter
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Filter, String>
+    {
+        Filter::with_defaults(s).ok_or_else(|| format!(
+            "unknown filter '{}', expected one of box, tent, gaussian, mitchell or blackman-harris", s,
+        ))
+    }
+}
@@ -0,0 +1,495 @@
+use crate::RGB;
+
+use std::io::{
+    self,
+    Read,
+    Write,
+    BufRead,
+    BufReader,
+    BufWriter,
+};
+use std::fs::File;
+use std::path::Path;
+
+/// The gamma applied to linear values before they are quantized to 8 bits.
+pub const GAMMA: f32 = 0.45;
+
+/// Writes a linear RGB float buffer to `path`, picking the format from the file extension.
+///
+/// `.png` and `.ppm` are gamma corrected and quantized to 8 bits,
+/// `.pfm` stores the linear values as-is.
+pub fn save(path: &Path, width: usize, height: usize, pixels: &[f32]) -> io::Result<()>
+{
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    match extension.as_deref()
+    {
+        Some("png") => write_png(File::create(path)?, width, height, pixels),
+        Some("ppm") => write_ppm(File::create(path)?, width, height, pixels),
+        Some("pfm") => write_pfm(File::create(path)?, width, height, pixels),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported image format: {}", path.display()),
+        )),
+    }
+}
+
+/// Reads a linear RGB float buffer from `path`, picking the format from the file extension.
+///
+/// Returns the width, height and three `f32`s per pixel, row by row from the top.
+/// Supports Radiance `.hdr`, `.pfm`, `.png` and `.jpg`. The 8-bit formats are gamma decoded
+/// the way `save` encodes them.
+pub fn load(path: &Path) -> io::Result<(usize, usize, Vec<f32>)>
+{
+    let (width, height, mut pixels, encoded) = read(path)?;
+
+    if encoded
+    {
+        for value in pixels.iter_mut()
+        {
+            *value = value.powf(1.0 / GAMMA);
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+/// Like `load`, but keeps 8-bit values as they are stored, mapped to 0 to 1. Meant for
+/// images holding data rather than colors, like roughness maps.
+pub fn load_raw(path: &Path) -> io::Result<(usize, usize, Vec<f32>)>
+{
+    let (width, height, pixels, _) = read(path)?;
+
+    Ok((width, height, pixels))
+}
+
+/// Reads any supported image, also returning whether its values are gamma encoded.
+fn read(path: &Path) -> io::Result<(usize, usize, Vec<f32>, bool)>
+{
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    let linear = |(width, height, pixels)| (width, height, pixels, false);
+    let encoded = |(width, height, pixels)| (width, height, pixels, true);
+
+    match extension.as_deref()
+    {
+        Some("hdr") => read_hdr(File::open(path)?).map(linear),
+        Some("pfm") => read_pfm(File::open(path)?).map(linear),
+        Some("png") => read_png(File::open(path)?).map(encoded),
+        Some("jpg") | Some("jpeg") => read_jpeg(File::open(path)?).map(encoded),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported image format: {}", path.display()),
+        )),
+    }
+}
+
+/// Converts a linear RGB float buffer to gamma corrected 8-bit RGB.
+pub fn to_u8(pixels: &[f32]) -> Vec<u8>
+{
+    pixels
+        .chunks(3)
+        .flat_map(|p| RGB::new(p[0], p[1], p[2]).clamp().gamma(GAMMA).as_u8().to_vec())
+        .collect()
+}
+
+pub fn write_png<W: Write>(writer: W, width: usize, height: usize, pixels: &[f32]) -> io::Result<()>
+{
+    let mut encoder = png::Encoder::new(BufWriter::new(writer), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&to_u8(pixels))?;
+
+    Ok(())
+}
+
+/// Writes a binary (P6) PPM.
+pub fn write_ppm<W: Write>(writer: W, width: usize, height: usize, pixels: &[f32]) -> io::Result<()>
+{
+    let mut writer = BufWriter::new(writer);
+
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    writer.write_all(&to_u8(pixels))?;
+
+    writer.flush()
+}
+
+/// Writes a little-endian color PFM.
+///
+/// PFM stores scanlines bottom to top, so the rows are flipped on the way out.
+pub fn write_pfm<W: Write>(writer: W, width: usize, height: usize, pixels: &[f32]) -> io::Result<()>
+{
+    if width == 0 || height == 0 || width.checked_mul(height).and_then(|pixels| pixels.checked_mul(3)) != Some(pixels.len())
+    {
+        return Err(invalid_data("the pixels do not match the image size"));
+    }
+
+    let mut writer = BufWriter::new(writer);
+
+    write!(writer, "PF\n{} {}\n-1.0\n", width, height)?;
+
+    for row in pixels.chunks(width * 3).rev()
+    {
+        for value in row
+        {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    writer.flush()
+}
+
+/// Reads a Radiance RGBE image, flat or with run-length encoded scanlines.
+pub fn read_hdr<R: Read>(reader: R) -> io::Result<(usize, usize, Vec<f32>)>
+{
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+
+    reader.read_line(&mut line)?;
+
+    if !line.starts_with("#?")
+    {
+        return Err(invalid_data("not a Radiance HDR file"));
+    }
+
+    // Header variables up to an empty line, then the resolution.
+    loop
+    {
+        line.clear();
+
+        if reader.read_line(&mut line)? == 0
+        {
+            return Err(invalid_data("unexpected end of header"));
+        }
+
+        let line = line.trim();
+
+        if line.is_empty()
+        {
+            break;
+        }
+
+        if let Some(format) = line.strip_prefix("FORMAT=")
+        {
+            if format != "32-bit_rle_rgbe"
+            {
+                return Err(invalid_data(&format!("unsupported pixel format '{}'", format)));
+            }
+        }
+    }
+
+    line.clear();
+    reader.read_line(&mut line)?;
+
+    let (width, height): (usize, usize) = match line.split_whitespace().collect::<Vec<_>>()[..]
+    {
+        ["-Y", height, "+X", width] => (
+            width.parse().map_err(|_| invalid_data("invalid image width"))?,
+            height.parse().map_err(|_| invalid_data("invalid image height"))?,
+        ),
+        _ => return Err(invalid_data(&format!("unsupported image orientation '{}'", line.trim()))),
+    };
+
+    if width == 0 || height == 0 || width.checked_mul(height).and_then(|pixels| pixels.checked_mul(4)).is_none()
+    {
+        return Err(invalid_data("invalid image size"));
+    }
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    let mut scanline = vec![0u8; width * 4];
+
+    for _ in 0..height
+    {
+        read_hdr_scanline(&mut reader, &mut scanline).map_err(|err| match err.kind()
+        {
+            io::ErrorKind::UnexpectedEof => invalid_data("truncated image data"),
+            _ => err,
+        })?;
+
+        for rgbe in scanline.chunks(4)
+        {
+            if rgbe[3] == 0
+            {
+                pixels.extend_from_slice(&[0.0; 3]);
+                continue;
+            }
+
+            let scale = 2f32.powi(rgbe[3] as i32 - (128 + 8));
+
+            pixels.extend(rgbe[..3].iter().map(|&c| (c as f32 + 0.5) * scale));
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+fn read_hdr_scanline<R: Read>(reader: &mut R, scanline: &mut [u8]) -> io::Result<()>
+{
+    let width = scanline.len() / 4;
+
+    if width == 0 || !scanline.len().is_multiple_of(4)
+    {
+        return Err(invalid_data("invalid scanline length"));
+    }
+
+    let mut start = [0u8; 4];
+    reader.read_exact(&mut start)?;
+
+    let is_rle = (8..0x8000).contains(&width)
+        && start[0] == 2 && start[1] == 2 && start[2] & 0x80 == 0;
+
+    if !is_rle
+    {
+        scanline[..4].copy_from_slice(&start);
+        return reader.read_exact(&mut scanline[4..]);
+    }
+
+    if ((start[2] as usize) << 8 | start[3] as usize) != width
+    {
+        return Err(invalid_data("scanline width mismatch"));
+    }
+
+    // Each of the four components is stored separately, as runs and literal spans.
+    for component in 0..4
+    {
+        let mut x = 0;
+
+        while x < width
+        {
+            let mut header = [0u8; 2];
+            reader.read_exact(&mut header[..1])?;
+
+            let (run, count) = if header[0] > 128
+            {
+                (true, header[0] as usize - 128)
+            }
+            else
+            {
+                (false, header[0] as usize)
+            };
+
+            if count == 0 || x + count > width
+            {
+                return Err(invalid_data("corrupt run-length encoded scanline"));
+            }
+
+            if run
+            {
+                reader.read_exact(&mut header[1..])?;
+
+                for i in x..x + count
+                {
+                    scanline[i * 4 + component] = header[1];
+                }
+            }
+            else
+            {
+                let mut values = [0u8; 128];
+                reader.read_exact(&mut values[..count])?;
+
+                for (i, &value) in values[..count].iter().enumerate()
+                {
+                    scanline[(x + i) * 4 + component] = value;
+                }
+            }
+
+            x += count;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a color (`PF`) or grayscale (`Pf`) PFM of either endianness.
+pub fn read_pfm<R: Read>(reader: R) -> io::Result<(usize, usize, Vec<f32>)>
+{
+    let mut reader = BufReader::new(reader);
+
+    // The header is three whitespace separated tokens after the magic number.
+    let mut tokens = Vec::new();
+
+    while tokens.len() < 4
+    {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line)? == 0
+        {
+            return Err(invalid_data("unexpected end of header"));
+        }
+
+        tokens.extend(line.split_whitespace().map(String::from));
+    }
+
+    let channels = match tokens[0].as_str()
+    {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("not a PFM file")),
+    };
+
+    let width: usize = tokens[1].parse().map_err(|_| invalid_data("invalid image width"))?;
+    let height: usize = tokens[2].parse().map_err(|_| invalid_data("invalid image height"))?;
+    let scale: f32 = tokens[3].parse().map_err(|_| invalid_data("invalid scale"))?;
+
+    let size = width.checked_mul(height).and_then(|pixels| pixels.checked_mul(channels * 4));
+
+    if width == 0 || height == 0 || size.is_none()
+    {
+        return Err(invalid_data("invalid image size"));
+    }
+
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    if Some(data.len()) != size
+    {
+        return Err(invalid_data("the image data does not match the image size"));
+    }
+
+    let values: Vec<f32> = data
+        .chunks(4)
+        .map(|bytes| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+            if scale < 0.0 { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) }
+        })
+        .collect();
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+
+    for row in values.chunks(width * channels).rev()
+    {
+        for value in row.chunks(channels)
+        {
+            match channels
+            {
+                1 => pixels.extend_from_slice(&[value[0]; 3]),
+                _ => pixels.extend_from_slice(value),
+            }
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+/// Reads a PNG of any color type and bit depth as RGB values from 0 to 1, dropping alpha.
+pub fn read_png<R: Read>(reader: R) -> io::Result<(usize, usize, Vec<f32>)>
+{
+    let mut decoder = png::Decoder::new(BufReader::new(reader));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info().map_err(|err| invalid_data(&err.to_string()))?;
+    let mut data = vec![0u8; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|err| invalid_data(&err.to_string()))?;
+
+    let channels = match info.color_type
+    {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err(invalid_data("unexpanded indexed PNG")),
+    };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    Ok((width, height, to_rgb(&data[..width * height * channels], channels)))
+}
+
+/// Reads a baseline or progressive JPEG as RGB values from 0 to 1.
+pub fn read_jpeg<R: Read>(reader: R) -> io::Result<(usize, usize, Vec<f32>)>
+{
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(reader));
+    let data = decoder.decode().map_err(|err| invalid_data(&err.to_string()))?;
+    let info = decoder.info().ok_or_else(|| invalid_data("missing JPEG header"))?;
+
+    let channels = match info.pixel_format
+    {
+        jpeg_decoder::PixelFormat::L8 => 1,
+        jpeg_decoder::PixelFormat::RGB24 => 3,
+        format => return Err(invalid_data(&format!("unsupported JPEG pixel format {:?}", format))),
+    };
+
+    Ok((info.width as usize, info.height as usize, to_rgb(&data, channels)))
+}
+
+/// Expands 8-bit gray, gray and alpha, RGB or RGBA values to RGB from 0 to 1.
+fn to_rgb(data: &[u8], channels: usize) -> Vec<f32>
+{
+    let value = |byte: u8| byte as f32 / 255.0;
+
+    data
+        .chunks(channels)
+        .flat_map(|p| match channels
+        {
+            1 | 2 => [value(p[0]); 3],
+            _ => [value(p[0]), value(p[1]), value(p[2])],
+        })
+        .collect()
+}
+
+fn invalid_data(message: &str) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn is_invalid<T>(result: io::Result<T>) -> bool
+    {
+        result.is_err_and(|err| err.kind() == io::ErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn hdr_sizes_must_match_the_data()
+    {
+        let header = |resolution: &str| {
+            format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", resolution).into_bytes()
+        };
+        let pixels = [128, 64, 0, 129, 0, 0, 0, 0];
+
+        assert_eq!(read_hdr(&[header("-Y 1 +X 2"), pixels.to_vec()].concat()[..]).unwrap(), (2, 1, vec![
+            1.0 + 1.0 / 256.0, 0.5 + 1.0 / 256.0, 1.0 / 256.0, 0.0, 0.0, 0.0,
+        ]));
+
+        assert!(is_invalid(read_hdr(&header("-Y 1 +X 0")[..])));
+        assert!(is_invalid(read_hdr(&header("-Y 0 +X 2")[..])));
+        assert!(is_invalid(read_hdr(&[header("-Y 2 +X 2"), pixels.to_vec()].concat()[..])));
+        assert!(is_invalid(read_hdr(&[header("-Y 1 +X 2"), pixels[..6].to_vec()].concat()[..])));
+        assert!(is_invalid(read_hdr_scanline(&mut &pixels[..], &mut [0; 3])));
+    }
+
+    #[test]
+    fn pfm_survives_a_round_trip()
+    {
+        let pixels: Vec<f32> = (0..2 * 3 * 3).map(|i| i as f32 * 0.5).collect();
+        let mut file = vec![];
+
+        write_pfm(&mut file, 2, 3, &pixels).unwrap();
+
+        assert_eq!(read_pfm(&file[..]).unwrap(), (2, 3, pixels));
+    }
+
+    #[test]
+    fn pfm_sizes_must_match_the_data()
+    {
+        assert!(is_invalid(write_pfm(vec![], 0, 0, &[])));
+        assert!(is_invalid(write_pfm(vec![], 2, 2, &[0.0; 9])));
+
+        let pixel = 1f32.to_le_bytes();
+
+        assert!(is_invalid(read_pfm(&b"PF\n0 1\n-1.0\n"[..])));
+        assert!(is_invalid(read_pfm(&[&b"Pf\n2 1\n-1.0\n"[..], &pixel].concat()[..])));
+        assert!(is_invalid(read_pfm(&[&b"Pf\n1 1\n-1.0\n"[..], &pixel, &pixel].concat()[..])));
+        assert!(is_invalid(read_pfm(&b"Pf\n18446744073709551615 2\n-1.0\n"[..])));
+    }
+}
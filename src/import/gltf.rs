@@ -0,0 +1,383 @@
+//! Materials of glTF 2.0 assets, `.gltf` or binary `.glb`.
+//!
+//! Only the material definitions are read, textures and geometry are ignored.
+
+use crate::{
+    Material,
+    RGB,
+    bsdf::Principled,
+};
+use super::ImportError;
+
+use serde::Deserialize;
+
+use std::io;
+use std::path::Path;
+
+/// The metallic-roughness parameters of a glTF material and the extensions that map onto a
+/// `Principled` material.
+#[derive(Debug, Clone)]
+pub struct GltfMaterial
+{
+    /// The material's name, or its index if it has none.
+    pub name: String,
+    /// Linear base color, the alpha is ignored.
+    pub base_color: [f64; 4],
+    pub metallic: f64,
+    pub roughness: f64,
+    /// Emissive color, scaled by `KHR_materials_emissive_strength`.
+    pub emissive: [f64; 3],
+    /// `KHR_materials_transmission`.
+    pub transmission: f64,
+    /// `KHR_materials_ior`, 1.5 by default.
+    pub ior: f64,
+    /// `KHR_materials_specular`, scaling the reflectance following from `ior`.
+    pub specular: f64,
+    /// `KHR_materials_clearcoat`.
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
+    /// `KHR_materials_sheen`.
+    pub sheen_color: [f64; 3],
+}
+
+impl GltfMaterial
+{
+    /// Converts to a principled `Material`.
+    ///
+    /// The specular strength is chosen so the head-on reflectance matches the one glTF derives
+    /// from `ior`, and the sheen color is split into a strength and a tint.
+    pub fn to_material(&self) -> Material
+    {
+        let max = |c: [f64; 3]| c[0].max(c[1]).max(c[2]);
+        let [r, g, b, _] = self.base_color;
+
+        let f0 = ((self.ior - 1.0) / (self.ior + 1.0)).powi(2) * self.specular;
+        let sheen = max(self.sheen_color);
+        let sheen_tint = if sheen > 0.0 && self.sheen_color.iter().any(|&c| c != sheen) { 1.0 } else { 0.0 };
+
+        let principled = Principled {
+            base_color: RGB::new(r as f32, g as f32, b as f32),
+            metallic: self.metallic as f32,
+            roughness: self.roughness as f32,
+            specular: (f0 / 0.08).min(1.0) as f32,
+            sheen: sheen as f32,
+            sheen_tint,
+            clearcoat: self.clearcoat as f32,
+            clearcoat_roughness: self.clearcoat_roughness as f32,
+            transmission: self.transmission as f32,
+            r_index: self.ior as f32,
+            ..Principled::default()
+        };
+
+        let [r, g, b] = self.emissive;
+
+        Material::principled(principled)
+            .with_emission(RGB::new(r as f32, g as f32, b as f32))
+            .with_name(&self.name)
+    }
+}
+
+/// Loads all materials of a `.gltf` or `.glb` file, in the order of the file.
+pub fn load(path: &Path) -> Result<Vec<GltfMaterial>, ImportError>
+{
+    let data = std::fs::read(path).map_err(|err| ImportError::Io(path.to_path_buf(), err))?;
+
+    let invalid = |message: String| {
+        ImportError::Io(path.to_path_buf(), io::Error::new(io::ErrorKind::InvalidData, message))
+    };
+
+    let json = if data.starts_with(b"glTF")
+    {
+        glb_json(&data).map_err(invalid)?
+    }
+    else
+    {
+        &data[..]
+    };
+
+    let source = std::str::from_utf8(json).map_err(|_| invalid("the JSON is not valid UTF-8".to_string()))?;
+
+    parse(source).map_err(|(line, message)| ImportError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    })
+}
+
+/// Parses the JSON of a glTF asset, errors carry the 1-based line number.
+pub fn parse(source: &str) -> Result<Vec<GltfMaterial>, (usize, String)>
+{
+    let root: Root = serde_json::from_str(source).map_err(|err| (err.line(), err.to_string()))?;
+
+    let materials = root.materials
+        .into_iter()
+        .enumerate()
+        .map(|(index, material)| {
+            let pbr = material.pbr_metallic_roughness;
+            let extensions = material.extensions;
+
+            let clearcoat = extensions.clearcoat.unwrap_or_default();
+            let specular = extensions.specular.unwrap_or_default();
+            let strength = extensions.emissive_strength.map_or(1.0, |e| e.emissive_strength);
+            let [r, g, b] = material.emissive_factor;
+
+            GltfMaterial {
+                name: material.name.unwrap_or_else(|| index.to_string()),
+                base_color: pbr.base_color_factor,
+                metallic: pbr.metallic_factor,
+                roughness: pbr.roughness_factor,
+                emissive: [r * strength, g * strength, b * strength],
+                transmission: extensions.transmission.map_or(0.0, |t| t.transmission_factor),
+                ior: extensions.ior.map_or(1.5, |ior| ior.ior),
+                specular: specular.specular_factor,
+                clearcoat: clearcoat.clearcoat_factor,
+                clearcoat_roughness: clearcoat.clearcoat_roughness_factor,
+                sheen_color: extensions.sheen.map_or([0.0; 3], |sheen| sheen.sheen_color_factor),
+            }
+        })
+        .collect();
+
+    Ok(materials)
+}
+
+/// The JSON chunk of a binary glTF file, which always comes first.
+fn glb_json(data: &[u8]) -> Result<&[u8], String>
+{
+    let word = |offset: usize| {
+        data.get(offset..offset + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    if word(4) != Some(2)
+    {
+        return Err("only version 2 of binary glTF is supported".to_string());
+    }
+
+    let length = word(12).ok_or("truncated header")? as usize;
+
+    // "JSON" in little endian.
+    if word(16) != Some(0x4e4f_534a)
+    {
+        return Err("the first chunk is not JSON".to_string());
+    }
+
+    data.get(20..20 + length).ok_or_else(|| "truncated JSON chunk".to_string())
+}
+
+#[derive(Deserialize)]
+struct Root
+{
+    #[serde(default)]
+    materials: Vec<MaterialJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialJson
+{
+    name: Option<String>,
+    #[serde(default)]
+    pbr_metallic_roughness: PbrJson,
+    #[serde(default)]
+    emissive_factor: [f64; 3],
+    #[serde(default)]
+    extensions: ExtensionsJson,
+}
+
+#[derive(Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct PbrJson
+{
+    base_color_factor: [f64; 4],
+    metallic_factor: f64,
+    roughness_factor: f64,
+}
+
+impl Default for PbrJson
+{
+    fn default() -> PbrJson
+    {
+        PbrJson {
+            base_color_factor: [1.0; 4],
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ExtensionsJson
+{
+    #[serde(rename = "KHR_materials_transmission")]
+    transmission: Option<TransmissionJson>,
+    #[serde(rename = "KHR_materials_ior")]
+    ior: Option<IorJson>,
+    #[serde(rename = "KHR_materials_specular")]
+    specular: Option<SpecularJson>,
+    #[serde(rename = "KHR_materials_clearcoat")]
+    clearcoat: Option<ClearcoatJson>,
+    #[serde(rename = "KHR_materials_sheen")]
+    sheen: Option<SheenJson>,
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    emissive_strength: Option<EmissiveStrengthJson>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct TransmissionJson
+{
+    transmission_factor: f64,
+}
+
+#[derive(Deserialize)]
+struct IorJson
+{
+    #[serde(default = "default_ior")]
+    ior: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SpecularJson
+{
+    specular_factor: f64,
+}
+
+impl Default for SpecularJson
+{
+    fn default() -> SpecularJson
+    {
+        SpecularJson {
+            specular_factor: 1.0,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ClearcoatJson
+{
+    clearcoat_factor: f64,
+    clearcoat_roughness_factor: f64,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SheenJson
+{
+    sheen_color_factor: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmissiveStrengthJson
+{
+    #[serde(default = "default_emissive_strength")]
+    emissive_strength: f64,
+}
+
+fn default_ior() -> f64
+{
+    1.5
+}
+
+fn default_emissive_strength() -> f64
+{
+    1.0
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::bsdf::Bsdf;
+
+    const ASSET: &str = r#"{
+        "asset": { "version": "2.0" },
+        "materials": [
+            {
+                "name": "glass",
+                "pbrMetallicRoughness": { "baseColorFactor": [0.5, 0.25, 1.0, 1.0], "metallicFactor": 0.0, "roughnessFactor": 0.125 },
+                "emissiveFactor": [1.0, 0.5, 0.0],
+                "extensions": {
+                    "KHR_materials_transmission": { "transmissionFactor": 0.75 },
+                    "KHR_materials_ior": { "ior": 1.25 },
+                    "KHR_materials_emissive_strength": { "emissiveStrength": 2.0 },
+                    "KHR_materials_sheen": { "sheenColorFactor": [0.5, 0.25, 0.25] }
+                }
+            },
+            {}
+        ]
+    }"#;
+
+    fn principled(material: &GltfMaterial) -> Principled
+    {
+        match material.to_material().bsdf
+        {
+            Bsdf::Principled(principled) => principled,
+            bsdf => panic!("expected a principled material, got {:?}", bsdf),
+        }
+    }
+
+    #[test]
+    fn extensions_map_onto_principled_parameters()
+    {
+        let materials = parse(ASSET).unwrap();
+        let glass = principled(&materials[0]);
+
+        assert_eq!(materials[0].name, "glass");
+        assert_eq!(glass.base_color, RGB::new(0.5, 0.25, 1.0));
+        assert_eq!(glass.metallic, 0.0);
+        assert_eq!(glass.roughness, 0.125);
+        assert_eq!(glass.transmission, 0.75);
+        assert_eq!(glass.r_index, 1.25);
+        assert_eq!(glass.sheen, 0.5);
+        assert_eq!(glass.sheen_tint, 1.0);
+        assert_eq!(materials[0].to_material().emission, RGB::new(2.0, 1.0, 0.0));
+
+        // (1.25 - 1)² / (1.25 + 1)² reflected head-on, against 8% for a specular of 1.
+        assert!((glass.specular - 0.154_321).abs() < 1e-4, "{}", glass.specular);
+    }
+
+    #[test]
+    fn missing_values_take_the_gltf_defaults()
+    {
+        let materials = parse(ASSET).unwrap();
+        let default = principled(&materials[1]);
+
+        assert_eq!(materials[1].name, "1");
+        assert_eq!(default.base_color, RGB::gray(1.0));
+        assert_eq!(default.metallic, 1.0);
+        assert_eq!(default.roughness, 1.0);
+        assert_eq!(default.transmission, 0.0);
+        assert_eq!(default.r_index, 1.5);
+        assert_eq!(default.specular, 0.5);
+        assert_eq!(default.sheen, 0.0);
+    }
+
+    #[test]
+    fn binary_assets_start_with_their_json()
+    {
+        let json = br#"{"materials":[]}  "#;
+        let mut glb = b"glTF".to_vec();
+
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(20 + json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(json);
+
+        assert_eq!(glb_json(&glb).unwrap(), &json[..]);
+        assert!(glb_json(&glb[..24]).is_err());
+
+        glb[4] = 1;
+
+        assert!(glb_json(&glb).is_err());
+    }
+
+    #[test]
+    fn syntax_errors_report_their_line()
+    {
+        assert_eq!(parse("{\n\"materials\": [\n{ \"name\": 1 }\n]\n}").unwrap_err().0, 3);
+    }
+}
@@ -0,0 +1,67 @@
+//! Importers for external asset formats.
+
+pub mod obj;
+pub mod mtl;
+pub mod gltf;
+
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum ImportError
+{
+    Io(PathBuf, std::io::Error),
+    /// A malformed line, `line` is 1-based.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ImportError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            ImportError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            ImportError::Parse { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Splits a line into its keyword and arguments, skipping blank lines and `#` comments.
+fn tokenize(line: &str) -> Option<(&str, Vec<&str>)>
+{
+    let line = match line.find('#')
+    {
+        Some(i) => &line[..i],
+        None => line,
+    };
+
+    let mut tokens = line.split_whitespace();
+    let keyword = tokens.next()?;
+
+    Some((keyword, tokens.collect()))
+}
+
+/// Parses exactly `N` floats, as used for vectors and colors.
+fn parse_floats<const N: usize>(args: &[&str]) -> Result<[f64; N], String>
+{
+    if args.len() < N
+    {
+        return Err(format!("expected {} numbers, found {}", N, args.len()));
+    }
+
+    let mut values = [0.0; N];
+
+    for (value, arg) in values.iter_mut().zip(args)
+    {
+        *value = arg.parse().map_err(|_| format!("invalid number '{}'", arg))?;
+    }
+
+    Ok(values)
+}
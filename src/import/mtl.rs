@@ -0,0 +1,290 @@
+//! Wavefront MTL material libraries.
+
+use crate::{
+    Material,
+    RGB,
+    bsdf::Principled,
+};
+use super::{
+    ImportError,
+    tokenize,
+    parse_floats,
+};
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The subset of MTL statements that map onto a `Material`.
+#[derive(Debug, Clone)]
+pub struct MtlMaterial
+{
+    pub name: String,
+    /// Diffuse color, `Kd`.
+    pub diffuse: [f64; 3],
+    /// Specular color, `Ks`.
+    pub specular: [f64; 3],
+    /// Specular exponent, `Ns`, 0 to 1000.
+    pub shininess: f64,
+    /// Dissolve, `d`, or one minus `Tr`.
+    pub dissolve: f64,
+    /// Optical density, `Ni`.
+    pub optical_density: f64,
+    /// Emissive color, `Ke`.
+    pub emissive: [f64; 3],
+    /// Roughness, `Pr`, from the PBR extension like all the following.
+    pub roughness: Option<f64>,
+    /// Metallic, `Pm`.
+    pub metallic: Option<f64>,
+    /// Sheen, `Ps`.
+    pub sheen: Option<f64>,
+    /// Clearcoat thickness, `Pc`.
+    pub clearcoat: Option<f64>,
+    /// Clearcoat roughness, `Pcr`.
+    pub clearcoat_roughness: Option<f64>,
+}
+
+impl MtlMaterial
+{
+    pub fn new(name: &str) -> MtlMaterial
+    {
+        MtlMaterial {
+            name: name.to_string(),
+            diffuse: [0.8, 0.8, 0.8],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 0.0,
+            dissolve: 1.0,
+            optical_density: 1.0,
+            emissive: [0.0, 0.0, 0.0],
+            roughness: None,
+            metallic: None,
+            sheen: None,
+            clearcoat: None,
+            clearcoat_roughness: None,
+        }
+    }
+
+    /// Whether any statement of the PBR extension is given.
+    pub fn is_pbr(&self) -> bool
+    {
+        self.roughness.is_some()
+            || self.metallic.is_some()
+            || self.sheen.is_some()
+            || self.clearcoat.is_some()
+            || self.clearcoat_roughness.is_some()
+    }
+
+    /// Converts to a `Material`.
+    ///
+    /// Materials using the PBR extension become principled materials, with `Kd` as the base
+    /// color and the dissolve as transmission. Otherwise transparent materials become glass,
+    /// using `Ni` unless it is left at 1, and materials with only a specular color become metals.
+    /// Glossy materials with both colors become principled ones, with a specular strength
+    /// following `Ks`. Everything else is diffuse. The roughness follows from the specular
+    /// exponent.
+    pub fn to_material(&self) -> Material
+    {
+        let max = |c: [f64; 3]| c[0].max(c[1]).max(c[2]);
+
+        // Blinn-Phong exponent to GGX alpha, which is the square of the roughness.
+        let roughness = (2.0 / (self.shininess.max(0.0) + 2.0)).powf(0.25) as f32;
+
+        let material = if self.is_pbr()
+        {
+            let defaults = Principled::default();
+
+            Material::principled(Principled {
+                base_color: rgb(self.diffuse),
+                metallic: self.metallic.unwrap_or(0.0) as f32,
+                roughness: self.roughness.map_or(roughness, |r| r as f32),
+                sheen: self.sheen.unwrap_or(0.0) as f32,
+                clearcoat: self.clearcoat.unwrap_or(0.0) as f32,
+                clearcoat_roughness: self.clearcoat_roughness.map_or(defaults.clearcoat_roughness, |r| r as f32),
+                transmission: (1.0 - self.dissolve) as f32,
+                r_index: if self.optical_density > 1.0 { self.optical_density as f32 } else { defaults.r_index },
+                ..defaults
+            })
+        }
+        else if self.dissolve < 1.0
+        {
+            let r_index = if self.optical_density > 1.0 { self.optical_density } else { 1.5 };
+
+            Material::dielectric(r_index as f32, roughness)
+        }
+        else if max(self.diffuse) == 0.0 && max(self.specular) > 0.0
+        {
+            Material::conductor(rgb(self.specular), roughness)
+        }
+        else if max(self.specular) > 0.0
+        {
+            // A `specular` of 1 reflects 8% head-on, about as much as Phong highlights get on
+            // non-metals.
+            Material::principled(Principled {
+                base_color: rgb(self.diffuse),
+                roughness,
+                specular: (max(self.specular) / 0.08).min(1.0) as f32,
+                ..Principled::default()
+            })
+        }
+        else
+        {
+            Material::diffuse(rgb(self.diffuse))
+        };
+
+        material.with_emission(rgb(self.emissive)).with_name(&self.name)
+    }
+}
+
+/// Loads all materials of an MTL file, keyed by name.
+pub fn load(path: &Path) -> Result<HashMap<String, MtlMaterial>, ImportError>
+{
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| ImportError::Io(path.to_path_buf(), err))?;
+
+    parse(&source).map_err(|(line, message)| ImportError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    })
+}
+
+/// Parses MTL source, errors carry the 1-based line number.
+pub fn parse(source: &str) -> Result<HashMap<String, MtlMaterial>, (usize, String)>
+{
+    let mut materials = HashMap::new();
+    let mut current: Option<MtlMaterial> = None;
+
+    for (number, line) in source.lines().enumerate()
+    {
+        let (keyword, args) = match tokenize(line)
+        {
+            Some(tokens) => tokens,
+            None => continue,
+        };
+
+        let error = |message: String| (number + 1, message);
+
+        if keyword == "newmtl"
+        {
+            if args.is_empty()
+            {
+                return Err(error("newmtl without a name".to_string()));
+            }
+
+            if let Some(material) = current.take()
+            {
+                materials.insert(material.name.clone(), material);
+            }
+
+            current = Some(MtlMaterial::new(&args.join(" ")));
+            continue;
+        }
+
+        let material = match current.as_mut()
+        {
+            Some(material) => material,
+            None => return Err(error(format!("'{}' before any newmtl", keyword))),
+        };
+
+        match keyword
+        {
+            "Kd" => material.diffuse = parse_floats::<3>(&args).map_err(error)?,
+            "Ks" => material.specular = parse_floats::<3>(&args).map_err(error)?,
+            "Ke" => material.emissive = parse_floats::<3>(&args).map_err(error)?,
+            "Ns" => material.shininess = parse_floats::<1>(&args).map_err(error)?[0],
+            "Ni" => material.optical_density = parse_floats::<1>(&args).map_err(error)?[0],
+            // `d -halo 0.5` is valid, only the factor matters here.
+            "d"  => material.dissolve = parse_floats::<1>(&args[args.len().saturating_sub(1)..]).map_err(error)?[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats::<1>(&args).map_err(error)?[0],
+            "Pr" => material.roughness = Some(parse_floats::<1>(&args).map_err(error)?[0]),
+            "Pm" => material.metallic = Some(parse_floats::<1>(&args).map_err(error)?[0]),
+            "Ps" => material.sheen = Some(parse_floats::<1>(&args).map_err(error)?[0]),
+            "Pc" => material.clearcoat = Some(parse_floats::<1>(&args).map_err(error)?[0]),
+            "Pcr" => material.clearcoat_roughness = Some(parse_floats::<1>(&args).map_err(error)?[0]),
+            // Ambient color, illumination model and texture maps are not supported.
+            _ => (),
+        }
+    }
+
+    if let Some(material) = current
+    {
+        materials.insert(material.name.clone(), material);
+    }
+
+    Ok(materials)
+}
+
+fn rgb(c: [f64; 3]) -> RGB
+{
+    RGB::new(c[0] as f32, c[1] as f32, c[2] as f32)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::bsdf::Bsdf;
+
+    fn material(statements: &str) -> Material
+    {
+        parse(&format!("newmtl test\n{}", statements)).unwrap()["test"].to_material()
+    }
+
+    #[test]
+    fn phong_materials_map_onto_bsdfs()
+    {
+        match material("Kd 0.5 0.25 0.125\n").bsdf
+        {
+            Bsdf::Lambertian(lambertian) => assert_eq!(lambertian.color, RGB::new(0.5, 0.25, 0.125)),
+            bsdf => panic!("expected a diffuse material, got {:?}", bsdf),
+        }
+
+        match material("Kd 0 0 0\nKs 0.9 0.9 0.9\nNs 1000\n").bsdf
+        {
+            Bsdf::Conductor(conductor) => assert!(conductor.roughness < 0.3),
+            bsdf => panic!("expected a conductor, got {:?}", bsdf),
+        }
+
+        match material("d 0.5\nNi 1.33\n").bsdf
+        {
+            Bsdf::Dielectric(dielectric) => assert_eq!(dielectric.r_index, 1.33),
+            bsdf => panic!("expected a dielectric, got {:?}", bsdf),
+        }
+    }
+
+    #[test]
+    fn glossy_materials_keep_their_highlights()
+    {
+        match material("Kd 0.6 0.1 0.1\nKs 0.04 0.04 0.04\nNs 200\n").bsdf
+        {
+            Bsdf::Principled(principled) => {
+                assert_eq!(principled.base_color, RGB::new(0.6, 0.1, 0.1));
+                assert_eq!(principled.metallic, 0.0);
+                assert_eq!(principled.specular, 0.5);
+                assert!(principled.roughness < 0.5);
+            },
+            bsdf => panic!("expected a principled material, got {:?}", bsdf),
+        }
+    }
+
+    #[test]
+    fn pbr_statements_make_principled_materials()
+    {
+        match material("Kd 1 1 1\nPm 1\nPr 0.25\nd 0.75\n").bsdf
+        {
+            Bsdf::Principled(principled) => {
+                assert_eq!(principled.metallic, 1.0);
+                assert_eq!(principled.roughness, 0.25);
+                assert_eq!(principled.transmission, 0.25);
+            },
+            bsdf => panic!("expected a principled material, got {:?}", bsdf),
+        }
+    }
+
+    #[test]
+    fn errors_report_their_line()
+    {
+        assert_eq!(parse("Kd 1 1 1\n").unwrap_err().0, 1);
+        assert_eq!(parse("newmtl a\n\nKd 1 1\n").unwrap_err().0, 3);
+        assert_eq!(parse("newmtl\n").unwrap_err().0, 1);
+    }
+}
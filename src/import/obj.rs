@@ -0,0 +1,422 @@
+//! Wavefront OBJ meshes.
+
+use crate::{
+    Material,
+    Mesh,
+    RGB,
+    math::Vec3,
+    object::{ MeshTriangle, VertexBuffers },
+};
+use super::{
+    ImportError,
+    mtl::{ self, MtlMaterial },
+    tokenize,
+    parse_floats,
+};
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A parsed OBJ file. All groups index into the same `buffers`.
+#[derive(Debug, Clone)]
+pub struct ObjFile
+{
+    pub buffers: Arc<VertexBuffers>,
+    pub groups: Vec<ObjGroup>,
+    /// Materials from every `mtllib`, keyed by name.
+    pub materials: HashMap<String, MtlMaterial>,
+    /// Problems that did not stop the file from loading, like missing MTL libraries.
+    pub warnings: Vec<String>,
+}
+
+/// The faces sharing one group name and material.
+#[derive(Debug, Clone)]
+pub struct ObjGroup
+{
+    pub name: String,
+    pub material: Option<String>,
+    pub triangles: Vec<MeshTriangle>,
+}
+
+impl ObjFile
+{
+    /// Creates one mesh per group, using the group's MTL material if it has one and
+    /// `default_material` otherwise.
+    pub fn meshes(&self, default_material: Material) -> Vec<Mesh>
+    {
+        self.groups
+            .iter()
+            .filter(|group| !group.triangles.is_empty())
+            .map(|group| {
+                let material = group.material
+                    .as_ref()
+                    .and_then(|name| self.materials.get(name))
+                    .map(|material| material.to_material())
+                    .unwrap_or_else(|| default_material.clone());
+
+                Mesh::new(self.buffers.clone(), group.triangles.clone(), material)
+            })
+            .collect()
+    }
+
+    /// Creates a single mesh of every group, ignoring the MTL materials.
+    pub fn mesh_with_material(&self, material: Material) -> Mesh
+    {
+        let triangles = self.groups
+            .iter()
+            .flat_map(|group| group.triangles.iter().copied())
+            .collect();
+
+        Mesh::new(self.buffers.clone(), triangles, material)
+    }
+}
+
+/// The material used for faces without a `usemtl`.
+pub fn default_material() -> Material
+{
+    Material::diffuse(RGB::gray(0.8))
+}
+
+/// Loads an OBJ file along with the MTL libraries it references, which are looked up
+/// relative to the OBJ file. Libraries that fail to load end up in `warnings`, and the
+/// faces using their materials get the default material.
+pub fn load(path: &Path) -> Result<ObjFile, ImportError>
+{
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| ImportError::Io(path.to_path_buf(), err))?;
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let (mut obj, libraries) = parse(&source).map_err(|(line, message)| ImportError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    })?;
+
+    for library in libraries.iter()
+    {
+        match mtl::load(&dir.join(library))
+        {
+            Ok(materials) => obj.materials.extend(materials),
+            Err(err) => obj.warnings.push(format!("{}, using the default material instead", err)),
+        }
+    }
+
+    Ok(obj)
+}
+
+/// Parses OBJ source, returning the file and the names of the `mtllib`s it references.
+/// Errors carry the 1-based line number.
+pub fn parse(source: &str) -> Result<(ObjFile, Vec<String>), (usize, String)>
+{
+    let mut buffers = VertexBuffers::default();
+    let mut groups = vec![ObjGroup {
+        name: "default".to_string(),
+        material: None,
+        triangles: vec![],
+    }];
+    let mut libraries = vec![];
+
+    for (number, line) in source.lines().enumerate()
+    {
+        let (keyword, args) = match tokenize(line)
+        {
+            Some(tokens) => tokens,
+            None => continue,
+        };
+
+        let error = |message: String| (number + 1, message);
+
+        match keyword
+        {
+            "v"  => buffers.positions.push(vec3(parse_floats::<3>(&args).map_err(error)?)),
+            "vn" => buffers.normals.push(vec3(parse_floats::<3>(&args).map_err(error)?).normalized()),
+            // One-dimensional textures only give `u`, and a third component is ignored.
+            "vt" => {
+                let u = parse_floats::<1>(&args).map_err(error)?[0];
+                let v = match args.get(1)
+                {
+                    Some(_) => parse_floats::<1>(&args[1..]).map_err(error)?[0],
+                    None => 0.0,
+                };
+
+                buffers.uvs.push((u, v));
+            },
+            "f" => {
+                if args.len() < 3
+                {
+                    return Err(error(format!("face with {} vertices, expected at least 3", args.len())));
+                }
+
+                let vertices = args
+                    .iter()
+                    .map(|arg| parse_vertex(arg, &buffers))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+
+                let has_uvs = vertices.iter().all(|v| v.1.is_some());
+                let has_normals = vertices.iter().all(|v| v.2.is_some());
+
+                let positions: Vec<_> = vertices.iter().map(|v| buffers.positions[v.0]).collect();
+                let group = groups.last_mut().unwrap();
+
+                for [a, b, c] in triangulate(&positions)
+                {
+                    let (a, b, c) = (vertices[a], vertices[b], vertices[c]);
+
+                    group.triangles.push(MeshTriangle {
+                        positions: [a.0, b.0, c.0],
+                        uvs: if has_uvs { Some([a.1.unwrap(), b.1.unwrap(), c.1.unwrap()]) } else { None },
+                        normals: if has_normals { Some([a.2.unwrap(), b.2.unwrap(), c.2.unwrap()]) } else { None },
+                    });
+                }
+            },
+            "g" | "o" => {
+                let material = groups.last().unwrap().material.clone();
+
+                groups.push(ObjGroup {
+                    name: if args.is_empty() { "default".to_string() } else { args.join(" ") },
+                    material,
+                    triangles: vec![],
+                });
+            },
+            "usemtl" => {
+                if args.is_empty()
+                {
+                    return Err(error("usemtl without a name".to_string()));
+                }
+
+                let name = groups.last().unwrap().name.clone();
+
+                groups.push(ObjGroup {
+                    name,
+                    material: Some(args.join(" ")),
+                    triangles: vec![],
+                });
+            },
+            "mtllib" => libraries.extend(args.iter().map(|arg| arg.to_string())),
+            // Smoothing groups, lines, points and free-form geometry are not supported.
+            _ => (),
+        }
+    }
+
+    groups.retain(|group| !group.triangles.is_empty());
+
+    Ok((ObjFile {
+        buffers: Arc::new(buffers),
+        groups,
+        materials: HashMap::new(),
+        warnings: vec![],
+    }, libraries))
+}
+
+/// Parses a face vertex, `v`, `v/vt`, `v//vn` or `v/vt/vn`, into zero-based indices.
+fn parse_vertex(arg: &str, buffers: &VertexBuffers) -> Result<(usize, Option<usize>, Option<usize>), String>
+{
+    let mut parts = arg.split('/');
+
+    let position = resolve_index(parts.next(), buffers.positions.len(), "position")?
+        .ok_or_else(|| format!("face vertex '{}' has no position", arg))?;
+    let uv = resolve_index(parts.next(), buffers.uvs.len(), "texture coordinate")?;
+    let normal = resolve_index(parts.next(), buffers.normals.len(), "normal")?;
+
+    if parts.next().is_some()
+    {
+        return Err(format!("malformed face vertex '{}'", arg));
+    }
+
+    Ok((position, uv, normal))
+}
+
+/// Turns a 1-based, or negative relative, OBJ index into a zero-based one.
+fn resolve_index(part: Option<&str>, len: usize, what: &str) -> Result<Option<usize>, String>
+{
+    let part = match part
+    {
+        Some(part) if !part.is_empty() => part,
+        _ => return Ok(None),
+    };
+
+    let index: i64 = part.parse().map_err(|_| format!("invalid {} index '{}'", what, part))?;
+
+    let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+
+    if index == 0 || resolved < 0 || resolved >= len as i64
+    {
+        return Err(format!("{} index {} is out of range, {} defined so far", what, index, len));
+    }
+
+    Ok(Some(resolved as usize))
+}
+
+/// Triangulates a simple polygon by ear clipping in the plane it is most aligned with,
+/// returning indices into `points`. Falls back to a fan if no ear can be found.
+fn triangulate(points: &[Vec3]) -> Vec<[usize; 3]>
+{
+    if points.len() == 3
+    {
+        return vec![[0, 1, 2]];
+    }
+
+    // Newell's method, robust for non-planar and concave polygons.
+    let mut normal = Vec3::zero();
+
+    for i in 0..points.len()
+    {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+
+        normal = normal + Vec3::new(
+            (a.y - b.y) * (a.z + b.z),
+            (a.z - b.z) * (a.x + b.x),
+            (a.x - b.x) * (a.y + b.y),
+        );
+    }
+
+    let abs = Vec3::new(normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let (u, v, flip) = if abs.x > abs.y && abs.x > abs.z
+    {
+        (1, 2, normal.x < 0.0)
+    }
+    else if abs.y > abs.z
+    {
+        (2, 0, normal.y < 0.0)
+    }
+    else
+    {
+        (0, 1, normal.z < 0.0)
+    };
+
+    let project = |p: Vec3| if flip { (p[v], p[u]) } else { (p[u], p[v]) };
+    let cross = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| {
+        (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+    };
+
+    let projected: Vec<_> = points.iter().map(|&p| project(p)).collect();
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3
+    {
+        let n = remaining.len();
+
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            let (pa, pb, pc) = (projected[a], projected[b], projected[c]);
+
+            if cross(pa, pb, pc) <= 0.0
+            {
+                return false;
+            }
+
+            remaining.iter().all(|&other| {
+                if other == a || other == b || other == c
+                {
+                    return true;
+                }
+
+                let p = projected[other];
+
+                cross(pa, pb, p) < 0.0 || cross(pb, pc, p) < 0.0 || cross(pc, pa, p) < 0.0
+            })
+        });
+
+        match ear
+        {
+            Some(i) => {
+                triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+                remaining.remove(i);
+            },
+            None => {
+                for i in 1..n - 1
+                {
+                    triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+                }
+
+                return triangles;
+            },
+        }
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    triangles
+}
+
+fn vec3(v: [f64; 3]) -> Vec3
+{
+    Vec3::new(v[0], v[1], v[2])
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn texture_coordinates_take_one_to_three_components()
+    {
+        let (obj, _) = parse("vt 0.25\nvt 0.5 0.75\nvt 0.125 0.375 1\n").unwrap();
+
+        assert_eq!(obj.buffers.uvs, vec![(0.25, 0.0), (0.5, 0.75), (0.125, 0.375)]);
+        assert_eq!(parse("vt\n").unwrap_err().0, 1);
+        assert_eq!(parse("vt 0.5 v\n").unwrap_err().0, 1);
+    }
+
+    #[test]
+    fn faces_are_triangulated_per_material()
+    {
+        let source = "\
+            mtllib a.mtl b.mtl\n\
+            v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+            vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+            f 1/1 2/2 3/3 4/4\n\
+            usemtl red\n\
+            f -4 -3 -2\n";
+        let (obj, libraries) = parse(source).unwrap();
+
+        assert_eq!(libraries, vec!["a.mtl", "b.mtl"]);
+        assert_eq!(obj.groups.len(), 2);
+        assert_eq!(obj.groups[0].material, None);
+        assert_eq!(obj.groups[0].triangles.len(), 2);
+        assert!(obj.groups[0].triangles.iter().all(|triangle| triangle.uvs.is_some()));
+        assert_eq!(obj.groups[1].material.as_deref(), Some("red"));
+        assert_eq!(obj.groups[1].triangles[0].positions, [0, 1, 2]);
+        assert_eq!(obj.groups[1].triangles[0].uvs, None);
+    }
+
+    #[test]
+    fn bad_faces_report_their_line()
+    {
+        let vertices = "v 0 0 0\nv 1 0 0\nv 1 1 0\n";
+
+        for face in ["f 1 2\n", "f 1 2 4\n", "f 0 1 2\n", "f 1 2 3/1\n", "f 1 2 3//1/1\n", "f 1 2 x\n"].iter()
+        {
+            let (line, message) = parse(&format!("{}{}", vertices, face)).unwrap_err();
+
+            assert_eq!(line, 4, "{}", message);
+        }
+    }
+
+    #[test]
+    fn missing_libraries_fall_back_to_the_default_material()
+    {
+        let dir = std::env::temp_dir().join(format!("raytracer-obj-{}", std::process::id()));
+        let path = dir.join("mesh.obj");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n").unwrap();
+
+        let obj = load(&path);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let obj = obj.unwrap();
+        let meshes = obj.meshes(default_material());
+
+        assert!(obj.materials.is_empty());
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(obj.warnings.len(), 1);
+        assert!(obj.warnings[0].contains("missing.mtl"), "{}", obj.warnings[0]);
+    }
+}
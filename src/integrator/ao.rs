@@ -0,0 +1,58 @@
+use crate::{
+    Scene,
+    Ray,
+    RGB,
+    math::{ Vec3, Rng },
+    medium::MediumStack,
+};
+use super::Surface;
+
+/// Ambient occlusion: how much of the hemisphere above the first hit is open, white where
+/// nothing is in the way within `distance` and black in tight corners. Rays that miss the
+/// scene are black.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion
+{
+    /// How far away surfaces still block the view of the sky, in world units.
+    pub distance: f64,
+}
+
+impl Default for AmbientOcclusion
+{
+    fn default() -> AmbientOcclusion
+    {
+        AmbientOcclusion {
+            distance: 1.0,
+        }
+    }
+}
+
+impl AmbientOcclusion
+{
+    pub fn radiance(&self, scene: &Scene, ray: Ray, rng: &mut Rng) -> RGB
+    {
+        let record = match scene.hit(ray, rng)
+        {
+            Some(record) => record,
+            None => return RGB::black(),
+        };
+
+        let surface = Surface::new(record, ray, MediumStack::new());
+
+        // Cosine weighted directions make the fraction of open ones the cosine weighted
+        // visibility, which looks like diffuse lighting under an overcast sky.
+        let normal = if surface.normal.dot(surface.wo) < 0.0 { -surface.normal } else { surface.normal };
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        let local = Vec3::random_cosine(rng);
+        let direction = tangent * local.x + bitangent * local.y + normal * local.z;
+
+        if scene.occluded(Ray::new(surface.point(), direction), self.distance, rng)
+        {
+            RGB::black()
+        }
+        else
+        {
+            RGB::gray(1.0)
+        }
+    }
+}
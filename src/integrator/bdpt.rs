@@ -0,0 +1,804 @@
+use crate::{
+    Scene,
+    Ray,
+    RGB,
+    Sky,
+    math::{ Vec3, Rng },
+    film::FilmSample,
+    light::{ Light, Emission },
+    medium::MediumStack,
+};
+use super::{ Surface, Scattering, Interaction, step, transmittance };
+
+use rand::Rng as _;
+
+/// Bidirectional path tracing: traces one path from the camera and one from a light, and
+/// connects every vertex of the first to every vertex of the second. Each connection is a
+/// different way of finding the same light, weighted against the others with the balance
+/// heuristic. Caustics seen through glass and rooms lit through small openings, which paths
+/// from the camera alone hardly ever find, converge much faster. Points where volumes scatter
+/// the paths are vertices like surfaces, so light shafts seen through glass are found too.
+///
+/// Light paths that connect straight to the camera land on arbitrary pixels and are splatted
+/// onto the film, which only perspective cameras support.
+#[derive(Debug, Clone, Copy)]
+pub struct Bidirectional;
+
+/// Anything light paths start from.
+#[derive(Clone, Copy)]
+enum Emitter<'a>
+{
+    Light(&'a Light),
+    Sky,
+}
+
+// Most vertices are surfaces, boxing them would only add allocations.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Copy)]
+enum Kind<'a>
+{
+    Camera,
+    Emitter(Emitter<'a>),
+    Surface(Surface<'a>),
+    Medium(Scattering<'a>),
+}
+
+/// A vertex of a camera or light path.
+#[derive(Clone, Copy)]
+struct Vertex<'a>
+{
+    kind: Kind<'a>,
+    /// Where the vertex is. Vertices on the sky or a distant light are anywhere along the
+    /// direction light arrives from.
+    point: Vec3,
+    /// The geometric normal, `None` for vertices not on a surface.
+    normal: Option<Vec3>,
+    /// The light or importance carried to the vertex, divided by the path's density.
+    beta: RGB,
+    /// The light emitted towards the previous vertex, for lights found by camera paths.
+    emitted: RGB,
+    /// Whether the path was scattered through a perfectly sharp lobe here, which no
+    /// connection can reach.
+    delta: bool,
+    /// Density over area of the path reaching the vertex from the previous one.
+    pdf_fwd: f64,
+    /// Density over area of the opposite path reaching the vertex from the next one.
+    pdf_rev: f64,
+}
+
+/// The lights and the sky light paths are started from, all equally likely.
+struct Emitters<'a>
+{
+    scene: &'a Scene,
+    /// Whether the sky gives off any light.
+    sky: bool,
+    center: Vec3,
+    radius: f64,
+}
+
+/// How far short of their end connections are checked for occluders, so they do not hit the
+/// surfaces they connect.
+const SHADOW_EPSILON: f64 = 0.001;
+
+impl Bidirectional
+{
+    pub fn radiance(
+        &self,
+        scene: &Scene,
+        ray: Ray,
+        max_bounces: u32,
+        rng: &mut Rng,
+        splats: &mut Vec<FilmSample>,
+    ) -> RGB {
+        let emitters = Emitters::new(scene);
+        let max_bounces = max_bounces as usize;
+
+        let mut camera_path = Vec::with_capacity(max_bounces + 2);
+        camera_path.push(Vertex::new(Kind::Camera, ray.origin, None, RGB::gray(1.0)));
+        let pdf = scene.camera.pdf_direction(ray.dir);
+        random_walk(scene, ray, RGB::gray(1.0), pdf, max_bounces + 1, false, rng, &mut camera_path);
+
+        let mut light_path = Vec::with_capacity(max_bounces + 1);
+        light_walk(&emitters, max_bounces, rng, &mut light_path);
+
+        let mut color = RGB::black();
+
+        for t in 1..=camera_path.len()
+        {
+            for s in 0..=light_path.len()
+            {
+                // The camera itself cannot be hit, so connecting a light straight to it is
+                // the one way to find paths without any bounces.
+                if s + t < 2 || s + t - 2 > max_bounces || (s == 1 && t == 1)
+                {
+                    continue;
+                }
+
+                let (light, splat) = connect(&emitters, &light_path, &camera_path, s, t, rng);
+
+                if light.is_black()
+                {
+                    continue;
+                }
+
+                match splat
+                {
+                    Some((x, y)) => splats.push(FilmSample { x, y, color: light }),
+                    None => color += light,
+                }
+            }
+        }
+
+        color
+    }
+}
+
+impl<'a> Emitters<'a>
+{
+    fn new(scene: &'a Scene) -> Emitters<'a>
+    {
+        let (center, radius) = scene.bounding_sphere();
+
+        Emitters {
+            scene,
+            sky: !matches!(scene.sky, Sky::Color(color) if color.is_black()),
+            center,
+            radius,
+        }
+    }
+
+    fn count(&self) -> usize
+    {
+        self.scene.lights.len() + self.sky as usize
+    }
+
+    /// The probability of picking any one emitter.
+    fn choice_pdf(&self) -> f64
+    {
+        if self.count() > 0 { 1.0 / self.count() as f64 } else { 0.0 }
+    }
+
+    fn pick(&self, rng: &mut Rng) -> Option<Emitter<'a>>
+    {
+        if self.count() == 0
+        {
+            return None;
+        }
+
+        let index = rng.gen_range(0, self.count());
+
+        Some(self.scene.lights.get(index).map_or(Emitter::Sky, Emitter::Light))
+    }
+
+    fn emit(&self, emitter: Emitter, rng: &mut Rng) -> Option<Emission>
+    {
+        match emitter
+        {
+            Emitter::Light(light) => Some(light.emit(self.center, self.radius, rng)),
+            Emitter::Sky => self.scene.sky.emit(self.center, self.radius, rng),
+        }
+    }
+
+    /// The density over solid angle of a light path leaving the sky along `direction`.
+    /// Distant lights are too sharp to be found any other way and do not count.
+    fn infinite_pdf(&self, direction: Vec3) -> f64
+    {
+        if self.sky { self.choice_pdf() * self.scene.sky.emitted_pdf(-direction) } else { 0.0 }
+    }
+}
+
+impl<'a> Vertex<'a>
+{
+    fn new(kind: Kind<'a>, point: Vec3, normal: Option<Vec3>, beta: RGB) -> Vertex<'a>
+    {
+        Vertex {
+            kind,
+            point,
+            normal,
+            beta,
+            emitted: RGB::black(),
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    /// Whether the vertex is infinitely far away, on the sky or a distant light.
+    fn is_infinite(&self) -> bool
+    {
+        match self.kind
+        {
+            Kind::Emitter(Emitter::Sky) => true,
+            Kind::Emitter(Emitter::Light(light)) => light.is_distant(),
+            _ => false,
+        }
+    }
+
+    /// Whether the vertex is on a light that is a single point or direction.
+    fn is_delta_light(&self) -> bool
+    {
+        matches!(self.kind, Kind::Emitter(Emitter::Light(light)) if light.is_delta())
+    }
+
+    /// Whether paths can be connected to the vertex, which scatters light into any direction.
+    fn connectible(&self) -> bool
+    {
+        match &self.kind
+        {
+            Kind::Surface(surface) => !surface.bsdf.is_specular(),
+            Kind::Medium(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The cosine factor of light leaving or arriving at the vertex along `direction`, 1 off
+    /// surfaces.
+    fn cos(&self, direction: Vec3) -> f64
+    {
+        match &self.kind
+        {
+            Kind::Surface(surface) => direction.dot(surface.normal).abs(),
+            _ => 1.0,
+        }
+    }
+
+    /// The media a connection leaving the vertex along `direction` travels through.
+    fn media_towards(&self, direction: Vec3) -> MediumStack<'a>
+    {
+        match &self.kind
+        {
+            Kind::Surface(surface) => surface.media_towards(direction),
+            Kind::Medium(scattering) => scattering.media,
+            _ => MediumStack::new(),
+        }
+    }
+
+    /// The unit vector from the vertex towards `other`, for infinite vertices the direction
+    /// their light travels in.
+    fn direction_to(&self, other: &Vertex) -> Vec3
+    {
+        (other.point - self.point).normalized()
+    }
+
+    /// The BSDF or phase function at the vertex for scattering between the previous vertex and
+    /// `next`. Light paths carry light in the opposite direction to the one the BSDF is written
+    /// for.
+    fn f(&self, next: &Vertex, light_path: bool) -> RGB
+    {
+        let surface = match &self.kind
+        {
+            Kind::Surface(surface) => surface,
+            Kind::Medium(scattering) => {
+                return RGB::gray(scattering.volume.phase(scattering.wo, self.direction_to(next)) as f32);
+            },
+            _ => return RGB::black(),
+        };
+
+        let wi = self.direction_to(next);
+        let f = surface.bsdf.eval(surface.wo, wi, surface.normal);
+
+        if light_path
+        {
+            f * surface.adjoint_correction(wi)
+        }
+        else
+        {
+            f
+        }
+    }
+
+    /// Converts a density over solid angle at the vertex into one over area at `next`.
+    fn convert_density(&self, pdf: f64, next: &Vertex) -> f64
+    {
+        if next.is_infinite()
+        {
+            return pdf;
+        }
+
+        let to_next = next.point - self.point;
+        let distance_sq = to_next.dot(to_next);
+
+        if distance_sq == 0.0
+        {
+            return 0.0;
+        }
+
+        let cos = next.normal.map_or(1.0, |normal| normal.dot(to_next).abs() / distance_sq.sqrt());
+
+        pdf * cos / distance_sq
+    }
+
+    /// The density over area of a path arriving from `prev` being continued to `next`.
+    fn pdf(&self, emitters: &Emitters, prev: Option<&Vertex>, next: &Vertex) -> f64
+    {
+        let pdf = match &self.kind
+        {
+            Kind::Emitter(_) => return self.pdf_light(emitters, next),
+            Kind::Camera => emitters.scene.camera.pdf_direction(self.direction_to(next)),
+            Kind::Surface(surface) => match prev
+            {
+                Some(prev) => surface.bsdf.pdf(self.direction_to(prev), self.direction_to(next), surface.normal),
+                None => 0.0,
+            },
+            Kind::Medium(scattering) => match prev
+            {
+                Some(prev) => scattering.volume.phase(self.direction_to(prev), self.direction_to(next)),
+                None => 0.0,
+            },
+        };
+
+        self.convert_density(pdf, next)
+    }
+
+    /// The density over area of a light path leaving this light vertex reaching `next`.
+    fn pdf_light(&self, emitters: &Emitters, next: &Vertex) -> f64
+    {
+        let to_next = next.point - self.point;
+        let distance_sq = to_next.dot(to_next);
+        let direction = to_next / distance_sq.sqrt();
+
+        let pdf = match self.kind
+        {
+            _ if self.is_infinite() => 1.0 / (std::f64::consts::PI * emitters.radius * emitters.radius),
+            Kind::Emitter(Emitter::Light(light)) => light.emit_pdf(self.point, direction).1 / distance_sq,
+            _ => 0.0,
+        };
+
+        pdf * next.normal.map_or(1.0, |normal| normal.dot(direction).abs())
+    }
+
+    /// The density of a light path starting at this light vertex, towards `next`.
+    fn pdf_light_origin(&self, emitters: &Emitters, next: &Vertex) -> f64
+    {
+        match self.kind
+        {
+            _ if self.is_infinite() => emitters.infinite_pdf(self.direction_to(next)),
+            Kind::Emitter(Emitter::Light(light)) => {
+                light.emit_pdf(self.point, self.direction_to(next)).0 * emitters.choice_pdf()
+            },
+            _ => 0.0,
+        }
+    }
+}
+
+/// Starts a path from a random light or the sky and follows it through at most `max_bounces`
+/// surfaces.
+fn light_walk<'a>(emitters: &Emitters<'a>, max_bounces: usize, rng: &mut Rng, path: &mut Vec<Vertex<'a>>)
+{
+    let emitter = match emitters.pick(rng)
+    {
+        Some(emitter) => emitter,
+        None => return,
+    };
+
+    let emission = match emitters.emit(emitter, rng)
+    {
+        Some(emission) if emission.pdf_pos > 0.0 && emission.pdf_dir > 0.0 && !emission.radiance.is_black() => emission,
+        _ => return,
+    };
+
+    let ray = emission.ray;
+    let choice_pdf = emitters.choice_pdf();
+    let cos = emission.normal.map_or(1.0, |normal| normal.dot(ray.dir).abs());
+
+    let mut origin = Vertex::new(Kind::Emitter(emitter), ray.origin, emission.normal, emission.radiance);
+    origin.pdf_fwd = emission.pdf_pos * choice_pdf;
+    path.push(origin);
+
+    let beta = emission.radiance * (cos / (choice_pdf * emission.pdf_pos * emission.pdf_dir)) as f32;
+
+    random_walk(emitters.scene, ray, beta, emission.pdf_dir, max_bounces, true, rng, path);
+
+    // Light from the sky is spread over a disk, the first hit's density is the disk's.
+    if path[0].is_infinite()
+    {
+        if let Some(first) = path.get_mut(1)
+        {
+            first.pdf_fwd = emission.pdf_pos * first.normal.map_or(1.0, |normal| normal.dot(ray.dir).abs());
+        }
+
+        path[0].pdf_fwd = emitters.infinite_pdf(ray.dir);
+    }
+}
+
+/// Follows `ray` through at most `max_vertices` surfaces and points in volumes by sampling
+/// their BSDFs and phase functions, adding a vertex for each to `path`. `pdf` is the density
+/// over solid angle `ray` was picked with at the last vertex of `path`.
+///
+/// Camera paths end at lights and the sky, light paths pass through lights.
+#[allow(clippy::too_many_arguments)]
+fn random_walk<'a>(
+    scene: &'a Scene,
+    ray: Ray,
+    beta: RGB,
+    pdf: f64,
+    max_vertices: usize,
+    light_path: bool,
+    rng: &mut Rng,
+    path: &mut Vec<Vertex<'a>>,
+) {
+    let (mut ray, mut beta, mut pdf_fwd) = (ray, beta, pdf);
+    let mut media = MediumStack::new();
+
+    for count in 1..=max_vertices
+    {
+        let prev = path.len() - 1;
+        let (interaction, weight) = step(scene, ray, media, !light_path, rng);
+
+        beta = beta * weight;
+
+        if beta.is_black()
+        {
+            return;
+        }
+
+        let (mut vertex, wo) = match interaction
+        {
+            Interaction::Light(light, dist, radiance) => {
+                let point = ray.point_at_dist(dist);
+                let mut vertex = Vertex::new(Kind::Emitter(Emitter::Light(light)), point, light.normal_at(point), beta);
+
+                vertex.emitted = radiance;
+                vertex.pdf_fwd = path[prev].convert_density(pdf_fwd, &vertex);
+                path.push(vertex);
+
+                return;
+            },
+            Interaction::Escaped => {
+                if !light_path
+                {
+                    let mut vertex = Vertex::new(Kind::Emitter(Emitter::Sky), ray.origin + ray.dir, None, beta);
+
+                    // Only the camera and sharp reflections see a sun the lights already sample.
+                    vertex.emitted = if matches!(path[prev].kind, Kind::Camera) || path[prev].delta
+                    {
+                        scene.sky.radiance(ray.dir)
+                    }
+                    else
+                    {
+                        scene.sky.sampled_radiance(ray.dir)
+                    };
+                    vertex.pdf_fwd = pdf_fwd;
+                    path.push(vertex);
+                }
+
+                return;
+            },
+            Interaction::Surface(surface) => {
+                let mut vertex = Vertex::new(Kind::Surface(surface), surface.point(), Some(surface.record.normal), beta);
+
+                vertex.emitted = surface.emission();
+
+                (vertex, surface.wo)
+            },
+            Interaction::Medium(scattering) => {
+                (Vertex::new(Kind::Medium(scattering), scattering.point, None, beta), scattering.wo)
+            },
+        };
+
+        vertex.pdf_fwd = path[prev].convert_density(pdf_fwd, &vertex);
+
+        if count == max_vertices
+        {
+            path.push(vertex);
+
+            return;
+        }
+
+        // The direction the path continues in, its density, and the density of the opposite
+        // path scattering the other way, 0 for perfectly sharp lobes.
+        let (wi, pdf_rev) = match &vertex.kind
+        {
+            Kind::Medium(scattering) => {
+                let (wi, pdf) = scattering.volume.sample_phase(wo, rng);
+
+                pdf_fwd = pdf;
+                media = scattering.media;
+
+                (wi, pdf)
+            },
+            Kind::Surface(surface) => {
+                let sample = match surface.bsdf.sample(wo, surface.normal, rng)
+                {
+                    Some(sample) => sample,
+                    None => {
+                        path.push(vertex);
+
+                        return;
+                    },
+                };
+
+                beta = beta * sample.weight;
+
+                if light_path
+                {
+                    beta *= surface.adjoint_correction(sample.wi);
+                }
+
+                media = surface.media_towards(sample.wi);
+
+                if sample.specular
+                {
+                    vertex.delta = true;
+                    pdf_fwd = 0.0;
+
+                    (sample.wi, 0.0)
+                }
+                else
+                {
+                    pdf_fwd = sample.pdf;
+
+                    (sample.wi, surface.bsdf.pdf(sample.wi, wo, surface.normal))
+                }
+            },
+            _ => unreachable!("only surfaces and volumes scatter paths"),
+        };
+
+        path[prev].pdf_rev = vertex.convert_density(pdf_rev, &path[prev]);
+        path.push(vertex);
+
+        if beta.is_black()
+        {
+            return;
+        }
+
+        ray = Ray::new(vertex.point, wi);
+    }
+}
+
+/// How much light makes it along the straight line from `from` to `to`, `distance` apart.
+fn connection(scene: &Scene, from: &Vertex, direction: Vec3, distance: f64, rng: &mut Rng) -> RGB
+{
+    let ray = Ray::new(from.point, direction);
+
+    transmittance(scene, ray, distance - SHADOW_EPSILON, from.media_towards(direction), rng)
+}
+
+/// The light found by connecting the first `s` vertices of the light path to the first `t` of
+/// the camera path, weighted against the other ways of finding it. Connections straight to the
+/// camera also return where they land on the film.
+fn connect(
+    emitters: &Emitters,
+    light_path: &[Vertex],
+    camera_path: &[Vertex],
+    s: usize,
+    t: usize,
+    rng: &mut Rng,
+) -> (RGB, Option<(f64, f64)>) {
+    let scene = emitters.scene;
+    let none = (RGB::black(), None);
+
+    // Camera paths that reached a light end there.
+    if t > 1 && s != 0 && matches!(camera_path[t - 1].kind, Kind::Emitter(_))
+    {
+        return none;
+    }
+
+    let mut sampled = None;
+    let mut splat = None;
+
+    let light = if s == 0
+    {
+        let pt = &camera_path[t - 1];
+
+        match pt.kind
+        {
+            Kind::Emitter(_) => pt.beta * pt.emitted,
+            // Glowing surfaces are not lights that paths can be started from, this is the only
+            // way to find their light.
+            Kind::Surface(_) => return (pt.beta * pt.emitted, None),
+            Kind::Camera | Kind::Medium(_) => return none,
+        }
+    }
+    else if t == 1
+    {
+        let qs = &light_path[s - 1];
+
+        if !qs.connectible() || !scene.camera.can_project()
+        {
+            return none;
+        }
+
+        let sample = match scene.camera.sample_importance(qs.point, rng)
+        {
+            Some(sample) => sample,
+            None => return none,
+        };
+
+        let importance = RGB::gray((sample.importance / sample.pdf) as f32);
+        let camera = Vertex::new(Kind::Camera, sample.lens_point, None, importance);
+        let light = qs.beta * qs.f(&camera, true) * camera.beta * qs.cos(sample.direction) as f32;
+
+        if light.is_black()
+        {
+            return none;
+        }
+
+        sampled = Some(camera);
+        splat = Some((sample.x, sample.y));
+
+        light * connection(scene, qs, sample.direction, sample.distance, rng)
+    }
+    else if s == 1
+    {
+        let pt = &camera_path[t - 1];
+
+        if !pt.connectible()
+        {
+            return none;
+        }
+
+        let emitter = match emitters.pick(rng)
+        {
+            Some(emitter) => emitter,
+            None => return none,
+        };
+
+        let sample = match emitter
+        {
+            Emitter::Light(light) => light.sample(pt.point, rng),
+            Emitter::Sky => match scene.sky.sample_emitted(rng)
+            {
+                Some(sample) => sample,
+                None => return none,
+            },
+        };
+
+        let (point, normal) = match emitter
+        {
+            Emitter::Light(light) if !light.is_distant() => {
+                let point = pt.point + sample.direction * sample.distance;
+
+                (point, light.normal_at(point))
+            },
+            _ => (pt.point + sample.direction, None),
+        };
+
+        let beta = sample.color / emitters.choice_pdf() as f32;
+        let mut vertex = Vertex::new(Kind::Emitter(emitter), point, normal, beta);
+        vertex.pdf_fwd = vertex.pdf_light_origin(emitters, pt);
+
+        let light = pt.beta * pt.f(&vertex, false) * vertex.beta * pt.cos(sample.direction) as f32;
+
+        if light.is_black()
+        {
+            return none;
+        }
+
+        sampled = Some(vertex);
+
+        light * connection(scene, pt, sample.direction, sample.distance, rng)
+    }
+    else
+    {
+        let (qs, pt) = (&light_path[s - 1], &camera_path[t - 1]);
+
+        if !qs.connectible() || !pt.connectible()
+        {
+            return none;
+        }
+
+        let light = qs.beta * qs.f(pt, true) * pt.f(qs, false) * pt.beta;
+
+        if light.is_black()
+        {
+            return none;
+        }
+
+        let to_camera = pt.point - qs.point;
+        let distance = to_camera.length();
+        let direction = to_camera / distance;
+
+        let g = qs.cos(direction) * pt.cos(direction) / (distance * distance);
+
+        if g == 0.0
+        {
+            return none;
+        }
+
+        light * connection(scene, qs, direction, distance, rng) * g as f32
+    };
+
+    if light.is_black()
+    {
+        return none;
+    }
+
+    (light * mis_weight(emitters, light_path, camera_path, sampled, s, t) as f32, splat)
+}
+
+/// The balance heuristic weight of the connection of `s` light and `t` camera vertices among
+/// all connections that make the same path. `sampled` replaces the end vertex of a
+/// one-vertex subpath, which connections pick anew.
+fn mis_weight(
+    emitters: &Emitters,
+    light_path: &[Vertex],
+    camera_path: &[Vertex],
+    sampled: Option<Vertex>,
+    s: usize,
+    t: usize,
+) -> f64 {
+    if s + t == 2
+    {
+        return 1.0;
+    }
+
+    let qs = match sampled
+    {
+        Some(vertex) if s == 1 => Some(vertex),
+        _ if s > 0 => Some(light_path[s - 1]),
+        _ => None,
+    };
+    let pt = match sampled
+    {
+        Some(vertex) if t == 1 => vertex,
+        _ => camera_path[t - 1],
+    };
+    let qs_minus = if s > 1 { Some(&light_path[s - 2]) } else { None };
+    let pt_minus = if t > 1 { Some(&camera_path[t - 2]) } else { None };
+
+    // The densities and sharpness of each vertex as this connection sees them, the vertices
+    // next to the connection are reached from the other side as well.
+    let mut camera: Vec<_> = camera_path[..t].iter().map(|v| (v.pdf_fwd, v.pdf_rev, v.delta)).collect();
+    let mut light: Vec<_> = light_path[..s].iter().map(|v| (v.pdf_fwd, v.pdf_rev, v.delta)).collect();
+
+    camera[t - 1] = match (&qs, pt_minus)
+    {
+        (Some(qs), _) => (pt.pdf_fwd, qs.pdf(emitters, qs_minus, &pt), false),
+        (None, Some(pt_minus)) => (pt.pdf_fwd, pt.pdf_light_origin(emitters, pt_minus), false),
+        (None, None) => (pt.pdf_fwd, 0.0, false),
+    };
+
+    if let Some(pt_minus) = pt_minus
+    {
+        camera[t - 2].1 = match &qs
+        {
+            Some(qs) => pt.pdf(emitters, Some(qs), pt_minus),
+            None => pt.pdf_light(emitters, pt_minus),
+        };
+    }
+
+    if let Some(qs) = &qs
+    {
+        light[s - 1] = (qs.pdf_fwd, pt.pdf(emitters, pt_minus, qs), false);
+
+        if let Some(qs_minus) = qs_minus
+        {
+            light[s - 2].1 = qs.pdf(emitters, Some(&pt), qs_minus);
+        }
+    }
+
+    let remap = |pdf: f64| if pdf != 0.0 { pdf } else { 1.0 };
+    let can_project = emitters.scene.camera.can_project();
+
+    let mut sum = 0.0;
+    let mut ratio = 1.0;
+
+    for i in (1..t).rev()
+    {
+        ratio *= remap(camera[i].1) / remap(camera[i].0);
+
+        // Connecting straight to the camera needs a projection onto the film.
+        if !camera[i].2 && !camera[i - 1].2 && (i > 1 || can_project)
+        {
+            sum += ratio;
+        }
+    }
+
+    let delta_light = qs.is_some_and(|qs| if s == 1 { qs.is_delta_light() } else { light_path[0].is_delta_light() });
+    let mut ratio = 1.0;
+
+    for i in (0..s).rev()
+    {
+        ratio *= remap(light[i].1) / remap(light[i].0);
+
+        let delta_before = if i > 0 { light[i - 1].2 } else { delta_light };
+
+        if !light[i].2 && !delta_before
+        {
+            sum += ratio;
+        }
+    }
+
+    1.0 / (1.0 + sum)
+}
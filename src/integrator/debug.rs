@@ -0,0 +1,112 @@
+use crate::{
+    Scene,
+    Ray,
+    RGB,
+    Material,
+    math::Rng,
+    medium::MediumStack,
+};
+use super::{ Surface, PathTracer };
+
+/// What a debug view shows of the first surface along each camera ray. Rays that miss the
+/// scene are black.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugView
+{
+    /// The shading normal, with `-1` to `1` mapped to `0` to `1` per axis.
+    Normals,
+    /// The distance from the camera, black up close, mid gray at the focus distance and white
+    /// far away.
+    Depth,
+    /// The fractional part of the texture coordinates, `u` in red and `v` in green.
+    Uv,
+    /// A random color per material, picked by the material's name so objects sharing one
+    /// match from render to render. Unnamed materials are told apart by where they are in
+    /// memory, so their colors change between runs.
+    Material,
+    /// How many surfaces the path tracer's paths bounce off, from blue for none through green
+    /// to red for the maximum.
+    Bounces,
+}
+
+impl DebugView
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            DebugView::Normals  => "normals",
+            DebugView::Depth    => "depth",
+            DebugView::Uv       => "uv",
+            DebugView::Material => "material",
+            DebugView::Bounces  => "bounces",
+        }
+    }
+
+    pub fn radiance(&self, scene: &Scene, ray: Ray, max_bounces: u32, rng: &mut Rng) -> RGB
+    {
+        let hit = scene.hit(ray, rng);
+
+        match self
+        {
+            DebugView::Normals => hit.map_or(RGB::black(), |record| {
+                let normal = Surface::new(record, ray, MediumStack::new()).normal;
+
+                RGB::new(normal.x as f32, normal.y as f32, normal.z as f32) * 0.5 + RGB::gray(0.5)
+            }),
+            DebugView::Depth => hit.map_or(RGB::black(), |record| {
+                let focus = scene.camera.lens.focus_distance.max(1e-6);
+
+                RGB::gray((record.offset / (record.offset + focus)) as f32)
+            }),
+            DebugView::Uv => hit.map_or(RGB::black(), |record| {
+                RGB::new(record.uv.0.rem_euclid(1.0) as f32, record.uv.1.rem_euclid(1.0) as f32, 0.0)
+            }),
+            DebugView::Material => hit.map_or(RGB::black(), |record| {
+                let h = match &record.material.name
+                {
+                    Some(name) => hash_str(name),
+                    None => hash(record.material as *const Material as u64),
+                };
+
+                RGB::new(channel(h), channel(h >> 21), channel(h >> 42))
+            }),
+            DebugView::Bounces => {
+                let (_, bounces) = PathTracer::default().trace(scene, ray, max_bounces, rng);
+
+                heat(bounces as f32 / max_bounces.max(1) as f32)
+            },
+        }
+    }
+}
+
+/// A blue to green to red ramp over `t` from 0 to 1.
+fn heat(t: f32) -> RGB
+{
+    let t = t.clamp(0.0, 1.0);
+
+    RGB::new((2.0 * t - 1.0).max(0.0), 1.0 - (2.0 * t - 1.0).abs(), (1.0 - 2.0 * t).max(0.0))
+}
+
+/// The SplitMix64 finalizer.
+fn hash(mut h: u64) -> u64
+{
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    h ^ (h >> 31)
+}
+
+/// FNV-1a over the bytes of `s`, finished with `hash` to spread them over all bits.
+fn hash_str(s: &str) -> u64
+{
+    let h = s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, byte| (h ^ byte as u64).wrapping_mul(0x100_0000_01b3));
+
+    hash(h)
+}
+
+/// The low bits of `h` as a color channel, kept away from black.
+fn channel(h: u64) -> f32
+{
+    0.2 + 0.8 * (h & 0xffff) as f32 / 65535.0
+}
@@ -0,0 +1,695 @@
+//! Integrators computing the light arriving along camera rays from a `Scene`.
+//!
+//! The scene only answers geometric questions, what a ray hits and whether a path is blocked,
+//! how that is turned into light is up to the integrator a render is set up with.
+
+mod path;
+mod whitted;
+mod ao;
+mod debug;
+mod bdpt;
+mod photon;
+mod photon_map;
+
+pub use path::PathTracer;
+pub use bdpt::Bidirectional;
+pub use photon::PhotonMapper;
+pub use photon_map::{ Photon, PhotonMap };
+pub use whitted::Whitted;
+pub use ao::AmbientOcclusion;
+pub use debug::DebugView;
+
+use crate::{
+    Scene,
+    Ray,
+    RGB,
+    HitRecord,
+    math::{ Vec3, Rng },
+    bsdf::Bsdf,
+    film::FilmSample,
+    light::{ Light, LightSample },
+    medium::{ Medium, MediumStack, Volume },
+};
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Integrator
+{
+    /// Unbiased path tracing, see `PathTracer`.
+    Path(PathTracer),
+    /// Paths traced from both the camera and the lights, see `Bidirectional`.
+    Bidirectional(Bidirectional),
+    /// Path tracing with caustics from a photon map, see `PhotonMapper`.
+    PhotonMapping(PhotonMapper),
+    /// Direct light and sharp reflections only, see `Whitted`.
+    Whitted(Whitted),
+    AmbientOcclusion(AmbientOcclusion),
+    /// False color views of the first hit, for finding problems with a scene.
+    Debug(DebugView),
+}
+
+impl Default for Integrator
+{
+    fn default() -> Integrator
+    {
+        Integrator::Path(PathTracer::default())
+    }
+}
+
+impl Integrator
+{
+    /// The names of all integrators, in the order the viewer cycles through them.
+    pub const NAMES: [&'static str; 10] = ["path", "bdpt", "photon", "whitted", "ao", "normals", "depth", "uv", "material", "bounces"];
+
+    /// The integrator of the given kind with its usual parameters.
+    pub fn with_defaults(kind: &str) -> Option<Integrator>
+    {
+        match kind
+        {
+            "path"     => Some(Integrator::Path(PathTracer::default())),
+            "bdpt"     => Some(Integrator::Bidirectional(Bidirectional)),
+            "photon"   => Some(Integrator::PhotonMapping(PhotonMapper::default())),
+            "whitted"  => Some(Integrator::Whitted(Whitted)),
+            "ao"       => Some(Integrator::AmbientOcclusion(AmbientOcclusion::default())),
+            "normals"  => Some(Integrator::Debug(DebugView::Normals)),
+            "depth"    => Some(Integrator::Debug(DebugView::Depth)),
+            "uv"       => Some(Integrator::Debug(DebugView::Uv)),
+            "material" => Some(Integrator::Debug(DebugView::Material)),
+            "bounces"  => Some(Integrator::Debug(DebugView::Bounces)),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Integrator::Path(_)             => "path",
+            Integrator::Bidirectional(_)    => "bdpt",
+            Integrator::PhotonMapping(_)    => "photon",
+            Integrator::Whitted(_)          => "whitted",
+            Integrator::AmbientOcclusion(_) => "ao",
+            Integrator::Debug(view)         => view.name(),
+        }
+    }
+
+    /// The integrator following this one in `NAMES`, with its usual parameters.
+    pub fn next(&self) -> Integrator
+    {
+        let index = Integrator::NAMES.iter().position(|&name| name == self.name()).unwrap_or(0);
+        let next = Integrator::NAMES[(index + 1) % Integrator::NAMES.len()];
+
+        Integrator::with_defaults(next).unwrap()
+    }
+
+    /// The integrator with the distance ambient occlusion looks for occluders within replaced,
+    /// other integrators are left alone.
+    pub fn with_ao_distance(self, distance: f64) -> Integrator
+    {
+        match self
+        {
+            Integrator::AmbientOcclusion(_) => Integrator::AmbientOcclusion(AmbientOcclusion { distance }),
+            integrator => integrator,
+        }
+    }
+
+    /// The photon mapper with the number of photons per pass replaced, other integrators are
+    /// left alone.
+    pub fn with_photons(self, photons: u32) -> Integrator
+    {
+        match self
+        {
+            Integrator::PhotonMapping(mapper) => Integrator::PhotonMapping(PhotonMapper { photons, ..mapper }),
+            integrator => integrator,
+        }
+    }
+
+    /// The photon mapper with the gather radius of the first pass replaced, other integrators
+    /// are left alone.
+    pub fn with_photon_radius(self, radius: f64) -> Integrator
+    {
+        match self
+        {
+            Integrator::PhotonMapping(mapper) => Integrator::PhotonMapping(PhotonMapper { radius, ..mapper }),
+            integrator => integrator,
+        }
+    }
+
+    /// The photon mapper with the rate its gather radius shrinks at replaced, other integrators
+    /// are left alone.
+    pub fn with_photon_alpha(self, alpha: f64) -> Integrator
+    {
+        match self
+        {
+            Integrator::PhotonMapping(mapper) => Integrator::PhotonMapping(PhotonMapper { alpha, ..mapper }),
+            integrator => integrator,
+        }
+    }
+
+    /// The photons pass number `pass` gathers, for integrators that use a photon map. `seed`
+    /// is the render's seed, `max_bounces` limits the photons' paths like the camera's.
+    pub fn photon_map(
+        &self,
+        scene: &Scene,
+        pass: u32,
+        seed: u64,
+        max_bounces: u32,
+        threads: usize,
+    ) -> Option<PhotonMap> {
+        match self
+        {
+            Integrator::PhotonMapping(mapper) => Some(mapper.photon_map(scene, pass, seed, max_bounces, threads)),
+            _ => None,
+        }
+    }
+
+    /// The light arriving along `ray`, which starts out in the air, following paths of at most
+    /// `max_bounces` surface interactions. `photons` is the pass's photon map, for integrators
+    /// that use one. Integrators that trace light from the lights onto other parts of the film
+    /// add it to `splats`.
+    pub fn radiance(
+        &self,
+        scene: &Scene,
+        ray: Ray,
+        max_bounces: u32,
+        photons: Option<&PhotonMap>,
+        rng: &mut Rng,
+        splats: &mut Vec<FilmSample>,
+    ) -> RGB {
+        match self
+        {
+            Integrator::Path(integrator) => integrator.radiance(scene, ray, max_bounces, rng),
+            Integrator::Bidirectional(integrator) => integrator.radiance(scene, ray, max_bounces, rng, splats),
+            Integrator::PhotonMapping(integrator) => integrator.radiance(scene, ray, max_bounces, photons, rng),
+            Integrator::Whitted(integrator) => integrator.radiance(scene, ray, max_bounces, rng),
+            Integrator::AmbientOcclusion(integrator) => integrator.radiance(scene, ray, rng),
+            Integrator::Debug(view) => view.radiance(scene, ray, max_bounces, rng),
+        }
+    }
+}
+
+impl fmt::Display for Integrator
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Integrator
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Integrator, String>
+    {
+        let (last, rest) = Integrator::NAMES.split_last().unwrap();
+
+        Integrator::with_defaults(s).ok_or_else(|| format!(
+            "unknown integrator '{}', expected one of {} or {}", s, rest.join(", "), last,
+        ))
+    }
+}
+
+/// A surface hit as seen from the ray that found it, with its BSDF set up for the media on
+/// either side.
+#[derive(Clone, Copy)]
+struct Surface<'a>
+{
+    record: HitRecord<'a>,
+    /// Unit vector back along the ray.
+    wo: Vec3,
+    /// The shading normal, never showing the back of the surface to `wo`.
+    normal: Vec3,
+    /// Whether the ray arrived from the outside of the surface.
+    entering: bool,
+    /// The medium inside the surface.
+    medium: Medium<'a>,
+    /// The media the ray arrived through.
+    media: MediumStack<'a>,
+    bsdf: Bsdf,
+}
+
+impl<'a> Surface<'a>
+{
+    /// `media` are the transparent objects `ray` is inside of.
+    fn new(record: HitRecord<'a>, ray: Ray, media: MediumStack<'a>) -> Surface<'a>
+    {
+        let wo = -ray.dir;
+        let geometric_normal = record.normal;
+        let mut normal = record.material.shading_normal(&record);
+
+        // A shading normal facing away from the viewer would show the back of the surface.
+        if (wo.dot(normal) > 0.0) != (wo.dot(geometric_normal) > 0.0)
+        {
+            normal = geometric_normal;
+        }
+
+        // Transparent objects are made of a medium, whose index is relative to whatever
+        // surrounds the object.
+        let medium = Medium::of(record.material);
+        let entering = wo.dot(geometric_normal) > 0.0;
+        let outside = if entering { media } else { media.exited(medium) };
+
+        let bsdf = record.material.bsdf_at(&record).relative_to(outside.r_index());
+
+        Surface {
+            record,
+            wo,
+            normal,
+            entering,
+            medium,
+            media,
+            bsdf,
+        }
+    }
+
+    fn point(&self) -> Vec3
+    {
+        self.record.point
+    }
+
+    fn emission(&self) -> RGB
+    {
+        self.record.material.emission_at(&self.record)
+    }
+
+    /// The media a ray leaving the surface along `wi` travels through.
+    fn media_towards(&self, wi: Vec3) -> MediumStack<'a>
+    {
+        if !self.bsdf.is_transmissive() || (wi.dot(self.record.normal) > 0.0) == self.entering
+        {
+            self.media
+        }
+        else if self.entering
+        {
+            self.media.entered(self.medium)
+        }
+        else
+        {
+            self.media.exited(self.medium)
+        }
+    }
+
+    /// The light from `sample` that the surface reflects towards `wo`, as much of it as makes
+    /// it to the surface.
+    fn direct_light(&self, scene: &Scene, sample: &LightSample, rng: &mut Rng) -> RGB
+    {
+        let f = self.bsdf.eval(self.wo, sample.direction, self.normal);
+
+        if f.is_black()
+        {
+            return RGB::black();
+        }
+
+        let ray = Ray::new(self.point(), sample.direction);
+        let transmittance = transmittance(scene, ray, sample.distance, self.media_towards(sample.direction), rng);
+
+        f * sample.color * transmittance * sample.direction.dot(self.normal).abs() as f32
+    }
+
+    /// Scales the BSDF for light arriving from `wo` and leaving along `wi`, the opposite of what
+    /// it is written for, as light traced from the lights does. Radiance is not squeezed when
+    /// crossing into a denser medium then, and shading normals do not keep the BSDF symmetric.
+    fn adjoint_correction(&self, wi: Vec3) -> f32
+    {
+        let (wo, normal, geometric) = (self.wo, self.normal, self.record.normal);
+        let denominator = wo.dot(geometric).abs() * wi.dot(normal).abs();
+
+        if denominator == 0.0
+        {
+            return 0.0;
+        }
+
+        let shading = wo.dot(normal).abs() * wi.dot(geometric).abs() / denominator;
+
+        shading as f32 / self.bsdf.radiance_scale(wo, wi, normal)
+    }
+}
+
+/// A point in a participating medium where a ray was scattered.
+#[derive(Clone, Copy)]
+struct Scattering<'a>
+{
+    point: Vec3,
+    /// Unit vector back along the ray.
+    wo: Vec3,
+    volume: &'a Volume,
+    /// The media around the point.
+    media: MediumStack<'a>,
+}
+
+impl<'a> Scattering<'a>
+{
+    /// The light from `sample` that the medium scatters towards `wo`, as much of it as makes
+    /// it to the point.
+    fn direct_light(&self, scene: &Scene, sample: &LightSample, rng: &mut Rng) -> RGB
+    {
+        let phase = self.volume.phase(self.wo, sample.direction);
+
+        if phase <= 0.0
+        {
+            return RGB::black();
+        }
+
+        let ray = Ray::new(self.point, sample.direction);
+
+        sample.color * transmittance(scene, ray, sample.distance, self.media, rng) * phase as f32
+    }
+}
+
+/// What a ray runs into first, see `step`.
+// Most steps end on surfaces, boxing them would only add allocations.
+#[allow(clippy::large_enum_variant)]
+enum Interaction<'a>
+{
+    /// A surface other than the boundary of a volume.
+    Surface(Surface<'a>),
+    /// A point where a volume scattered the ray.
+    Medium(Scattering<'a>),
+    /// A light's surface, with its distance from the ray's origin and its radiance.
+    Light(&'a Light, f64, RGB),
+    /// Nothing, the ray leaves the scene towards the sky.
+    Escaped,
+}
+
+/// Follows `ray` through the media in `media` up to whatever it runs into, passing through the
+/// boundaries of volumes. Returns that and the light that makes it there, divided by the
+/// probability of getting there. Only paths from the camera stop at lights.
+fn step<'a>(
+    scene: &'a Scene,
+    ray: Ray,
+    media: MediumStack<'a>,
+    stop_at_lights: bool,
+    rng: &mut Rng,
+) -> (Interaction<'a>, RGB) {
+    let (mut segment, mut media) = (ray, media);
+    let mut weight = RGB::gray(1.0);
+    let mut travelled = 0.0;
+
+    loop
+    {
+        let hit = scene.hit(segment, rng);
+        let mut max_dist = hit.as_ref().map_or(f64::INFINITY, |record| record.offset);
+
+        let light = if stop_at_lights { scene.hit_light(segment, max_dist) } else { None };
+
+        if let Some((_, dist, _)) = light
+        {
+            max_dist = dist;
+        }
+
+        if let Some((volume, (start, end))) = volume_span(scene, segment, max_dist, media)
+        {
+            let sample = volume.sample_distance(end - start, rng);
+
+            weight = weight * sample.weight;
+
+            if let Some(distance) = sample.distance
+            {
+                let scattering = Scattering {
+                    point: segment.point_at_dist(start + distance),
+                    wo: -ray.dir,
+                    volume,
+                    media,
+                };
+
+                return (Interaction::Medium(scattering), weight);
+            }
+        }
+
+        if let Some((light, dist, radiance)) = light
+        {
+            return (Interaction::Light(light, travelled + dist, radiance), weight);
+        }
+
+        let record = match hit
+        {
+            Some(record) => record,
+            None => return (Interaction::Escaped, weight),
+        };
+
+        if !matches!(record.material.bsdf, Bsdf::Interface)
+        {
+            return (Interaction::Surface(Surface::new(record, segment, media)), weight);
+        }
+
+        let surface = Surface::new(record, segment, media);
+
+        media = surface.media_towards(ray.dir);
+        travelled += surface.record.offset;
+        segment = Ray::new(surface.point(), ray.dir);
+    }
+}
+
+/// The fraction of light that makes it along `ray` for `distance` through the media in
+/// `media`, 0 if a surface is in the way. Only the boundaries of volumes let light through.
+fn transmittance<'a>(scene: &'a Scene, ray: Ray, distance: f64, media: MediumStack<'a>, rng: &mut Rng) -> RGB
+{
+    let (mut ray, mut media) = (ray, media);
+    let mut remaining = distance;
+    let mut transmittance = RGB::gray(1.0);
+
+    loop
+    {
+        let hit = scene.hit_before(ray, remaining, rng);
+        let max_dist = hit.as_ref().map_or(remaining, |record| record.offset);
+
+        if let Some((volume, (start, end))) = volume_span(scene, ray, max_dist, media)
+        {
+            transmittance = transmittance * volume.transmittance(end - start);
+
+            if transmittance.is_black()
+            {
+                return transmittance;
+            }
+        }
+
+        let record = match hit
+        {
+            Some(record) => record,
+            None => return transmittance,
+        };
+
+        if !matches!(record.material.bsdf, Bsdf::Interface)
+        {
+            return RGB::black();
+        }
+
+        let surface = Surface::new(record, ray, media);
+
+        media = surface.media_towards(ray.dir);
+        remaining -= surface.record.offset;
+        ray = Ray::new(surface.point(), ray.dir);
+    }
+}
+
+/// The volume `ray` travels through inside `media` and the stretch of it before `max_dist`
+/// that is inside the volume. Out in the open, that is the scene's atmosphere.
+fn volume_span<'a>(
+    scene: &'a Scene,
+    ray: Ray,
+    max_dist: f64,
+    media: MediumStack<'a>,
+) -> Option<(&'a Volume, (f64, f64))> {
+    if media.is_empty()
+    {
+        return scene.atmosphere_span(ray, max_dist);
+    }
+
+    media.current().volume.map(|volume| (volume, (0.0, max_dist)))
+}
+
+/// Veach's power heuristic with an exponent of 2, the weight of a sample picked with density
+/// `pdf` that could also have been picked by a strategy with density `other`. Infinite
+/// densities belong to strategies that are the only way to find the sample.
+fn power_heuristic(pdf: f64, other: f64) -> f64
+{
+    if pdf.is_infinite()
+    {
+        return 1.0;
+    }
+
+    let (a, b) = (pdf * pdf, other * other);
+
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{
+        render::{ self, RenderSettings },
+        scene_file::SceneDesc,
+    };
+
+    use rand::SeedableRng;
+
+    /// Diffuse objects under a rect light, without any caustics or sky, which every
+    /// integrator must render the same on average.
+    const ROOM: &str = "version = 2
+
+[camera]
+position = [0.0, 0.6, -4.0]
+target = [0.0, 0.0, 0.0]
+
+[sky]
+type = \"color\"
+color = [0.0, 0.0, 0.0]
+
+[[objects]]
+type = \"triangle\"
+vertices = [[-50.0, -0.5, -50.0], [50.0, -0.5, -50.0], [0.0, -0.5, 50.0]]
+material = { type = \"diffuse\", color = [0.7, 0.7, 0.7] }
+
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 0.5
+material = { type = \"diffuse\", color = [0.8, 0.4, 0.2] }
+
+[[lights]]
+type = \"rect\"
+corner = [-4.0, 1.0, -3.0]
+edge_u = [8.0, 0.0, 0.0]
+edge_v = [0.0, 0.0, 8.0]
+color = [1.0, 1.0, 1.0]
+";
+
+    /// The mean of all pixels of a small render of `source`, splats included.
+    fn image_mean(source: &str, integrator: Integrator) -> f32
+    {
+        let mut scene = SceneDesc::parse(source).unwrap().build().unwrap();
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples: 64,
+            max_bounces: 5,
+            seed: Some(1),
+            threads: 2,
+            integrator,
+            ..RenderSettings::default()
+        };
+
+        let pixels = render::render(&mut scene, &settings, |_| ());
+
+        pixels.iter().sum::<f32>() / pixels.len() as f32
+    }
+
+    fn assert_agrees_with_path(integrator: Integrator)
+    {
+        let path = image_mean(ROOM, Integrator::Path(PathTracer::default()));
+        let other = image_mean(ROOM, integrator);
+
+        assert!((other - path).abs() < 0.03 * path, "{} {} against path {}", integrator, other, path);
+    }
+
+    /// Diffuse ground under a physical sky and its sun. Nothing but the sky and the sun lights
+    /// it, so paths must agree with Whitted's direct lighting.
+    const GROUND: &str = "version = 2
+
+[camera]
+position = [0.0, 1.0, -5.0]
+target = [0.0, 0.0, 0.0]
+
+[sky]
+type = \"physical\"
+sun_elevation = 35.0
+sun_diameter = 2.0
+
+[[objects]]
+type = \"triangle\"
+vertices = [[-1000.0, 0.0, -1000.0], [1000.0, 0.0, -1000.0], [0.0, 0.0, 1000.0]]
+material = { type = \"diffuse\", color = [0.5, 0.5, 0.5] }
+";
+
+    fn mean(integrator: Integrator, scene: &Scene) -> f32
+    {
+        const SAMPLES: usize = 20_000;
+
+        let mut rng = Rng::seed_from_u64(3);
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.3).normalized());
+
+        (0..SAMPLES)
+            .map(|_| integrator.radiance(scene, ray, 2, None, &mut rng, &mut vec![]).luminance())
+            .sum::<f32>() / SAMPLES as f32
+    }
+
+    #[test]
+    fn the_sun_is_counted_once()
+    {
+        // Once as a light of its own, and once as part of the sky.
+        for source in [GROUND.to_string(), GROUND.replace("sun_diameter = 2.0", "sun_diameter = 2.0\nsun = false")].iter()
+        {
+            let scene = SceneDesc::parse(source).unwrap().build().unwrap();
+            let direct = mean(Integrator::Whitted(Whitted), &scene);
+            let path = mean(Integrator::Path(PathTracer::default()), &scene);
+
+            assert!((path - direct).abs() < 0.03 * direct, "path {} against direct {}", path, direct);
+        }
+    }
+
+    #[test]
+    fn material_colors_follow_names()
+    {
+        let scene = SceneDesc::parse("version = 2
+
+[camera]
+position = [0.0, 0.0, -5.0]
+target = [0.0, 0.0, 0.0]
+
+[sky]
+type = \"color\"
+color = [0.5, 0.5, 0.5]
+
+[materials.red]
+type = \"diffuse\"
+color = [1.0, 0.0, 0.0]
+
+[[objects]]
+type = \"sphere\"
+center = [-2.0, 0.0, 0.0]
+radius = 0.5
+material = \"red\"
+
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 0.5
+material = \"red\"
+
+[[objects]]
+type = \"sphere\"
+center = [2.0, 0.0, 0.0]
+radius = 0.5
+material = { type = \"diffuse\", color = [1.0, 0.0, 0.0] }
+").unwrap().build().unwrap();
+
+        let mut rng = Rng::seed_from_u64(6);
+        let mut color = |x: f64| {
+            let ray = Ray::new(Vec3::new(x, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+
+            Integrator::Debug(DebugView::Material).radiance(&scene, ray, 1, None, &mut rng, &mut vec![])
+        };
+
+        let (left, middle, right) = (color(-2.0), color(0.0), color(2.0));
+
+        assert_eq!(left, middle);
+        assert_ne!(left, right);
+        assert_eq!(right, color(2.0));
+    }
+
+    #[test]
+    fn photon_mapping_keeps_direct_light_without_caustics()
+    {
+        assert_agrees_with_path(Integrator::PhotonMapping(PhotonMapper::default()));
+    }
+
+    #[test]
+    fn bidirectional_agrees_with_path_under_an_area_light()
+    {
+        assert_agrees_with_path(Integrator::Bidirectional(Bidirectional));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    Scene,
+    Ray,
+    RGB,
+    math::Rng,
+    light::LightSample,
+    medium::MediumStack,
+};
+use super::{ Surface, Scattering, Interaction, PhotonMap, step, power_heuristic };
+
+use rand::Rng as _;
+
+/// Traces paths from the camera by sampling the BSDF at every hit, and connects each hit to
+/// the lights and the sky with shadow rays. Light found both ways is weighted by multiple
+/// importance sampling, so small lights and sharp reflections are both found quickly.
+///
+/// Volumes scatter paths at random points inside them, which are lit the same way as surfaces,
+/// with the phase function in place of the BSDF.
+#[derive(Debug, Clone, Copy)]
+pub struct PathTracer
+{
+    /// The number of bounces after which paths are ended at random by Russian roulette, the
+    /// less light they still carry the more likely.
+    pub roulette_depth: u32,
+}
+
+impl Default for PathTracer
+{
+    fn default() -> PathTracer
+    {
+        PathTracer {
+            roulette_depth: 3,
+        }
+    }
+}
+
+/// The highest chance of a path surviving Russian roulette, which ends even paths carrying
+/// all their light now and then.
+const MAX_SURVIVAL: f32 = 0.95;
+
+impl PathTracer
+{
+    pub fn radiance(&self, scene: &Scene, ray: Ray, max_bounces: u32, rng: &mut Rng) -> RGB
+    {
+        self.trace(scene, ray, max_bounces, rng).0
+    }
+
+    /// The light arriving along `ray` and the number of times the path was scattered.
+    pub fn trace(&self, scene: &Scene, ray: Ray, max_bounces: u32, rng: &mut Rng) -> (RGB, u32)
+    {
+        self.trace_with(scene, ray, max_bounces, None, rng)
+    }
+
+    /// Like `trace`, but with the caustics on surfaces that are not perfectly sharp taken from
+    /// a map of photons that reached them through perfectly sharp ones only. Paths finding
+    /// the lights that way are not counted again.
+    pub fn trace_with(
+        &self,
+        scene: &Scene,
+        ray: Ray,
+        max_bounces: u32,
+        caustics: Option<&PhotonMap>,
+        rng: &mut Rng,
+    ) -> (RGB, u32) {
+        let mut ray = ray;
+        let mut media = MediumStack::new();
+        let mut color = RGB::black();
+        let mut throughput = RGB::gray(1.0);
+
+        // The density the last bounce picked the ray's direction with, `None` for camera rays
+        // and sharp reflections, whose light is never found by sampling the lights.
+        let mut bsdf_pdf: Option<f64> = None;
+
+        // Whether the path left the last surface with caustics from the map by a lobe they
+        // cover, and whether it has crossed perfectly sharp surfaces and nothing else since.
+        // Lights found that way are in the map, lights found straight away are not.
+        let mut left_diffuse = false;
+        let mut in_caustic = false;
+
+        for bounce in 0..max_bounces
+        {
+            let (interaction, weight) = step(scene, ray, media, true, rng);
+
+            throughput = throughput * weight;
+
+            // The direction the path continues in, its density, whether it was picked from a
+            // perfectly sharp lobe, and what the light from it is scaled by.
+            let (point, wi, pdf, specular, scale) = match interaction
+            {
+                Interaction::Light(light, dist, radiance) => {
+                    if in_caustic
+                    {
+                        return (color, bounce);
+                    }
+
+                    let weight = bsdf_pdf.map_or(1.0, |pdf| power_heuristic(pdf, light.pdf(ray, dist)));
+
+                    return (color + throughput * radiance * weight as f32, bounce);
+                },
+                Interaction::Escaped => {
+                    let (radiance, weight) = match bsdf_pdf
+                    {
+                        Some(pdf) if scene.sky.is_sampled() => {
+                            (scene.sky.sampled_radiance(ray.dir), power_heuristic(pdf, scene.sky.pdf(ray.dir)))
+                        },
+                        Some(_) => (scene.sky.sampled_radiance(ray.dir), 1.0),
+                        None => (scene.sky.radiance(ray.dir), 1.0),
+                    };
+
+                    return (color + throughput * radiance * weight as f32, bounce);
+                },
+                Interaction::Medium(scattering) => {
+                    for light in scene.lights.iter()
+                    {
+                        let sample = light.sample(scattering.point, rng);
+
+                        color += throughput * medium_light(scene, &scattering, &sample, rng);
+                    }
+
+                    if let Some(sample) = scene.sky.sample(rng)
+                    {
+                        color += throughput * medium_light(scene, &scattering, &sample, rng);
+                    }
+
+                    // The photon map only holds light that reached surfaces through sharp ones.
+                    left_diffuse = false;
+                    in_caustic = false;
+                    media = scattering.media;
+
+                    let (wi, pdf) = scattering.volume.sample_phase(scattering.wo, rng);
+
+                    (scattering.point, wi, pdf, false, RGB::gray(1.0))
+                },
+                Interaction::Surface(surface) => {
+                    color += throughput * surface.emission();
+
+                    if !surface.bsdf.is_specular()
+                    {
+                        for light in scene.lights.iter()
+                        {
+                            let sample = light.sample(surface.point(), rng);
+
+                            color += throughput * direct_light(scene, &surface, &sample, rng);
+                        }
+
+                        if let Some(sample) = scene.sky.sample(rng)
+                        {
+                            color += throughput * direct_light(scene, &surface, &sample, rng);
+                        }
+
+                        if let Some(caustics) = caustics
+                        {
+                            color += throughput * caustic_light(&surface, caustics);
+                        }
+                    }
+
+                    let sample = match surface.bsdf.sample(surface.wo, surface.normal, rng)
+                    {
+                        Some(sample) => sample,
+                        None => return (color, bounce + 1),
+                    };
+
+                    if surface.bsdf.is_specular()
+                    {
+                        in_caustic = left_diffuse;
+                    }
+                    else
+                    {
+                        left_diffuse = caustics.is_some() && !sample.specular;
+                        in_caustic = false;
+                    }
+
+                    media = surface.media_towards(sample.wi);
+
+                    (surface.point(), sample.wi, sample.pdf, sample.specular, sample.weight)
+                },
+            };
+
+            throughput = throughput * scale;
+
+            if throughput.is_black()
+            {
+                return (color, bounce + 1);
+            }
+
+            bsdf_pdf = if specular { None } else { Some(pdf) };
+            ray = Ray::new(point, wi);
+
+            if bounce + 1 >= self.roulette_depth
+            {
+                let survival = throughput.r.max(throughput.g).max(throughput.b).min(MAX_SURVIVAL);
+
+                if rng.gen::<f32>() >= survival
+                {
+                    return (color, bounce + 1);
+                }
+
+                throughput = throughput / survival;
+            }
+        }
+
+        (color, max_bounces)
+    }
+}
+
+/// The light from `sample` reflected by `surface`, weighted against finding the same light by
+/// sampling the BSDF.
+fn direct_light(scene: &Scene, surface: &Surface, sample: &LightSample, rng: &mut Rng) -> RGB
+{
+    let light = surface.direct_light(scene, sample, rng);
+
+    if light.is_black()
+    {
+        return light;
+    }
+
+    light * power_heuristic(sample.pdf, surface.bsdf.pdf(surface.wo, sample.direction, surface.normal)) as f32
+}
+
+/// The light from `sample` scattered by a volume at `scattering`, weighted against finding the
+/// same light by sampling the phase function.
+fn medium_light(scene: &Scene, scattering: &Scattering, sample: &LightSample, rng: &mut Rng) -> RGB
+{
+    let light = scattering.direct_light(scene, sample, rng);
+
+    if light.is_black()
+    {
+        return light;
+    }
+
+    light * power_heuristic(sample.pdf, scattering.volume.phase(scattering.wo, sample.direction)) as f32
+}
+
+/// The light the photons near `surface` bring, reflected towards `wo`.
+fn caustic_light(surface: &Surface, caustics: &PhotonMap) -> RGB
+{
+    let mut light = RGB::black();
+
+    caustics.gather(surface.point(), |photon| {
+        light += surface.bsdf.eval(surface.wo, photon.wi, surface.normal) * photon.power;
+    });
+
+    let radius = caustics.radius();
+
+    light / (std::f64::consts::PI * radius * radius) as f32
+}
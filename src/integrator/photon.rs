@@ -0,0 +1,213 @@
+use crate::{
+    Scene,
+    Ray,
+    RGB,
+    math::{ Vec3, Rng },
+    medium::MediumStack,
+    render,
+};
+use super::{ Interaction, PathTracer, Photon, PhotonMap, step };
+
+use rand::Rng as _;
+
+use std::sync::Mutex;
+
+/// Progressive photon mapping for caustics: every pass sends photons from the lights through
+/// perfectly sharp mirrors and glass, and the path tracer takes the caustics on other surfaces
+/// from the photons landing near them instead of hoping to hit a light through the glass.
+/// Caustics of point lights and the sun, which paths from the camera never find, show up too.
+///
+/// Gathering photons from around a point blurs the caustics a little. The gather radius shrinks
+/// with every pass, so the blur and the noise both fade as passes are averaged.
+#[derive(Debug, Clone, Copy)]
+pub struct PhotonMapper
+{
+    /// The number of photons sent from the lights each pass.
+    pub photons: u32,
+    /// How far from a point photons are gathered in the first pass, in world units.
+    pub radius: f64,
+    /// How much of the gathered photons each pass keeps after shrinking the radius, between 0
+    /// and 1. Smaller values shrink the radius faster, sharpening caustics at the cost of noise.
+    pub alpha: f64,
+}
+
+impl Default for PhotonMapper
+{
+    fn default() -> PhotonMapper
+    {
+        PhotonMapper {
+            photons: 100_000,
+            radius: 0.05,
+            alpha: 0.7,
+        }
+    }
+}
+
+/// The number of photons traced together with one generator, so the map does not depend on
+/// the number of threads.
+const CHUNK_SIZE: u32 = 4096;
+
+/// Keeps photon generators apart from the ones of camera rays on the same pass.
+const PHOTON_SEED: u64 = 0x7068_6f74_6f6e_7321;
+
+impl PhotonMapper
+{
+    pub fn radiance(
+        &self,
+        scene: &Scene,
+        ray: Ray,
+        max_bounces: u32,
+        photons: Option<&PhotonMap>,
+        rng: &mut Rng,
+    ) -> RGB {
+        PathTracer::default().trace_with(scene, ray, max_bounces, photons, rng).0
+    }
+
+    /// The gather radius of pass number `pass`, following Knaus and Zwicker's probabilistic
+    /// progressive photon mapping.
+    pub fn radius(&self, pass: u32) -> f64
+    {
+        let radius_sq = (1..=pass).fold(self.radius * self.radius, |radius_sq, i| {
+            radius_sq * (i as f64 + self.alpha) / (i as f64 + 1.0)
+        });
+
+        radius_sq.sqrt()
+    }
+
+    /// Sends this pass's photons from the lights, spread over `threads` threads, and keeps the
+    /// ones that reach a surface that is not perfectly sharp through ones that are. Distant
+    /// lights only send photons towards the perfectly sharp objects, the rest would be lost.
+    pub fn photon_map(
+        &self,
+        scene: &Scene,
+        pass: u32,
+        seed: u64,
+        max_bounces: u32,
+        threads: usize,
+    ) -> PhotonMap {
+        let chunks = self.photons.div_ceil(CHUNK_SIZE);
+        let mut chunk_photons = vec![vec![]; chunks as usize];
+
+        if let (false, Some(bounds)) = (scene.lights.is_empty(), scene.specular_bounding_sphere())
+        {
+            let queue = Mutex::new(chunk_photons.iter_mut().zip(0..chunks));
+
+            std::thread::scope(|s| {
+                for _ in 0..threads.max(1)
+                {
+                    s.spawn(|| loop
+                    {
+                        let next = queue.lock().unwrap().next();
+
+                        let (photons, chunk) = match next
+                        {
+                            Some(next) => next,
+                            None => break,
+                        };
+
+                        let mut rng = render::pass_rng(seed ^ PHOTON_SEED, pass, chunk as usize);
+                        let count = CHUNK_SIZE.min(self.photons - chunk * CHUNK_SIZE);
+
+                        for _ in 0..count
+                        {
+                            self.trace_photon(scene, bounds, max_bounces, &mut rng, photons);
+                        }
+                    });
+                }
+            });
+        }
+
+        PhotonMap::new(chunk_photons.concat(), self.radius(pass))
+    }
+
+    /// Traces a photon from a random light. `bounds` is the sphere around the perfectly sharp
+    /// objects, which distant lights spread their photons over.
+    fn trace_photon(&self, scene: &Scene, bounds: (Vec3, f64), max_bounces: u32, rng: &mut Rng, out: &mut Vec<Photon>)
+    {
+        let light = &scene.lights[rng.gen_range(0, scene.lights.len())];
+        let (center, radius) = bounds;
+        let mut emission = light.emit(center, radius, rng);
+
+        // Start outside the whole scene, so whatever shades the sharp objects still does.
+        if light.is_distant()
+        {
+            let (scene_center, scene_radius) = scene.bounding_sphere();
+            let back = scene_radius + (scene_center - center).length();
+
+            emission.ray = Ray::new(emission.ray.origin - emission.ray.dir * back, emission.ray.dir);
+        }
+
+        if emission.pdf_pos <= 0.0 || emission.pdf_dir <= 0.0
+        {
+            return;
+        }
+
+        let cos = emission.normal.map_or(1.0, |normal| normal.dot(emission.ray.dir).abs());
+        let pdf = emission.pdf_pos * emission.pdf_dir / scene.lights.len() as f64;
+
+        let mut power = emission.radiance * (cos / (pdf * self.photons as f64)) as f32;
+        let mut ray = emission.ray;
+        let mut media = MediumStack::new();
+        let mut caustic = false;
+
+        for _ in 0..max_bounces
+        {
+            let (interaction, weight) = step(scene, ray, media, false, rng);
+
+            // Light scattered by volumes is found by the path tracer.
+            let surface = match interaction
+            {
+                Interaction::Surface(surface) => surface,
+                _ => return,
+            };
+
+            power = power * weight;
+
+            // Light reaching other surfaces directly is found by the path tracer.
+            if !surface.bsdf.is_specular()
+            {
+                if caustic
+                {
+                    out.push(Photon { point: surface.point(), wi: surface.wo, power });
+                }
+
+                return;
+            }
+
+            let sample = match surface.bsdf.sample(surface.wo, surface.normal, rng)
+            {
+                Some(sample) => sample,
+                None => return,
+            };
+
+            power = power * sample.weight * surface.adjoint_correction(sample.wi);
+
+            if power.is_black()
+            {
+                return;
+            }
+
+            caustic = true;
+            media = surface.media_towards(sample.wi);
+            ray = Ray::new(surface.point(), sample.wi);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::scene_file::SceneDesc;
+
+    #[test]
+    fn distant_lights_reach_the_sharp_objects()
+    {
+        // The demo's ground is far larger than its glass and mirror spheres, and only lit by a
+        // hemi light.
+        let scene = SceneDesc::demo().build().unwrap();
+        let mapper = PhotonMapper { photons: 20_000, ..PhotonMapper::default() };
+
+        assert!(!mapper.photon_map(&scene, 0, 1, 10, 2).is_empty());
+    }
+}
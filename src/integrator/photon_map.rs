@@ -0,0 +1,128 @@
+use crate::{
+    RGB,
+    math::Vec3,
+    bvh::Aabb,
+};
+
+/// A bundle of light that came to rest on a surface.
+#[derive(Debug, Clone, Copy)]
+pub struct Photon
+{
+    pub point: Vec3,
+    /// Unit vector back towards where the photon came from.
+    pub wi: Vec3,
+    /// The flux the photon carries.
+    pub power: RGB,
+}
+
+/// Photons in a balanced kd-tree, for finding the ones near a point.
+///
+/// The tree is implicit: every slice of the photon list is split at its middle photon, along
+/// the axis its photons spread furthest on, with the photons below the split before it and the
+/// ones above after it.
+#[derive(Debug, Clone)]
+pub struct PhotonMap
+{
+    photons: Vec<Photon>,
+    /// The split axis of the node at each photon.
+    axes: Vec<u8>,
+    /// How far from a point photons are gathered.
+    radius: f64,
+}
+
+impl PhotonMap
+{
+    pub fn new(mut photons: Vec<Photon>, radius: f64) -> PhotonMap
+    {
+        let mut axes = vec![0; photons.len()];
+
+        build(&mut photons, &mut axes);
+
+        PhotonMap {
+            photons,
+            axes,
+            radius,
+        }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.photons.is_empty()
+    }
+
+    pub fn radius(&self) -> f64
+    {
+        self.radius
+    }
+
+    /// Calls `visit` with every photon within the gather radius of `point`.
+    pub fn gather<F>(&self, point: Vec3, mut visit: F)
+    where F: FnMut(&Photon)
+    {
+        self.gather_range(0, self.photons.len(), point, self.radius * self.radius, &mut visit);
+    }
+
+    fn gather_range<F>(&self, start: usize, end: usize, point: Vec3, radius_sq: f64, visit: &mut F)
+    where F: FnMut(&Photon)
+    {
+        if start >= end
+        {
+            return;
+        }
+
+        let mid = start + (end - start) / 2;
+        let photon = &self.photons[mid];
+        let axis = self.axes[mid] as usize;
+
+        let offset = point - photon.point;
+
+        if offset.dot(offset) <= radius_sq
+        {
+            visit(photon);
+        }
+
+        // Search the side of the split the point is on first, the other only if the gather
+        // sphere reaches across.
+        let (near, far) = if offset[axis] <= 0.0
+        {
+            ((start, mid), (mid + 1, end))
+        }
+        else
+        {
+            ((mid + 1, end), (start, mid))
+        };
+
+        self.gather_range(near.0, near.1, point, radius_sq, visit);
+
+        if offset[axis] * offset[axis] <= radius_sq
+        {
+            self.gather_range(far.0, far.1, point, radius_sq, visit);
+        }
+    }
+}
+
+fn build(photons: &mut [Photon], axes: &mut [u8])
+{
+    if photons.is_empty()
+    {
+        return;
+    }
+
+    let bounds = photons.iter().fold(Aabb::empty(), |bounds, photon| bounds.grow(photon.point));
+    let axis = bounds.longest_axis();
+    let mid = photons.len() / 2;
+
+    photons.select_nth_unstable_by(mid, |a, b| a.point[axis].total_cmp(&b.point[axis]));
+    axes[mid] = axis as u8;
+
+    let (below, rest) = photons.split_at_mut(mid);
+    let (below_axes, rest_axes) = axes.split_at_mut(mid);
+
+    build(below, below_axes);
+    build(&mut rest[1..], &mut rest_axes[1..]);
+}
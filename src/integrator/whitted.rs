@@ -0,0 +1,91 @@
+use crate::{
+    Scene,
+    Ray,
+    RGB,
+    math::Rng,
+    medium::MediumStack,
+};
+use super::{ Interaction, step };
+
+/// A classic Whitted-style ray tracer: surfaces are lit directly by the lights and a sampled
+/// sky, and only perfectly sharp reflections and refractions are followed. Diffuse and glossy
+/// surfaces get no light bounced off the rest of the scene, which makes it quick to converge
+/// but darker than the path tracer. Volumes likewise only scatter the light coming straight
+/// from the lights and the sky.
+///
+/// Glossy lobes are not followed either, so rough metals and frosted glass only show the
+/// highlights of the lights and of a sampled sky. Under a plain colored sky, which is never
+/// sampled, they reflect and refract nothing and come out black.
+#[derive(Debug, Clone, Copy)]
+pub struct Whitted;
+
+impl Whitted
+{
+    pub fn radiance(&self, scene: &Scene, ray: Ray, max_bounces: u32, rng: &mut Rng) -> RGB
+    {
+        let mut ray = ray;
+        let mut media = MediumStack::new();
+        let mut color = RGB::black();
+        let mut throughput = RGB::gray(1.0);
+
+        for _ in 0..max_bounces
+        {
+            let (interaction, weight) = step(scene, ray, media, true, rng);
+
+            throughput = throughput * weight;
+
+            let surface = match interaction
+            {
+                Interaction::Surface(surface) => surface,
+                Interaction::Light(_, _, radiance) => return color + throughput * radiance,
+                Interaction::Escaped => return color + throughput * scene.sky.radiance(ray.dir),
+                Interaction::Medium(scattering) => {
+                    for light in scene.lights.iter()
+                    {
+                        let sample = light.sample(scattering.point, rng);
+
+                        color += throughput * scattering.direct_light(scene, &sample, rng);
+                    }
+
+                    if let Some(sample) = scene.sky.sample(rng)
+                    {
+                        color += throughput * scattering.direct_light(scene, &sample, rng);
+                    }
+
+                    break;
+                },
+            };
+
+            color += throughput * surface.emission();
+
+            if !surface.bsdf.is_specular()
+            {
+                for light in scene.lights.iter()
+                {
+                    let sample = light.sample(surface.point(), rng);
+
+                    color += throughput * surface.direct_light(scene, &sample, rng);
+                }
+
+                if let Some(sample) = scene.sky.sample(rng)
+                {
+                    color += throughput * surface.direct_light(scene, &sample, rng);
+                }
+            }
+
+            // Sharp lobes are never hit by sampling the lights, so following them counts no
+            // light twice.
+            let sample = match surface.bsdf.sample(surface.wo, surface.normal, rng)
+            {
+                Some(sample) if sample.specular => sample,
+                _ => break,
+            };
+
+            media = surface.media_towards(sample.wi);
+            throughput = throughput * sample.weight;
+            ray = Ray::new(surface.point(), sample.wi);
+        }
+
+        color
+    }
+}
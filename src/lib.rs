@@ -0,0 +1,73 @@
+//! A small progressive ray tracer.
+//!
+//! Scenes are either built by hand from `Scene`, `Sphere`, `Material` and `Light`, or loaded
+//! from a TOML description with `scene_file::SceneDesc`. `render::render` traces a scene into
+//! a linear RGB float buffer that `imageio` can write to disk.
+//!
+//! ```
+//! use raytracer::{ Scene, Camera, Sphere, Material, Light, Sky, RGB, light, math::Vec3 };
+//! use raytracer::render::{ self, RenderSettings };
+//!
+//! let camera = Camera::look_at(
+//!     Vec3::new(0.0, 0.0, -5.0),
+//!     Vec3::new(0.0, 0.0, 0.0),
+//!     Vec3::new(0.0, 1.0, 0.0),
+//!     60.0,
+//!     32,
+//!     24,
+//! );
+//!
+//! let mut scene = Scene::new(
+//!     Sky::Color(RGB::gray(0.5)),
+//!     camera,
+//!     vec![Box::from(Sphere::new(Vec3::zero(), 1.0, Material::diffuse(RGB::new(1.0, 0.2, 0.2))))],
+//!     vec![Light::Hemi(light::Hemi::new(Vec3::new(-1.0, -1.0, 1.0), RGB::gray(0.6)))],
+//! );
+//!
+//! let settings = RenderSettings { width: 32, height: 24, samples: 2, ..RenderSettings::default() };
+//!
+//! let pixels = render::render(&mut scene, &settings, |pass| {
+//!     println!("finished pass {}/{}", pass, settings.samples);
+//! });
+//!
+//! assert_eq!(pixels.len(), 32 * 24 * 3);
+//! ```
+//!
+//! The `cli` feature adds the command line parsing shared by the binaries and the `viewer`
+//! feature adds the glium based progressive viewer in `ui`.
+
+pub mod ray;
+pub mod camera;
+pub mod object;
+pub mod light;
+pub mod sky;
+pub mod scene;
+pub mod integrator;
+pub mod material;
+pub mod bsdf;
+pub mod texture;
+pub mod medium;
+pub mod color;
+pub mod math;
+pub mod render;
+pub mod film;
+pub mod filter;
+pub mod imageio;
+pub mod scene_file;
+pub mod bvh;
+pub mod import;
+
+#[cfg(feature = "cli")]
+pub mod cli;
+
+#[cfg(feature = "viewer")]
+pub mod ui;
+
+pub use ray::Ray;
+pub use camera::Camera;
+pub use object::{ Object, HitRecord, Sphere, Triangle, Mesh, };
+pub use light::Light;
+pub use sky::Sky;
+pub use scene::Scene;
+pub use color::RGB;
+pub use material::Material;
@@ -0,0 +1,72 @@
+use crate::{
+    RGB,
+    Ray,
+    math::Vec3,
+};
+
+/// A one-sided circular light, emitting to the side `normal` points to.
+pub struct Disk
+{
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f64,
+    /// Emitted radiance.
+    pub radiance: RGB,
+}
+
+impl Disk
+{
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, radiance: RGB) -> Disk
+    {
+        Disk {
+            center,
+            normal: normal.normalized(),
+            radius,
+            radiance,
+        }
+    }
+
+    pub fn area(&self) -> f64
+    {
+        std::f64::consts::PI * self.radius * self.radius
+    }
+
+    /// Maps `(u, v)` in `[0, 1)²` uniformly onto the disk.
+    pub fn point_at(&self, u: f64, v: f64) -> Vec3
+    {
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let r = self.radius * u.sqrt();
+        let phi = 2.0 * std::f64::consts::PI * v;
+
+        self.center + tangent * (r * phi.cos()) + bitangent * (r * phi.sin())
+    }
+
+    /// Distance along `ray` to the disk, hit from either side.
+    pub fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<f64>
+    {
+        let denom = ray.dir.dot(self.normal);
+
+        if denom.abs() < 1e-12
+        {
+            return None;
+        }
+
+        let t = (self.center - ray.origin).dot(self.normal) / denom;
+
+        if t <= ray_range.0 || t >= ray_range.1
+        {
+            return None;
+        }
+
+        let offset = ray.point_at_dist(t) - self.center;
+
+        if offset.dot(offset) <= self.radius * self.radius
+        {
+            Some(t)
+        }
+        else
+        {
+            None
+        }
+    }
+}
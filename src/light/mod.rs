@@ -1,10 +1,327 @@
 mod hemi;
+mod point;
+mod sun;
+mod rect;
+mod disk;
+mod sphere;
 
 pub use hemi::Hemi;
+pub use point::Point;
+pub use sun::Sun;
+pub use rect::Rect;
+pub use disk::Disk;
+pub use sphere::Sphere;
+
+use crate::{
+    RGB,
+    Ray,
+    math::{ Vec3, Rng },
+    bvh::Aabb,
+};
+
+use std::f64::consts::PI;
+
+use rand::Rng as _;
 
 pub enum Light
 {
-    // Point(Point),
+    Point(Point),
     Hemi(Hemi),
-    // Sun(Sun),
-}
\ No newline at end of file
+    Sun(Sun),
+    Rect(Rect),
+    Disk(Disk),
+    Sphere(Sphere),
+}
+
+/// Light arriving at a point from one direction.
+pub struct LightSample
+{
+    /// Unit vector from the point towards the light.
+    pub direction: Vec3,
+    /// Distance to the light, infinite for directional lights.
+    pub distance: f64,
+    pub color: RGB,
+    /// Density over solid angle the direction was picked with, infinite for lights that
+    /// rays cannot hit by chance.
+    pub pdf: f64,
+}
+
+/// A ray of light leaving a light, picked by `Light::emit`.
+pub struct Emission
+{
+    pub ray: Ray,
+    /// The normal of the light's surface at the ray's origin, `None` for lights without one.
+    pub normal: Option<Vec3>,
+    /// Radiance along the ray, or the intensity of point lights and the irradiance of
+    /// distant ones.
+    pub radiance: RGB,
+    /// Density over area the origin was picked with, 1 for point lights.
+    pub pdf_pos: f64,
+    /// Density over solid angle the direction was picked with, 1 for distant lights.
+    pub pdf_dir: f64,
+}
+
+impl Light
+{
+    /// Picks a direction towards the light as seen from `point`, soft lights pick a random one.
+    pub fn sample(&self, point: Vec3, rng: &mut Rng) -> LightSample
+    {
+        match self
+        {
+            Light::Point(light) => {
+                let to_light = light.position - point;
+                let distance_sq = to_light.dot(to_light);
+
+                LightSample {
+                    direction: to_light.normalized(),
+                    distance: distance_sq.sqrt(),
+                    color: light.color * (light.intensity / distance_sq as f32),
+                    pdf: f64::INFINITY,
+                }
+            },
+            Light::Hemi(light) => LightSample {
+                direction: -light.direction,
+                distance: f64::INFINITY,
+                color: light.color,
+                pdf: f64::INFINITY,
+            },
+            Light::Sun(light) => LightSample {
+                direction: Vec3::random_in_cone(rng, -light.direction, (light.angular_diameter * 0.5).cos()),
+                distance: f64::INFINITY,
+                color: light.color,
+                pdf: f64::INFINITY,
+            },
+            Light::Rect(light) => {
+                let target = light.point_at(rng.gen(), rng.gen());
+
+                area_sample(point, target, light.normal(), light.area(), light.radiance)
+            },
+            Light::Disk(light) => {
+                let target = light.point_at(rng.gen(), rng.gen());
+
+                area_sample(point, target, light.normal, light.area(), light.radiance)
+            },
+            Light::Sphere(light) => {
+                // Sample the cone of directions the sphere covers, which is never occluded by the
+                // sphere itself, unlike points on its back.
+                let to_center = light.center - point;
+                let distance_sq = to_center.dot(to_center);
+                let sin_max_sq = (light.radius * light.radius / distance_sq).min(1.0);
+                let cos_max = (1.0 - sin_max_sq).sqrt();
+
+                let direction = Vec3::random_in_cone(rng, to_center.normalized(), cos_max);
+                let distance = light.hit(Ray::new(point, direction), (0.0, f64::INFINITY))
+                    .unwrap_or_else(|| distance_sq.sqrt() - light.radius);
+                let solid_angle = 2.0 * std::f64::consts::PI * (1.0 - cos_max);
+
+                LightSample {
+                    direction,
+                    distance,
+                    color: light.radiance * solid_angle as f32,
+                    pdf: 1.0 / solid_angle,
+                }
+            },
+        }
+    }
+
+    /// The distance and radiance of the light's surface along `ray`, for lights that have one.
+    pub fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<(f64, RGB)>
+    {
+        match self
+        {
+            Light::Point(_) | Light::Hemi(_) | Light::Sun(_) => None,
+            Light::Rect(light) => light.hit(ray, ray_range).map(|t| {
+                (t, if ray.dir.dot(light.normal()) < 0.0 { light.radiance } else { RGB::black() })
+            }),
+            Light::Disk(light) => light.hit(ray, ray_range).map(|t| {
+                (t, if ray.dir.dot(light.normal) < 0.0 { light.radiance } else { RGB::black() })
+            }),
+            Light::Sphere(light) => light.hit(ray, ray_range).map(|t| (t, light.radiance)),
+        }
+    }
+
+    /// Whether the light is a single point or direction, which no ray hits by chance.
+    pub fn is_delta(&self) -> bool
+    {
+        matches!(self, Light::Point(_) | Light::Hemi(_) | Light::Sun(_))
+    }
+
+    /// Whether the light is infinitely far away, shining in from one direction.
+    pub fn is_distant(&self) -> bool
+    {
+        matches!(self, Light::Hemi(_) | Light::Sun(_))
+    }
+
+    /// The bounds of the light, `None` for distant lights.
+    pub fn bounding_box(&self) -> Option<Aabb>
+    {
+        match self
+        {
+            Light::Point(light) => Some(Aabb::new(light.position, light.position)),
+            Light::Hemi(_) | Light::Sun(_) => None,
+            Light::Rect(light) => Some(Aabb::from_points(&[
+                light.corner,
+                light.corner + light.edge_u,
+                light.corner + light.edge_v,
+                light.corner + light.edge_u + light.edge_v,
+            ])),
+            Light::Disk(light) => {
+                let r = Vec3::new(light.radius, light.radius, light.radius);
+
+                Some(Aabb::new(light.center - r, light.center + r))
+            },
+            Light::Sphere(light) => {
+                let r = Vec3::new(light.radius, light.radius, light.radius);
+
+                Some(Aabb::new(light.center - r, light.center + r))
+            },
+        }
+    }
+
+    /// The normal of the light's surface at `point` on it, for lights with a surface.
+    pub fn normal_at(&self, point: Vec3) -> Option<Vec3>
+    {
+        match self
+        {
+            Light::Point(_) | Light::Hemi(_) | Light::Sun(_) => None,
+            Light::Rect(light) => Some(light.normal()),
+            Light::Disk(light) => Some(light.normal),
+            Light::Sphere(light) => Some((point - light.center).normalized()),
+        }
+    }
+
+    /// Picks a ray of light leaving the light, for tracing paths from the lights. Distant
+    /// lights shine in across a disk covering the sphere around the scene at `center`.
+    pub fn emit(&self, center: Vec3, radius: f64, rng: &mut Rng) -> Emission
+    {
+        match self
+        {
+            Light::Point(light) => Emission {
+                ray: Ray::new(light.position, Vec3::random_unit(rng)),
+                normal: None,
+                radiance: light.color * light.intensity,
+                pdf_pos: 1.0,
+                pdf_dir: 1.0 / (4.0 * PI),
+            },
+            Light::Hemi(light) => distant_emission(light.direction, light.color, center, radius, rng),
+            Light::Sun(light) => {
+                let direction = -Vec3::random_in_cone(rng, -light.direction, (light.angular_diameter * 0.5).cos());
+
+                distant_emission(direction, light.color, center, radius, rng)
+            },
+            Light::Rect(light) => {
+                let origin = light.point_at(rng.gen(), rng.gen());
+
+                area_emission(origin, light.normal(), light.area(), light.radiance, rng)
+            },
+            Light::Disk(light) => {
+                let origin = light.point_at(rng.gen(), rng.gen());
+
+                area_emission(origin, light.normal, light.area(), light.radiance, rng)
+            },
+            Light::Sphere(light) => {
+                let normal = Vec3::random_unit(rng);
+                let area = 4.0 * PI * light.radius * light.radius;
+
+                area_emission(light.center + normal * light.radius, normal, area, light.radiance, rng)
+            },
+        }
+    }
+
+    /// The densities of `emit` picking `point` on the light and `direction` from there, see
+    /// `Emission`. Distant lights have neither.
+    pub fn emit_pdf(&self, point: Vec3, direction: Vec3) -> (f64, f64)
+    {
+        match self
+        {
+            Light::Point(_) => (1.0, 1.0 / (4.0 * PI)),
+            Light::Hemi(_) | Light::Sun(_) => (0.0, 0.0),
+            Light::Rect(light) => (1.0 / light.area(), direction.dot(light.normal()).max(0.0) / PI),
+            Light::Disk(light) => (1.0 / light.area(), direction.dot(light.normal).max(0.0) / PI),
+            Light::Sphere(light) => {
+                let normal = (point - light.center).normalized();
+
+                (1.0 / (4.0 * PI * light.radius * light.radius), direction.dot(normal).max(0.0) / PI)
+            },
+        }
+    }
+
+    /// The density of `sample` picking the direction of `ray`, from the ray's origin, given
+    /// that the ray hits the light at distance `dist`.
+    pub fn pdf(&self, ray: Ray, dist: f64) -> f64
+    {
+        match self
+        {
+            Light::Point(_) | Light::Hemi(_) | Light::Sun(_) => 0.0,
+            Light::Rect(light) => area_pdf(ray.dir, dist, light.normal(), light.area()),
+            Light::Disk(light) => area_pdf(ray.dir, dist, light.normal, light.area()),
+            Light::Sphere(light) => {
+                let to_center = light.center - ray.origin;
+                let sin_max_sq = (light.radius * light.radius / to_center.dot(to_center)).min(1.0);
+                let cos_max = (1.0 - sin_max_sq).sqrt();
+
+                1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_max))
+            },
+        }
+    }
+}
+
+/// Light from a distant light travelling along `direction`, entering the sphere around the
+/// scene through a disk facing it.
+pub fn distant_emission(direction: Vec3, irradiance: RGB, center: Vec3, radius: f64, rng: &mut Rng) -> Emission
+{
+    let (tangent, bitangent) = direction.orthonormal_basis();
+    let (r, phi) = (radius * rng.gen::<f64>().sqrt(), 2.0 * PI * rng.gen::<f64>());
+    let origin = center - direction * radius + tangent * (r * phi.cos()) + bitangent * (r * phi.sin());
+
+    Emission {
+        ray: Ray::new(origin, direction),
+        normal: None,
+        radiance: irradiance,
+        pdf_pos: 1.0 / (PI * radius * radius),
+        pdf_dir: 1.0,
+    }
+}
+
+/// Light leaving `origin` on a light's surface in a cosine distributed direction.
+fn area_emission(origin: Vec3, normal: Vec3, area: f64, radiance: RGB, rng: &mut Rng) -> Emission
+{
+    let (tangent, bitangent) = normal.orthonormal_basis();
+    let local = Vec3::random_cosine(rng);
+    let direction = tangent * local.x + bitangent * local.y + normal * local.z;
+
+    Emission {
+        ray: Ray::new(origin, direction),
+        normal: Some(normal),
+        radiance,
+        pdf_pos: 1.0 / area,
+        pdf_dir: local.z / PI,
+    }
+}
+
+/// Converts a point picked uniformly on a flat light's surface into the light it sends
+/// towards `point`.
+fn area_sample(point: Vec3, target: Vec3, normal: Vec3, area: f64, radiance: RGB) -> LightSample
+{
+    let to_light = target - point;
+    let distance_sq = to_light.dot(to_light);
+    let distance = distance_sq.sqrt();
+    let direction = to_light / distance;
+    let cos_light = (-direction.dot(normal)).max(0.0);
+
+    LightSample {
+        direction,
+        distance,
+        color: radiance * (cos_light * area / distance_sq) as f32,
+        pdf: area_pdf(direction, distance, normal, area),
+    }
+}
+
+/// The density over solid angle of picking a point uniformly on a flat light's surface,
+/// seen along `direction` at `distance`.
+fn area_pdf(direction: Vec3, distance: f64, normal: Vec3, area: f64) -> f64
+{
+    let cos_light = direction.dot(normal).abs();
+
+    if cos_light > 0.0 { distance * distance / (cos_light * area) } else { f64::INFINITY }
+}
@@ -0,0 +1,25 @@
+use crate::{
+    RGB,
+    math::Vec3,
+};
+
+/// A light emitting equally in all directions from a single point.
+pub struct Point
+{
+    pub position: Vec3,
+    pub color: RGB,
+    /// Scales `color`, the light received at distance `d` is `color * intensity / d²`.
+    pub intensity: f32,
+}
+
+impl Point
+{
+    pub fn new(position: Vec3, color: RGB, intensity: f32) -> Point
+    {
+        Point {
+            position,
+            color,
+            intensity,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{
+    RGB,
+    Ray,
+    math::Vec3,
+};
+
+/// A one-sided parallelogram light, emitting to the side `edge_u × edge_v` points to.
+pub struct Rect
+{
+    pub corner: Vec3,
+    pub edge_u: Vec3,
+    pub edge_v: Vec3,
+    /// Emitted radiance.
+    pub radiance: RGB,
+    normal: Vec3,
+    area: f64,
+}
+
+impl Rect
+{
+    pub fn new(corner: Vec3, edge_u: Vec3, edge_v: Vec3, radiance: RGB) -> Rect
+    {
+        let cross = edge_u.cross(edge_v);
+
+        Rect {
+            corner,
+            edge_u,
+            edge_v,
+            radiance,
+            normal: cross.normalized(),
+            area: cross.dot(cross).sqrt(),
+        }
+    }
+
+    pub fn normal(&self) -> Vec3
+    {
+        self.normal
+    }
+
+    pub fn area(&self) -> f64
+    {
+        self.area
+    }
+
+    /// The point at `(u, v)` in `[0, 1)²` on the rectangle.
+    pub fn point_at(&self, u: f64, v: f64) -> Vec3
+    {
+        self.corner + self.edge_u * u + self.edge_v * v
+    }
+
+    /// Distance along `ray` to the rectangle, hit from either side.
+    pub fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<f64>
+    {
+        let denom = ray.dir.dot(self.normal);
+
+        if denom.abs() < 1e-12
+        {
+            return None;
+        }
+
+        let t = (self.corner - ray.origin).dot(self.normal) / denom;
+
+        if t <= ray_range.0 || t >= ray_range.1
+        {
+            return None;
+        }
+
+        // Project onto the edges, dividing by the squared lengths handles non-square rects.
+        let offset = ray.point_at_dist(t) - self.corner;
+        let u = offset.dot(self.edge_u) / self.edge_u.dot(self.edge_u);
+        let v = offset.dot(self.edge_v) / self.edge_v.dot(self.edge_v);
+
+        if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v)
+        {
+            Some(t)
+        }
+        else
+        {
+            None
+        }
+    }
+}
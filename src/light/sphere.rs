@@ -0,0 +1,49 @@
+use crate::{
+    RGB,
+    Ray,
+    math::Vec3,
+};
+
+/// A spherical light emitting outwards from its whole surface.
+pub struct Sphere
+{
+    pub center: Vec3,
+    pub radius: f64,
+    /// Emitted radiance.
+    pub radiance: RGB,
+}
+
+impl Sphere
+{
+    pub fn new(center: Vec3, radius: f64, radiance: RGB) -> Sphere
+    {
+        Sphere {
+            center,
+            radius,
+            radiance,
+        }
+    }
+
+    /// Distance along `ray` to the nearest intersection with the sphere.
+    pub fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<f64>
+    {
+        let oc = ray.origin - self.center;
+
+        let a = ray.dir.dot(ray.dir);
+        let half_b = oc.dot(ray.dir);
+        let c = oc.dot(oc) - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+
+        if discriminant < 0.0
+        {
+            return None;
+        }
+
+        let sqrt_d = discriminant.sqrt();
+
+        [(-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a]
+            .iter()
+            .copied()
+            .find(|&t| t > ray_range.0 && t < ray_range.1)
+    }
+}
@@ -0,0 +1,26 @@
+use crate::{
+    RGB,
+    math::Vec3,
+};
+
+/// A distant light covering a small disk of the sky, giving soft shadows.
+pub struct Sun
+{
+    pub direction: Vec3,
+    pub color: RGB,
+    /// Angular diameter of the sun disk in radians.
+    pub angular_diameter: f64,
+}
+
+impl Sun
+{
+    /// `angular_diameter` is given in degrees, the real sun is about 0.53.
+    pub fn new(direction: Vec3, color: RGB, angular_diameter: f64) -> Sun
+    {
+        Sun {
+            direction: direction.normalized(),
+            color,
+            angular_diameter: angular_diameter.to_radians(),
+        }
+    }
+}
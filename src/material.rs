@@ -1,55 +1,263 @@
-use crate::RGB;
+use crate::{
+    RGB,
+    HitRecord,
+    math::Vec3,
+    texture::Texture,
+    medium::Volume,
+};
+use crate::bsdf::{
+    Bsdf,
+    Lambertian,
+    Conductor,
+    Metal,
+    Dielectric,
+    Principled,
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Material
 {
-    /// The color of the material.
-    pub color: RGB,
-    /// How reflective the material is.
-    /// 
-    /// 0.0 = completely diffuse
-    /// 1.0 = completely reflective
-    pub reflectivity: f32, // TODO change to roughness
-    /// Opacity
-    pub opacity: f32,
-    /// Refractive index
-    /// Only applies if `opacity` < 1.0
-    pub r_index: f32,
+    /// How the surface scatters light.
+    pub bsdf: Bsdf,
+    /// Light emitted by the surface.
+    pub emission: RGB,
+    pub maps: TextureMaps,
+    /// What fills objects made of the material, for materials light passes through.
+    pub volume: Option<Volume>,
+    /// What the scene or the asset the material comes from calls it, if anything.
+    pub name: Option<String>,
 }
 
+/// Textures replacing the constant parameters of a material where given.
+#[derive(Debug, Clone, Default)]
+pub struct TextureMaps
+{
+    /// The color of the BSDF, see `Bsdf::with_color`.
+    pub color: Option<Texture>,
+    pub roughness: Option<Texture>,
+    /// 1 where the surface is solid and 0 where it is cut away.
+    pub opacity: Option<Texture>,
+    pub emission: Option<Texture>,
+    /// A tangent space normal map, with +y along the `v` texture coordinate.
+    pub normal: Option<Texture>,
+    pub bump: Option<BumpMap>,
+}
+
+/// A height field pushing the surface out along its normal for shading.
+#[derive(Debug, Clone)]
+pub struct BumpMap
+{
+    pub texture: Texture,
+    /// The offset in world units a texture value of 1 stands for.
+    pub height: f64,
+}
+
+/// The step in texture coordinates the slope of bump maps is measured over.
+const BUMP_DELTA: f64 = 1e-3;
+
 impl Material
 {
-    pub fn diffuse(color: RGB) -> Material
+    pub fn new(bsdf: Bsdf) -> Material
     {
         Material {
-            color,
-            reflectivity: 0.0,
-            opacity: 1.0,
-            r_index: 1.0,
+            bsdf,
+            emission: RGB::black(),
+            maps: TextureMaps::default(),
+            volume: None,
+            name: None,
         }
     }
 
-    pub fn reflective(color: RGB, reflectivity: f32) -> Material
+    pub fn diffuse(color: RGB) -> Material
     {
-        Material {
-            color,
-            reflectivity,
-            opacity: 1.0,
-            r_index: 1.0,
+        Material::new(Bsdf::Lambertian(Lambertian { color }))
+    }
+
+    /// A measured metal, `roughness` going from a mirror at 0 to very rough at 1.
+    pub fn metal(metal: Metal, roughness: f32) -> Material
+    {
+        Material::new(Bsdf::Conductor(Conductor::metal(metal, roughness)))
+    }
+
+    /// A metal reflecting `color` head-on.
+    pub fn conductor(color: RGB, roughness: f32) -> Material
+    {
+        Material::new(Bsdf::Conductor(Conductor::from_color(color, roughness)))
+    }
+
+    /// Glass-like material with index of refraction `r_index`, about 1.5 for glass.
+    pub fn dielectric(r_index: f32, roughness: f32) -> Material
+    {
+        Material::new(Bsdf::Dielectric(Dielectric::new(r_index, roughness)))
+    }
+
+    /// A Disney-style uber material, see `Principled` for the parameters.
+    pub fn principled(principled: Principled) -> Material
+    {
+        Material::new(Bsdf::Principled(principled))
+    }
+
+    /// An invisible surface, only marking the boundary of the volume filling the object.
+    pub fn interface(volume: Volume) -> Material
+    {
+        Material::new(Bsdf::Interface).with_volume(volume)
+    }
+
+    pub fn with_name(mut self, name: &str) -> Material
+    {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Makes the material glow with `emission`, on top of whatever light it reflects.
+    pub fn with_emission(mut self, emission: RGB) -> Material
+    {
+        self.emission = emission;
+        self
+    }
+
+    /// Fills objects made of the material with `volume`. Only materials light passes through
+    /// let it be seen.
+    pub fn with_volume(mut self, volume: Volume) -> Material
+    {
+        self.volume = Some(volume);
+        self
+    }
+
+    pub fn with_color_map(mut self, texture: Texture) -> Material
+    {
+        self.maps.color = Some(texture);
+        self
+    }
+
+    pub fn with_roughness_map(mut self, texture: Texture) -> Material
+    {
+        self.maps.roughness = Some(texture);
+        self
+    }
+
+    pub fn with_opacity_map(mut self, texture: Texture) -> Material
+    {
+        self.maps.opacity = Some(texture);
+        self
+    }
+
+    pub fn with_emission_map(mut self, texture: Texture) -> Material
+    {
+        self.maps.emission = Some(texture);
+        self
+    }
+
+    pub fn with_normal_map(mut self, texture: Texture) -> Material
+    {
+        self.maps.normal = Some(texture);
+        self
+    }
+
+    pub fn with_bump_map(mut self, texture: Texture, height: f64) -> Material
+    {
+        self.maps.bump = Some(BumpMap { texture, height });
+        self
+    }
+
+    /// The normal to shade a hit with, the hit's normal perturbed by the normal and bump maps.
+    /// It stays on the same side of the surface.
+    pub fn shading_normal(&self, hit: &HitRecord) -> Vec3
+    {
+        let mut normal = hit.normal;
+
+        if let Some(texture) = &self.maps.normal
+        {
+            let color = texture.color(hit);
+            let local = Vec3::new(color.r as f64, color.g as f64, color.b as f64) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+
+            let tangent = (hit.tangent - normal * normal.dot(hit.tangent)).normalized();
+            let mut bitangent = normal.cross(tangent);
+
+            // Mirrored texture coordinates flip the frame.
+            if bitangent.dot(hit.bitangent) < 0.0
+            {
+                bitangent = -bitangent;
+            }
+
+            let mapped = tangent * local.x + bitangent * local.y + normal * local.z;
+
+            if mapped.dot(normal) > 0.0
+            {
+                normal = mapped.normalized();
+            }
+        }
+
+        if let Some(bump) = &self.maps.bump
+        {
+            let height = |hit: &HitRecord| bump.texture.value(hit) as f64 * bump.height;
+
+            let base = height(hit);
+            let shifted_u = HitRecord {
+                uv: (hit.uv.0 + BUMP_DELTA, hit.uv.1),
+                point: hit.point + hit.tangent * BUMP_DELTA,
+                ..*hit
+            };
+            let shifted_v = HitRecord {
+                uv: (hit.uv.0, hit.uv.1 + BUMP_DELTA),
+                point: hit.point + hit.bitangent * BUMP_DELTA,
+                ..*hit
+            };
+
+            let dpdu = hit.tangent + normal * ((height(&shifted_u) - base) / BUMP_DELTA);
+            let dpdv = hit.bitangent + normal * ((height(&shifted_v) - base) / BUMP_DELTA);
+            let bumped = dpdu.cross(dpdv).normalized();
+
+            if bumped.x.is_finite()
+            {
+                normal = if bumped.dot(normal) < 0.0 { -bumped } else { bumped };
+            }
         }
+
+        normal
     }
 
-    pub fn transparent(
-        color: RGB,
-        reflectivity: f32,
-        opacity: f32,
-        r_index: f32
-    ) -> Material {
-        Material {
-            color,
-            reflectivity,
-            opacity,
-            r_index,
+    /// The BSDF at a hit, with the textured parameters looked up.
+    pub fn bsdf_at(&self, hit: &HitRecord) -> Bsdf
+    {
+        let mut bsdf = self.bsdf;
+
+        if let Some(texture) = &self.maps.color
+        {
+            bsdf = bsdf.with_color(texture.color(hit));
         }
+
+        if let Some(texture) = &self.maps.roughness
+        {
+            bsdf = bsdf.with_roughness(texture.value(hit).clamp(0.0, 1.0));
+        }
+
+        bsdf
     }
-}
\ No newline at end of file
+
+    pub fn emission_at(&self, hit: &HitRecord) -> RGB
+    {
+        match &self.maps.emission
+        {
+            Some(texture) => texture.color(hit),
+            None => self.emission,
+        }
+    }
+
+    /// How likely a ray hitting the surface is to stop there instead of passing through.
+    pub fn opacity_at(&self, hit: &HitRecord) -> f32
+    {
+        match &self.maps.opacity
+        {
+            Some(texture) => texture.value(hit),
+            None => 1.0,
+        }
+    }
+
+    /// The index of refraction of the inside of objects made of this material, `None` for
+    /// opaque materials and interfaces.
+    pub fn r_index(&self) -> Option<f32>
+    {
+        self.bsdf.r_index()
+    }
+}
@@ -0,0 +1,131 @@
+/// A piecewise constant distribution over `[0, 1)`, sampled by inverting its CDF.
+#[derive(Debug, Clone)]
+pub struct Distribution1D
+{
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D
+{
+    /// `func` holds the (non-negative) value of each of the equally sized pieces.
+    /// A function that is zero everywhere is treated as uniform.
+    pub fn new(func: Vec<f64>) -> Distribution1D
+    {
+        let n = func.len() as f64;
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+
+        cdf.push(0.0);
+
+        for value in func.iter()
+        {
+            cdf.push(cdf[cdf.len() - 1] + value / n);
+        }
+
+        let integral = cdf[cdf.len() - 1];
+
+        for (i, c) in cdf.iter_mut().enumerate()
+        {
+            *c = if integral > 0.0 { *c / integral } else { i as f64 / n };
+        }
+
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.func.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.func.is_empty()
+    }
+
+    /// The integral of the function over `[0, 1)`.
+    pub fn integral(&self) -> f64
+    {
+        self.integral
+    }
+
+    /// Maps `u` in `[0, 1)` to a point distributed like the function.
+    /// Returns the point, its density and the index of the piece it lies in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize)
+    {
+        // The last piece whose cdf starts at or below u.
+        let index = self.cdf.partition_point(|&c| c <= u).clamp(1, self.len()) - 1;
+
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { (u - self.cdf[index]) / width } else { 0.0 };
+
+        ((index as f64 + offset) / self.len() as f64, self.pdf_at(index), index)
+    }
+
+    /// The density of samples within piece `index`.
+    pub fn pdf_at(&self, index: usize) -> f64
+    {
+        if self.integral > 0.0
+        {
+            self.func[index] / self.integral
+        }
+        else
+        {
+            1.0
+        }
+    }
+}
+
+/// A piecewise constant distribution over `[0, 1)²` made from a grid of values, sampled as
+/// a marginal distribution over the rows and a conditional one within the picked row.
+#[derive(Debug, Clone)]
+pub struct Distribution2D
+{
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D
+{
+    /// `func` holds `width` values per row, row by row.
+    pub fn new(func: &[f64], width: usize) -> Distribution2D
+    {
+        let rows: Vec<_> = func.chunks(width).map(|row| Distribution1D::new(row.to_vec())).collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+
+        Distribution2D {
+            rows,
+            marginal,
+        }
+    }
+
+    /// Maps `(u, v)` in `[0, 1)²` to a point `(x, y)` distributed like the function,
+    /// along with its density.
+    pub fn sample(&self, u: f64, v: f64) -> ((f64, f64), f64)
+    {
+        let (y, pdf_y, row) = self.marginal.sample(v);
+        let (x, pdf_x, _) = self.rows[row].sample(u);
+
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    /// The density of samples at `(x, y)`.
+    pub fn pdf(&self, x: f64, y: f64) -> f64
+    {
+        let row = ((y * self.rows.len() as f64) as usize).min(self.rows.len() - 1);
+        let column = ((x * self.rows[row].len() as f64) as usize).min(self.rows[row].len() - 1);
+
+        if self.marginal.integral() > 0.0
+        {
+            self.rows[row].func[column] / self.marginal.integral()
+        }
+        else
+        {
+            1.0
+        }
+    }
+}
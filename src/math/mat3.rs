@@ -13,32 +13,34 @@ impl Mat3
         Mat3(values)
     }
 
+    /// The matrix with `vecs` as its columns.
     pub fn from_col_vec3(vecs: [Vec3; 3]) -> Mat3
     {
         Mat3([
             vecs[0].x,
-            vecs[0].y,
-            vecs[0].z,
             vecs[1].x,
-            vecs[1].y,
-            vecs[1].z,
             vecs[2].x,
+            vecs[0].y,
+            vecs[1].y,
             vecs[2].y,
+            vecs[0].z,
+            vecs[1].z,
             vecs[2].z,
         ])
     }
 
+    /// The matrix with `vecs` as its rows.
     pub fn from_row_vec3(vecs: [Vec3; 3]) -> Mat3
     {
         Mat3([
             vecs[0].x,
+            vecs[0].y,
+            vecs[0].z,
+            vecs[1].x,
             vecs[1].y,
-            vecs[2].z,
-            vecs[0].x,
-            vecs[1].y,
-            vecs[2].z,
-            vecs[0].x,
-            vecs[1].y,
+            vecs[1].z,
+            vecs[2].x,
+            vecs[2].y,
             vecs[2].z,
         ])
     }
@@ -1,7 +1,15 @@
 mod quaternion;
 mod vec3;
 mod mat3;
+mod distribution;
 
 pub use quaternion::Quaternion;
 pub use vec3::Vec3;
-pub use mat3::Mat3;
\ No newline at end of file
+pub use mat3::Mat3;
+pub use distribution::{ Distribution1D, Distribution2D };
+
+/// The random number generator used while rendering.
+///
+/// Each render thread owns its own generator, seeded per scanline so renders are reproducible
+/// regardless of how the work is scheduled.
+pub type Rng = rand::rngs::SmallRng;
@@ -27,15 +27,13 @@ impl Vec3
     }
 
     /// Genrates a random vector uniformally distributed on a unit sphere.
-    pub fn random_unit(rng: &mut rand::rngs::StdRng) -> Vec3
+    pub fn random_unit<R: rand::Rng + ?Sized>(rng: &mut R) -> Vec3
     {
-        use rand::Rng;
-
         let theta = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
-        let phi   = std::f64::consts::PI * rng.gen::<f64>();
 
-        let sin_phi = phi.sin();
-        let cos_phi = phi.cos();
+        // Uniform heights give uniform areas, by Archimedes' hat-box theorem.
+        let cos_phi = 1.0 - 2.0 * rng.gen::<f64>();
+        let sin_phi = (1.0 - cos_phi * cos_phi).max(0.0).sqrt();
         let sin_theta = theta.sin();
         let cos_theta = theta.cos();
 
@@ -47,7 +45,7 @@ impl Vec3
     }
 
     /// Generates a random vector uniformally distributes on one half of a unit sphere.
-    pub fn random_half_sphere(rng: &mut rand::rngs::StdRng, normal: Vec3) -> Vec3
+    pub fn random_half_sphere<R: rand::Rng + ?Sized>(rng: &mut R, normal: Vec3) -> Vec3
     {
         let sphere = Vec3::random_unit(rng);
         let l = sphere.dot(normal);
@@ -66,11 +64,53 @@ impl Vec3
         }
     }
 
+    /// Generates a random unit vector around `+z`, distributed proportionally to its `z`.
+    pub fn random_cosine<R: rand::Rng + ?Sized>(rng: &mut R) -> Vec3
+    {
+        let u = rng.gen::<f64>();
+        let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+        let r = u.sqrt();
+
+        Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - u).max(0.0).sqrt())
+    }
+
+    /// Generates a random vector uniformally distributed over the directions within a cone
+    /// around `axis`, `cos_max` being the cosine of the cone's half angle.
+    pub fn random_in_cone<R: rand::Rng + ?Sized>(rng: &mut R, axis: Vec3, cos_max: f64) -> Vec3
+    {
+        let cos_theta = 1.0 - rng.gen::<f64>() * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+
+        let (tangent, bitangent) = axis.orthonormal_basis();
+
+        (tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + axis * cos_theta).normalized()
+    }
+
+    /// Two unit vectors perpendicular to this unit vector and to each other.
+    pub fn orthonormal_basis(&self) -> (Vec3, Vec3)
+    {
+        // Duff et al. 2017, "Building an Orthonormal Basis, Revisited".
+        let sign = 1.0f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+
+        (
+            Vec3::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Vec3::new(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
+
+    pub fn length(&self) -> f64
+    {
+        self.dot(*self).sqrt()
+    }
+
     pub fn normalized(&self) -> Vec3
     {
-        let length = (self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        let length = self.length();
 
-        if length.abs() < std::f64::EPSILON
+        if length.abs() < f64::EPSILON
         {
             Vec3::zero()
         }
@@ -105,18 +145,21 @@ impl Vec3
         *self - normal * (2.0 * self.dot(normal))
     }
 
-    pub fn refract(&self, normal: Vec3, eta: f64) -> Vec3
+    /// Refracts this unit vector through a surface with `normal` facing against it, `eta`
+    /// being the ratio of the refractive indices on the incoming and the outgoing side.
+    /// `None` under total internal reflection.
+    pub fn refract(&self, normal: Vec3, eta: f64) -> Option<Vec3>
     {
         let d = self.dot(normal);
         let k = 1.0 - eta * eta * (1.0 - d * d);
 
         if k < 0.0
         {
-            Vec3::zero()
+            None
         }
         else
         {
-            *self * eta - normal * (eta * d + k.sqrt())
+            Some(*self * eta - normal * (eta * d + k.sqrt()))
         }
     }
 }
@@ -189,4 +232,19 @@ impl ops::Neg for Vec3
             z: -self.z,
         }
     }
-}
\ No newline at end of file
+}
+impl ops::Index<usize> for Vec3
+{
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64
+    {
+        match axis
+        {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 index out of range: {}", axis),
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{
+    RGB,
+    Material,
+    math::{ Vec3, Rng },
+};
+
+use rand::Rng as _;
+
+use std::f64::consts::PI;
+
+/// The stuff a ray travels through between surfaces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium<'a>
+{
+    /// The index of refraction, `None` inside boundaries that do not bend light, which keep
+    /// the index of whatever surrounds them.
+    pub r_index: Option<f32>,
+    /// The fog, smoke or murk filling the medium, `None` for clear ones.
+    pub volume: Option<&'a Volume>,
+}
+
+impl<'a> Medium<'a>
+{
+    /// The medium the camera and the world around the scene are in.
+    pub fn air() -> Medium<'a>
+    {
+        Medium {
+            r_index: Some(1.0),
+            volume: None,
+        }
+    }
+
+    /// The medium inside objects made of `material`.
+    pub fn of(material: &'a Material) -> Medium<'a>
+    {
+        Medium {
+            r_index: material.r_index(),
+            volume: material.volume.as_ref(),
+        }
+    }
+}
+
+/// A homogeneous participating medium, which absorbs light and scatters it into other
+/// directions everywhere inside it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume
+{
+    /// The fraction of light absorbed per unit of distance, for each channel.
+    pub absorption: RGB,
+    /// The fraction of light scattered into other directions per unit of distance.
+    pub scattering: RGB,
+    /// The asymmetry of the Henyey-Greenstein phase function, from -1 scattering all light
+    /// back where it came from over 0 scattering it evenly to 1 letting it go straight on.
+    pub anisotropy: f32,
+}
+
+/// A volume filling the space around the objects of a scene.
+#[derive(Debug, Clone, Copy)]
+pub struct Atmosphere
+{
+    pub volume: Volume,
+    /// The altitude along +y the atmosphere ends at, for fog lying on the ground. `None` fills
+    /// the whole scene.
+    pub height: Option<f64>,
+}
+
+/// Where a ray travelling through a `Volume` is scattered, picked by `Volume::sample_distance`.
+#[derive(Debug, Clone, Copy)]
+pub struct DistanceSample
+{
+    /// The distance along the ray, `None` if the ray made it through.
+    pub distance: Option<f64>,
+    /// The light surviving up to there, times the scattering coefficient if it is scattered,
+    /// divided by the probability of getting there.
+    pub weight: RGB,
+}
+
+impl Volume
+{
+    /// The fraction of light absorbed or scattered away per unit of distance.
+    pub fn extinction(&self) -> RGB
+    {
+        self.absorption + self.scattering
+    }
+
+    /// The fraction of light that makes it through `distance` of the volume.
+    pub fn transmittance(&self, distance: f64) -> RGB
+    {
+        let extinction = self.extinction();
+        let distance = distance as f32;
+
+        if distance.is_infinite()
+        {
+            let gone = |sigma: f32| if sigma > 0.0 { 0.0 } else { 1.0 };
+
+            return RGB::new(gone(extinction.r), gone(extinction.g), gone(extinction.b));
+        }
+
+        RGB::new(
+            (-extinction.r * distance).exp(),
+            (-extinction.g * distance).exp(),
+            (-extinction.b * distance).exp(),
+        )
+    }
+
+    /// Picks how far a ray gets before it is scattered, if it is before `max_dist`. The
+    /// distance is picked for one random channel and weighted by the density of all three, so
+    /// colored volumes stay unbiased.
+    pub fn sample_distance(&self, max_dist: f64, rng: &mut Rng) -> DistanceSample
+    {
+        let extinction = self.extinction();
+        let channel = match rng.gen_range(0, 3)
+        {
+            0 => extinction.r,
+            1 => extinction.g,
+            _ => extinction.b,
+        };
+
+        let free_flight = -(1.0 - rng.gen::<f64>()).ln() / channel as f64;
+        let distance = free_flight.min(max_dist);
+        let transmittance = self.transmittance(distance);
+
+        let average = |c: RGB| (c.r + c.g + c.b) as f64 / 3.0;
+
+        if distance < max_dist
+        {
+            let pdf = average(extinction * transmittance);
+
+            DistanceSample {
+                distance: Some(distance),
+                weight: if pdf > 0.0 { transmittance * self.scattering / pdf as f32 } else { RGB::black() },
+            }
+        }
+        else
+        {
+            let pdf = average(transmittance);
+
+            DistanceSample {
+                distance: None,
+                weight: if pdf > 0.0 { transmittance / pdf as f32 } else { RGB::black() },
+            }
+        }
+    }
+
+    /// The Henyey-Greenstein phase function, the fraction of light scattered at a point that
+    /// arrives from `wi` and leaves towards `wo`, both pointing away from the point.
+    pub fn phase(&self, wo: Vec3, wi: Vec3) -> f64
+    {
+        henyey_greenstein(wo.dot(wi), self.anisotropy as f64)
+    }
+
+    /// Picks a direction for light leaving towards `wo` to arrive from, following the phase
+    /// function. Returns the direction and its density, which equals the phase function.
+    pub fn sample_phase(&self, wo: Vec3, rng: &mut Rng) -> (Vec3, f64)
+    {
+        let g = self.anisotropy as f64;
+        let (u, v) = (rng.gen::<f64>(), rng.gen::<f64>());
+
+        // The cosine between `wo` and `wi`, where forward scattering leaves `wi` opposite `wo`.
+        let cos_theta = if g.abs() < 1e-3
+        {
+            1.0 - 2.0 * u
+        }
+        else
+        {
+            let term = (1.0 - g * g) / (1.0 + g - 2.0 * g * u);
+
+            -(1.0 + g * g - term * term) / (2.0 * g)
+        };
+
+        let cos_theta = cos_theta.clamp(-1.0, 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+
+        let (tangent, bitangent) = wo.orthonormal_basis();
+        let wi = tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + wo * cos_theta;
+
+        (wi.normalized(), henyey_greenstein(cos_theta, g))
+    }
+}
+
+fn henyey_greenstein(cos_theta: f64, g: f64) -> f64
+{
+    let denominator = 1.0 + g * g + 2.0 * g * cos_theta;
+
+    (1.0 - g * g) / (4.0 * PI * denominator * denominator.max(1e-12).sqrt())
+}
+
+/// How deep transparent objects can be nested inside each other.
+const MAX_DEPTH: usize = 8;
+
+/// The media a ray is inside of, innermost last.
+///
+/// Entering a transparent object pushes its medium, leaving it removes that medium again even
+/// if the ray entered other objects in between, so overlapping objects are handled sensibly.
+#[derive(Debug, Clone, Copy)]
+pub struct MediumStack<'a>
+{
+    media: [Medium<'a>; MAX_DEPTH],
+    len: usize,
+}
+
+impl<'a> MediumStack<'a>
+{
+    /// A ray out in the air.
+    pub fn new() -> MediumStack<'a>
+    {
+        MediumStack {
+            media: [Medium::air(); MAX_DEPTH],
+            len: 0,
+        }
+    }
+
+    /// Whether the ray is out in the air, or the scene's atmosphere, and not inside any object.
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+
+    /// The medium the ray currently travels through.
+    pub fn current(&self) -> Medium<'a>
+    {
+        if self.len == 0 { Medium::air() } else { self.media[self.len - 1] }
+    }
+
+    /// The index of refraction around the ray, that of the innermost medium that has one.
+    pub fn r_index(&self) -> f32
+    {
+        self.media[..self.len].iter().rev().find_map(|medium| medium.r_index).unwrap_or(1.0)
+    }
+
+    /// The stack after entering `medium`. Beyond the maximum depth the innermost medium
+    /// is replaced instead.
+    pub fn entered(&self, medium: Medium<'a>) -> MediumStack<'a>
+    {
+        let mut stack = *self;
+
+        if stack.len == MAX_DEPTH
+        {
+            stack.len -= 1;
+        }
+
+        stack.media[stack.len] = medium;
+        stack.len += 1;
+
+        stack
+    }
+
+    /// The stack after leaving `medium`, removing its innermost entry. Leaving a medium the
+    /// ray was never in, like the inside of an open mesh, changes nothing.
+    pub fn exited(&self, medium: Medium<'a>) -> MediumStack<'a>
+    {
+        let mut stack = *self;
+
+        if let Some(i) = stack.media[..stack.len].iter().rposition(|m| *m == medium)
+        {
+            stack.media.copy_within(i + 1..stack.len, i);
+            stack.len -= 1;
+        }
+
+        stack
+    }
+}
+
+impl<'a> Default for MediumStack<'a>
+{
+    fn default() -> MediumStack<'a>
+    {
+        MediumStack::new()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use rand::SeedableRng;
+
+    const SAMPLES: usize = 100_000;
+
+    fn assert_close(actual: f64, expected: f64)
+    {
+        assert!((actual - expected).abs() < 0.01, "{} against {}", actual, expected);
+    }
+
+    #[test]
+    fn distance_weights_average_to_the_transmittance()
+    {
+        let volume = Volume {
+            absorption: RGB::new(0.1, 0.5, 1.0),
+            scattering: RGB::new(0.4, 0.2, 0.1),
+            anisotropy: 0.0,
+        };
+        let max_dist = 1.5;
+        let mut rng = Rng::seed_from_u64(1);
+
+        let (mut through, mut scattered) = (RGB::black(), RGB::black());
+
+        for _ in 0..SAMPLES
+        {
+            let sample = volume.sample_distance(max_dist, &mut rng);
+
+            match sample.distance
+            {
+                Some(_) => scattered += sample.weight / SAMPLES as f32,
+                None => through += sample.weight / SAMPLES as f32,
+            }
+        }
+
+        // Light scattered somewhere along the ray is the scattering albedo of what did not make
+        // it through.
+        let channels = |c: RGB| [c.r as f64, c.g as f64, c.b as f64];
+        let transmittance = channels(volume.transmittance(max_dist));
+        let (scattering, extinction) = (channels(volume.scattering), channels(volume.extinction()));
+
+        for i in 0..3
+        {
+            assert_close(channels(through)[i], transmittance[i]);
+            assert_close(channels(scattered)[i], scattering[i] / extinction[i] * (1.0 - transmittance[i]));
+        }
+    }
+
+    #[test]
+    fn phase_samples_follow_the_phase_function()
+    {
+        let wo = Vec3::new(1.0, 2.0, -0.5).normalized();
+        let mut rng = Rng::seed_from_u64(2);
+
+        for &anisotropy in [-0.7, 0.0, 0.5, 0.9].iter()
+        {
+            let volume = Volume {
+                absorption: RGB::black(),
+                scattering: RGB::gray(1.0),
+                anisotropy,
+            };
+
+            let mut mean_cos = 0.0;
+
+            for _ in 0..SAMPLES
+            {
+                let (wi, pdf) = volume.sample_phase(wo, &mut rng);
+                let phase = volume.phase(wo, wi);
+
+                assert!((pdf - phase).abs() < 1e-6 * phase.max(1.0), "{} against {}", pdf, phase);
+                mean_cos += wo.dot(wi) / SAMPLES as f64;
+            }
+
+            // Light going on forwards arrives from opposite `wo`, the mean cosine of
+            // Henyey-Greenstein is the anisotropy.
+            assert_close(-mean_cos, anisotropy as f64);
+        }
+    }
+
+    #[test]
+    fn leaving_overlapping_objects_removes_their_own_medium()
+    {
+        let glass = Medium { r_index: Some(1.5), volume: None };
+        let water = Medium { r_index: Some(1.33), volume: None };
+
+        // Into the glass, into the water overlapping it, then out of the glass first.
+        let stack = MediumStack::new().entered(glass).entered(water).exited(glass);
+
+        assert_eq!(stack.current(), water);
+        assert_eq!(stack.r_index(), 1.33);
+
+        let stack = stack.exited(water);
+
+        assert!(stack.is_empty());
+        assert_eq!(stack.current(), Medium::air());
+
+        // Leaving what the ray never entered, like the inside of an open mesh, changes nothing.
+        let stack = MediumStack::new().entered(glass).exited(water);
+
+        assert_eq!(stack.current(), glass);
+    }
+}
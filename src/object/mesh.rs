@@ -0,0 +1,158 @@
+use crate::math::Vec3;
+use crate::{
+    Ray,
+    Object,
+    HitRecord,
+    Material,
+    bvh::{ Aabb, Bvh },
+};
+use super::triangle;
+
+use std::sync::Arc;
+
+/// Vertex attributes that any number of meshes can index into.
+#[derive(Debug, Clone, Default)]
+pub struct VertexBuffers
+{
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+}
+
+/// One triangle of a mesh, as indices into its `VertexBuffers`.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshTriangle
+{
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+}
+
+impl MeshTriangle
+{
+    /// A triangle using the same index for every attribute the buffers have.
+    pub fn indexed(indices: [usize; 3], buffers: &VertexBuffers) -> MeshTriangle
+    {
+        MeshTriangle {
+            positions: indices,
+            normals: if buffers.normals.is_empty() { None } else { Some(indices) },
+            uvs: if buffers.uvs.is_empty() { None } else { Some(indices) },
+        }
+    }
+}
+
+/// An indexed triangle mesh with its own `Bvh` over the triangles.
+#[derive(Debug)]
+pub struct Mesh
+{
+    buffers: Arc<VertexBuffers>,
+    triangles: Vec<MeshTriangle>,
+    bvh: Bvh,
+    material: Material,
+}
+
+impl Mesh
+{
+    /// # Panics
+    ///
+    /// If a triangle indexes outside of `buffers`.
+    pub fn new(
+        buffers: Arc<VertexBuffers>,
+        triangles: Vec<MeshTriangle>,
+        material: Material,
+    ) -> Mesh {
+        for tri in triangles.iter()
+        {
+            let in_range = |indices: Option<[usize; 3]>, len: usize| {
+                indices.is_none_or(|indices| indices.iter().all(|&i| i < len))
+            };
+
+            assert!(in_range(Some(tri.positions), buffers.positions.len()), "position index out of range");
+            assert!(in_range(tri.normals, buffers.normals.len()), "normal index out of range");
+            assert!(in_range(tri.uvs, buffers.uvs.len()), "uv index out of range");
+        }
+
+        let bounds: Vec<_> = triangles
+            .iter()
+            .map(|tri| Aabb::from_points(&Mesh::vertices_of(&buffers, tri)))
+            .collect();
+
+        Mesh {
+            bvh: Bvh::build(&bounds),
+            buffers,
+            triangles,
+            material,
+        }
+    }
+
+    pub fn buffers(&self) -> &Arc<VertexBuffers>
+    {
+        &self.buffers
+    }
+
+    pub fn triangles(&self) -> &[MeshTriangle]
+    {
+        &self.triangles
+    }
+
+    fn vertices_of(buffers: &VertexBuffers, tri: &MeshTriangle) -> [Vec3; 3]
+    {
+        [
+            buffers.positions[tri.positions[0]],
+            buffers.positions[tri.positions[1]],
+            buffers.positions[tri.positions[2]],
+        ]
+    }
+}
+
+impl Object for Mesh
+{
+    fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<HitRecord<'_>>
+    {
+        self.bvh.hit(ray, ray_range, |index, ray, ray_range| {
+            let tri = &self.triangles[index];
+            let vertices = Mesh::vertices_of(&self.buffers, tri);
+            let (t, bary) = triangle::intersect(ray, vertices, ray_range)?;
+
+            let normal = match tri.normals
+            {
+                Some(n) => triangle::interpolate([
+                    self.buffers.normals[n[0]],
+                    self.buffers.normals[n[1]],
+                    self.buffers.normals[n[2]],
+                ], bary).normalized(),
+                None => triangle::face_normal(vertices),
+            };
+
+            let (uv, (tangent, bitangent)) = match tri.uvs
+            {
+                Some(uv) => {
+                    let uvs = [self.buffers.uvs[uv[0]], self.buffers.uvs[uv[1]], self.buffers.uvs[uv[2]]];
+
+                    (triangle::interpolate_uv(uvs, bary), triangle::tangents(vertices, uvs, normal))
+                },
+                None => ((0.0, 0.0), normal.orthonormal_basis()),
+            };
+
+            Some(HitRecord {
+                offset: t,
+                point: ray.point_at_dist(t),
+                normal,
+                tangent,
+                bitangent,
+                uv,
+                material: &self.material,
+            })
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb
+    {
+        self.bvh.bounds()
+    }
+
+    fn material(&self) -> &Material
+    {
+        &self.material
+    }
+}
@@ -2,20 +2,41 @@ use crate::{
     Ray,
     math::Vec3,
     Material,
+    bvh::Aabb,
 };
 
 mod sphere;
 pub use sphere::Sphere;
 
-pub trait Object: std::fmt::Debug
+mod triangle;
+pub use triangle::Triangle;
+
+mod mesh;
+pub use mesh::{ Mesh, MeshTriangle, VertexBuffers };
+
+pub trait Object: std::fmt::Debug + Send + Sync
 {
-    fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<HitRecord>;
+    fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<HitRecord<'_>>;
+
+    /// A box enclosing the whole object, used to build the scene's `Bvh`.
+    fn bounding_box(&self) -> Aabb;
+
+    /// The material the whole object is made of.
+    fn material(&self) -> &Material;
 }
 
-#[derive(Debug)]
-pub struct HitRecord
+#[derive(Debug, Clone, Copy)]
+pub struct HitRecord<'a>
 {
     pub offset: f64,
+    pub point: Vec3,
     pub normal: Vec3,
-    pub material: Material,
+    /// How the hit point moves along the surface with the texture coordinate `u`. Not
+    /// normalized, its length is needed for bump mapping.
+    pub tangent: Vec3,
+    /// How the hit point moves along the surface with `v`.
+    pub bitangent: Vec3,
+    /// Texture coordinates at the hit, (0, 0) for objects without any.
+    pub uv: (f64, f64),
+    pub material: &'a Material,
 }
\ No newline at end of file
@@ -4,9 +4,12 @@ use crate::{
     Object,
     HitRecord,
     Material,
+    bvh::Aabb,
 };
 
-#[derive(Debug, Clone, Copy)]
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone)]
 pub struct Sphere
 {
     center: Vec3,
@@ -31,7 +34,7 @@ impl Sphere
 
 impl Object for Sphere
 {
-    fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<HitRecord>
+    fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<HitRecord<'_>>
     {
         let oc = ray.origin - self.center;
 
@@ -40,7 +43,7 @@ impl Object for Sphere
         let c = oc.dot(oc) - self.radius.powi(2);
         let discriminant = b * b - 4.0 * a * c;
 
-        if discriminant > std::f64::EPSILON
+        if discriminant > f64::EPSILON
         {
             let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
             let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
@@ -48,14 +51,62 @@ impl Object for Sphere
 
             if t < ray_range.1 && t > ray_range.0
             {
+                let point = ray.point_at_dist(t);
+                let normal = (point - self.center) / self.radius;
+                let (tangent, bitangent) = spherical_tangents(point - self.center, normal);
+
                 return Some(HitRecord {
                     offset: t,
-                    normal: (ray.point_at_dist(t) - self.center) / self.radius,
-                    material: self.material,
+                    point,
+                    normal,
+                    tangent,
+                    bitangent,
+                    uv: spherical_uv(normal * self.radius.signum()),
+                    material: &self.material,
                 });
             }
         }
 
         None
     }
-}
\ No newline at end of file
+
+    fn bounding_box(&self) -> Aabb
+    {
+        let radius = self.radius.abs();
+        let radius = Vec3::new(radius, radius, radius);
+
+        Aabb::new(self.center - radius, self.center + radius)
+    }
+
+    fn material(&self) -> &Material
+    {
+        &self.material
+    }
+}
+/// Texture coordinates of the point on the unit sphere at `direction`: `u` goes once around
+/// the y axis, eastwards as seen from outside with the seam at +z, and `v` from the south pole
+/// at 0 to the north pole at 1.
+fn spherical_uv(direction: Vec3) -> (f64, f64)
+{
+    let longitude = direction.x.atan2(-direction.z);
+    let latitude = direction.y.clamp(-1.0, 1.0).asin();
+
+    (0.5 + longitude / (2.0 * PI), 0.5 + latitude / PI)
+}
+
+/// The derivatives of the point `d` from the center with respect to the coordinates of
+/// `spherical_uv`. At the poles, where they vanish, any frame around `normal` is used.
+fn spherical_tangents(d: Vec3, normal: Vec3) -> (Vec3, Vec3)
+{
+    let rho = (d.x * d.x + d.z * d.z).sqrt();
+
+    if rho < 1e-9 * d.length()
+    {
+        return normal.orthonormal_basis();
+    }
+
+    (
+        Vec3::new(-d.z, 0.0, d.x) * (2.0 * PI),
+        Vec3::new(-d.x * d.y / rho, rho, -d.y * d.z / rho) * PI,
+    )
+}
@@ -0,0 +1,221 @@
+use crate::math::Vec3;
+use crate::{
+    Ray,
+    Object,
+    HitRecord,
+    Material,
+    bvh::Aabb,
+};
+
+#[derive(Debug, Clone)]
+pub struct Triangle
+{
+    vertices: [Vec3; 3],
+    /// Per-vertex normals, the face normal is used if `None`.
+    normals: Option<[Vec3; 3]>,
+    uvs: [(f64, f64); 3],
+    material: Material,
+}
+
+impl Triangle
+{
+    /// Creates a flat shaded triangle, the front face is the one the vertices wind
+    /// counter-clockwise around.
+    pub fn new(vertices: [Vec3; 3], material: Material) -> Triangle
+    {
+        Triangle {
+            vertices,
+            normals: None,
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            material,
+        }
+    }
+
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Triangle
+    {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Triangle
+    {
+        self.uvs = uvs;
+        self
+    }
+}
+
+impl Object for Triangle
+{
+    fn hit(&self, ray: Ray, ray_range: (f64, f64)) -> Option<HitRecord<'_>>
+    {
+        let (t, bary) = intersect(ray, self.vertices, ray_range)?;
+
+        let normal = match self.normals
+        {
+            Some(normals) => interpolate(normals, bary).normalized(),
+            None => face_normal(self.vertices),
+        };
+
+        let (tangent, bitangent) = tangents(self.vertices, self.uvs, normal);
+
+        Some(HitRecord {
+            offset: t,
+            point: ray.point_at_dist(t),
+            normal,
+            tangent,
+            bitangent,
+            uv: interpolate_uv(self.uvs, bary),
+            material: &self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb
+    {
+        Aabb::from_points(&self.vertices)
+    }
+
+    fn material(&self) -> &Material
+    {
+        &self.material
+    }
+}
+
+/// Watertight ray/triangle intersection (Woop, Benthin and Wald 2013).
+///
+/// Returns the distance along the ray and the barycentric weights of the three vertices.
+/// Rays through a shared edge or vertex hit at least one of the triangles sharing it, so meshes
+/// have no cracks, but they may hit more than one.
+pub fn intersect(ray: Ray, vertices: [Vec3; 3], ray_range: (f64, f64)) -> Option<(f64, [f64; 3])>
+{
+    // Permute the axes so the ray direction's largest component becomes z.
+    let abs = Vec3::new(ray.dir.x.abs(), ray.dir.y.abs(), ray.dir.z.abs());
+    let kz = if abs.x > abs.y && abs.x > abs.z { 0 } else if abs.y > abs.z { 1 } else { 2 };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+
+    if ray.dir[kz] < 0.0
+    {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // Shear so the ray points along +z.
+    let sx = ray.dir[kx] / ray.dir[kz];
+    let sy = ray.dir[ky] / ray.dir[kz];
+    let sz = 1.0 / ray.dir[kz];
+
+    let a = vertices[0] - ray.origin;
+    let b = vertices[1] - ray.origin;
+    let c = vertices[2] - ray.origin;
+
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    // Scaled barycentric coordinates, the edge functions of the sheared triangle.
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0)
+    {
+        return None;
+    }
+
+    let det = u + v + w;
+
+    if det == 0.0
+    {
+        return None;
+    }
+
+    let t_scaled = u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz];
+    let t = t_scaled / det;
+
+    if t <= ray_range.0 || t >= ray_range.1
+    {
+        return None;
+    }
+
+    Some((t, [u / det, v / det, w / det]))
+}
+
+pub fn face_normal(vertices: [Vec3; 3]) -> Vec3
+{
+    (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]).normalized()
+}
+
+pub fn interpolate(values: [Vec3; 3], bary: [f64; 3]) -> Vec3
+{
+    values[0] * bary[0] + values[1] * bary[1] + values[2] * bary[2]
+}
+
+pub fn interpolate_uv(uvs: [(f64, f64); 3], bary: [f64; 3]) -> (f64, f64)
+{
+    (
+        uvs[0].0 * bary[0] + uvs[1].0 * bary[1] + uvs[2].0 * bary[2],
+        uvs[0].1 * bary[0] + uvs[1].1 * bary[1] + uvs[2].1 * bary[2],
+    )
+}
+
+/// The derivatives of the position with respect to the texture coordinates, which are
+/// constant over a triangle. If the coordinates are degenerate any frame around `normal`
+/// is used.
+pub fn tangents(vertices: [Vec3; 3], uvs: [(f64, f64); 3], normal: Vec3) -> (Vec3, Vec3)
+{
+    let (du1, dv1) = (uvs[1].0 - uvs[0].0, uvs[1].1 - uvs[0].1);
+    let (du2, dv2) = (uvs[2].0 - uvs[0].0, uvs[2].1 - uvs[0].1);
+    let det = du1 * dv2 - dv1 * du2;
+
+    if det.abs() < 1e-12
+    {
+        return normal.orthonormal_basis();
+    }
+
+    let e1 = vertices[1] - vertices[0];
+    let e2 = vertices[2] - vertices[0];
+
+    ((e1 * dv2 - e2 * dv1) / det, (e2 * du1 - e1 * du2) / det)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn shared_edges_leave_no_cracks()
+    {
+        let square = [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0),
+        ];
+        let triangles = [[square[0], square[1], square[2]], [square[0], square[2], square[3]]];
+
+        // Through the diagonal both triangles share, and through the corners they share.
+        for &t in [-0.7, -0.3, 0.0, 0.1, 0.5, 0.999, 1.0, -1.0].iter()
+        {
+            let ray = Ray::new(Vec3::new(t, t, -1.0), Vec3::new(0.0, 0.0, 1.0));
+            let hits = triangles.iter().filter(|&&vertices| intersect(ray, vertices, (0.0, f64::INFINITY)).is_some()).count();
+
+            assert!(hits >= 1, "ray through ({}, {}) slipped through", t, t);
+        }
+    }
+
+    #[test]
+    fn barycentrics_locate_the_hit()
+    {
+        let vertices = [Vec3::new(0.0, 0.0, 2.0), Vec3::new(1.0, 0.0, 2.0), Vec3::new(0.0, 1.0, 2.0)];
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 0.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let (t, weights) = intersect(ray, vertices, (0.0, f64::INFINITY)).unwrap();
+
+        assert!((t - 2.0).abs() < 1e-9);
+        assert!((weights[0] - 0.25).abs() < 1e-9 && (weights[1] - 0.25).abs() < 1e-9 && (weights[2] - 0.5).abs() < 1e-9);
+        assert!(intersect(ray, vertices, (0.0, 1.0)).is_none());
+    }
+}
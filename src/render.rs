@@ -0,0 +1,265 @@
+use crate::{
+    Scene,
+    RGB,
+    math::Rng,
+    film::{ Film, FilmSample },
+    filter::Filter,
+    integrator::{ Integrator, PhotonMap },
+};
+
+use rand::Rng as _;
+
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderSettings
+{
+    pub width: usize,
+    pub height: usize,
+    /// Number of samples taken per pixel.
+    pub samples: u32,
+    /// Maximum number of bounces before a path is terminated.
+    pub max_bounces: u32,
+    /// Seed for the random number generators, `None` picks a random seed.
+    pub seed: Option<u64>,
+    /// Number of worker threads.
+    pub threads: usize,
+    /// Where within each pixel the samples are taken.
+    pub sampler: PixelSampler,
+    /// The filter samples are weighted by when they are accumulated into pixels.
+    pub filter: Filter,
+    /// How the light arriving along camera rays is computed.
+    pub integrator: Integrator,
+}
+
+impl Default for RenderSettings
+{
+    fn default() -> RenderSettings
+    {
+        RenderSettings {
+            width: 640,
+            height: 480,
+            samples: 16,
+            max_bounces: 10,
+            seed: None,
+            threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            sampler: PixelSampler::Stratified,
+            filter: Filter::default(),
+            integrator: Integrator::default(),
+        }
+    }
+}
+
+impl RenderSettings
+{
+    /// The seed to render with, drawing a random one if none is set.
+    pub fn seed_or_random(&self) -> u64
+    {
+        self.seed.unwrap_or_else(rand::random)
+    }
+}
+
+/// How sample positions within a pixel are chosen for each pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PixelSampler
+{
+    /// Always the pixel center, no anti-aliasing.
+    Center,
+    /// Uniformly random positions.
+    Random,
+    /// One random position within each cell of a grid over the pixel, one cell per pass.
+    /// Passes beyond the largest square grid that fits the sample count are random.
+    Stratified,
+}
+
+impl PixelSampler
+{
+    /// The offset within the pixel, in `[0, 1)²`, for pass `pass` out of `samples`.
+    pub fn offset(&self, pass: u32, samples: u32, rng: &mut Rng) -> (f64, f64)
+    {
+        match self
+        {
+            PixelSampler::Center => (0.5, 0.5),
+            PixelSampler::Random => (rng.gen(), rng.gen()),
+            PixelSampler::Stratified => {
+                let cells = (samples as f64).sqrt() as u32;
+
+                if pass >= cells * cells
+                {
+                    return (rng.gen(), rng.gen());
+                }
+
+                let (cx, cy) = (pass % cells, pass / cells);
+
+                (
+                    (cx as f64 + rng.gen::<f64>()) / cells as f64,
+                    (cy as f64 + rng.gen::<f64>()) / cells as f64,
+                )
+            },
+        }
+    }
+}
+
+impl fmt::Display for PixelSampler
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            PixelSampler::Center     => write!(f, "center"),
+            PixelSampler::Random     => write!(f, "random"),
+            PixelSampler::Stratified => write!(f, "stratified"),
+        }
+    }
+}
+
+impl FromStr for PixelSampler
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PixelSampler, String>
+    {
+        match s
+        {
+            "center"     => Ok(PixelSampler::Center),
+            "random"     => Ok(PixelSampler::Random),
+            "stratified" => Ok(PixelSampler::Stratified),
+            _ => Err(format!("unknown sampler '{}', expected one of center, random or stratified", s)),
+        }
+    }
+}
+
+/// Renders `scene` without a window.
+///
+/// Returns the filtered linear RGB values, three `f32`s per pixel, row by row from the top.
+/// `progress` is called after every finished sample pass with the number of passes done so far.
+pub fn render<F>(scene: &mut Scene, settings: &RenderSettings, mut progress: F) -> Vec<f32>
+where F: FnMut(u32)
+{
+    let (width, height) = (settings.width, settings.height);
+
+    scene.camera().set_w_h((width, height));
+
+    let scene = &*scene;
+    let seed = settings.seed_or_random();
+
+    let mut film = Film::new(width, height, settings.filter);
+    let mut samples = vec![FilmSample { x: 0.0, y: 0.0, color: RGB::black() }; width * height];
+    let mut splats = vec![];
+
+    for index in 0..settings.samples
+    {
+        let pass = Pass::new(scene, settings, index, seed);
+
+        splats.clear();
+        trace_lines(scene, 0..height, &pass, settings, &mut samples, &mut splats);
+        film.add_samples(&samples);
+        film.add_splats(&splats);
+
+        progress(index + 1);
+    }
+
+    film.pixels()
+}
+
+/// One sample pass over the image, with what the integrator prepares for all of its samples.
+pub struct Pass
+{
+    pub index: u32,
+    /// The render's seed, which the generators of the pass are derived from.
+    pub seed: u64,
+    /// The photons gathered during the pass, for integrators that use a photon map.
+    pub photons: Option<PhotonMap>,
+}
+
+impl Pass
+{
+    pub fn new(scene: &Scene, settings: &RenderSettings, index: u32, seed: u64) -> Pass
+    {
+        Pass {
+            index,
+            seed,
+            photons: settings.integrator.photon_map(scene, index, seed, settings.max_bounces, settings.threads),
+        }
+    }
+}
+
+/// Traces one sample for every pixel on `lines` of `pass`, spread over `settings.threads`
+/// threads.
+///
+/// `out` receives one sample per pixel, row by row, ready to be added to a `Film`. Light the
+/// integrator traces from the lights onto the film is appended to `splats`, line by line. Every
+/// line is traced with its own generator derived from the pass and the line number, so the
+/// result does not depend on the number of threads.
+pub fn trace_lines(
+    scene: &Scene,
+    lines: Range<usize>,
+    pass: &Pass,
+    settings: &RenderSettings,
+    out: &mut [FilmSample],
+    splats: &mut Vec<FilmSample>,
+) {
+    let width = scene.camera.width();
+
+    let mut line_splats = vec![vec![]; lines.len()];
+    let queue = Mutex::new(out.chunks_mut(width).zip(line_splats.iter_mut()).zip(lines));
+
+    std::thread::scope(|s| {
+        for _ in 0..settings.threads.max(1)
+        {
+            s.spawn(|| loop
+            {
+                let next = queue.lock().unwrap().next();
+
+                let ((row, row_splats), y) = match next
+                {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                let mut rng = pass_rng(pass.seed, pass.index, y);
+
+                for (x, sample) in row.iter_mut().enumerate()
+                {
+                    let (dx, dy) = settings.sampler.offset(pass.index, settings.samples, &mut rng);
+                    let (fx, fy) = (x as f64 + dx, y as f64 + dy);
+
+                    let color = match scene.camera.sample_ray(fx, fy, &mut rng)
+                    {
+                        Some(ray) => {
+                            let photons = pass.photons.as_ref();
+
+                            settings.integrator.radiance(scene, ray, settings.max_bounces, photons, &mut rng, row_splats)
+                        },
+                        None => RGB::black(),
+                    };
+
+                    *sample = FilmSample { x: fx, y: fy, color };
+                }
+            });
+        }
+    });
+
+    for line in line_splats
+    {
+        splats.extend(line);
+    }
+}
+
+/// The generator for line or chunk `index` of pass number `pass`.
+pub fn pass_rng(seed: u64, pass: u32, index: usize) -> Rng
+{
+    use rand::SeedableRng;
+
+    // SplitMix64 finalizer, spreads neighbouring indices and passes over the whole seed space.
+    let mut z = seed
+        ^ (pass as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (index as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    Rng::seed_from_u64(z ^ (z >> 31))
+}
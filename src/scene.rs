@@ -2,151 +2,231 @@ use crate::{
     Camera,
     Object,
     Light,
+    Sky,
     Ray,
     HitRecord,
     RGB,
-    math::Vec3,
+    math::{ Vec3, Rng },
+    bvh::{ Bvh, Aabb },
+    medium::{ Atmosphere, Volume },
 };
 
+use rand::{ Rng as _, SeedableRng };
+
 pub struct Scene
 {
-    pub sky: RGB,
+    pub sky: Sky,
     pub camera: Camera,
-    pub objects: Vec<Box<Object>>,
+    objects: Vec<Box<dyn Object>>,
     pub lights: Vec<Light>,
-    pub rng: rand::rngs::StdRng,
+    /// The fog or haze filling the space around the objects, see `atmosphere_span`.
+    pub atmosphere: Option<Atmosphere>,
+    bvh: Bvh,
+    /// The bounds of the objects and the lights, distant lights aside.
+    bounds: Aabb,
+    /// The bounds of the objects with perfectly sharp materials, empty if there are none.
+    specular_bounds: Aabb,
 }
 
 impl Scene
 {
-    pub fn new(sky: RGB, camera: Camera, objects: Vec<Box<Object>>, lights: Vec<Light>) -> Scene
+    pub fn new(sky: Sky, camera: Camera, objects: Vec<Box<dyn Object>>, lights: Vec<Light>) -> Scene
     {
-        use rand::FromEntropy;
+        let object_bounds: Vec<_> = objects.iter().map(|object| object.bounding_box()).collect();
+        let bvh = Bvh::build(&object_bounds);
+
+        let mut bounds = lights.iter()
+            .filter_map(|light| light.bounding_box())
+            .fold(bvh.bounds(), |bounds, light| bounds.union(light));
+
+        // Keep a scene without any objects or local lights around the camera.
+        if bounds.min.x > bounds.max.x
+        {
+            bounds = Aabb::new(camera.pos, camera.pos);
+        }
 
-        Scene {
+        let specular_bounds = objects.iter()
+            .filter(|object| object.material().bsdf.is_specular())
+            .fold(Aabb::empty(), |bounds, object| bounds.union(object.bounding_box()));
+
+        let mut scene = Scene {
             sky,
             camera,
             objects,
             lights,
-            rng: rand::rngs::StdRng::from_entropy(),
+            atmosphere: None,
+            bvh,
+            bounds,
+            specular_bounds,
+        };
+
+        if scene.camera.lens.autofocus
+        {
+            scene.autofocus();
         }
+
+        scene
     }
 
-    pub fn camera(&mut self) -> &mut Camera
+    /// Fills the space around the objects with `atmosphere`.
+    pub fn with_atmosphere(mut self, atmosphere: Atmosphere) -> Scene
     {
-        &mut self.camera
+        self.atmosphere = Some(atmosphere);
+        self
     }
 
-    pub fn trace_ray(&mut self, ray: Ray, inside: bool, rem_bounces: u32) -> RGB
+    /// Sets the focus distance to whatever the ray through the center of the image hits.
+    /// Returns the new distance, or `None` if the ray hits nothing and the focus is left alone.
+    pub fn autofocus(&mut self) -> Option<f64>
     {
-        if rem_bounces <= 0
-        {
-            return RGB::black();
-        }
+        // Cut out parts of surfaces are decided randomly, focus on the same ones every time.
+        let mut rng = Rng::seed_from_u64(0);
+        let distance = self.hit(Ray::new(self.camera.pos, self.camera.forward()), &mut rng)?.offset;
 
-        if let Some(record) = self.hit(ray)
-        {
-            let hit_point = ray.point_at_dist(record.offset);
+        self.camera.lens.focus_distance = distance;
 
-            if inside
-            {
-                // TODO roughness on ray exit
-                // TODO total internal reflection
-                return self.trace_ray(Ray::new(hit_point, ray.dir.refract(-record.normal, 1.0 / record.material.r_index as f64)), false, rem_bounces);
-            }
+        Some(distance)
+    }
 
-            let mut color = RGB::black();
+    pub fn objects(&self) -> &[Box<dyn Object>]
+    {
+        &self.objects
+    }
 
-            for light in self.lights.iter()
-            {
-                match light
-                {
-                    Light::Hemi(hemi) => {
-                        if record.material.reflectivity != 1.0
-                        {
-                            if let None = self.hit(Ray::new(hit_point, -hemi.direction))
-                            {
-                                let mut intensity = (-record.normal.dot(hemi.direction)).max(0.0) as f32;
-
-                                if record.material.reflectivity != 0.0
-                                {
-                                    intensity = intensity.powf(1.0 / (1.0 - record.material.reflectivity));
-                                    intensity *= (record.material.reflectivity - 2.0) / (record.material.reflectivity - 1.0);
-                                }
-
-                                color += hemi.color * intensity; // TODO diffuse using reflectivity
-                            }
-                        }
-                    },
-                }
-            }
+    /// The center and radius of a sphere around the objects and the lights, distant lights
+    /// shine into the scene through it.
+    pub fn bounding_sphere(&self) -> (Vec3, f64)
+    {
+        let center = self.bounds.center();
+        let radius = self.bounds.extent().length() * 0.5;
 
-            if record.material.reflectivity == 1.0
-            {
-                color += self.trace_ray(ray.reflect_at(record.offset, record.normal), false, rem_bounces - 1);
-            }
-            else if record.material.reflectivity == 0.0
-            {
-                let dir = Vec3::random_half_sphere(&mut self.rng, record.normal);
-                color += self.trace_ray(Ray::new(hit_point, dir), false, rem_bounces - 1);
-            }
-            else
-            {
-                let diffuse = Vec3::random_half_sphere(&mut self.rng, record.normal);
-                let reflective = ray.dir.reflect(record.normal);
+        (center, radius.max(1e-3))
+    }
 
-                let dir = (diffuse * (1.0 - record.material.reflectivity as f64) + reflective * record.material.reflectivity as f64).normalized();
+    /// Like `bounding_sphere`, but only around the objects with perfectly sharp materials,
+    /// which all caustics pass through. `None` if there are none.
+    pub fn specular_bounding_sphere(&self) -> Option<(Vec3, f64)>
+    {
+        if self.specular_bounds.min.x > self.specular_bounds.max.x
+        {
+            return None;
+        }
 
-                color += self.trace_ray(Ray::new(hit_point, dir), false, rem_bounces - 1);
-            }
+        let center = self.specular_bounds.center();
+        let radius = self.specular_bounds.extent().length() * 0.5;
 
+        Some((center, radius.max(1e-3)))
+    }
 
-            if record.material.opacity < 1.0
-            {
-                let refract_dir = ray.dir.refract(record.normal, record.material.r_index as f64);
+    pub fn camera(&mut self) -> &mut Camera
+    {
+        &mut self.camera
+    }
 
-                if record.material.reflectivity == 1.0
-                {
-                    let inner_color = self.trace_ray(Ray::new(hit_point, ray.dir.refract(record.normal, record.material.r_index as f64)), true, rem_bounces - 1);
+    /// The closest surface `ray` hits, skipping the parts cut away by opacity maps.
+    pub fn hit(&self, ray: Ray, rng: &mut Rng) -> Option<HitRecord<'_>>
+    {
+        self.hit_before(ray, f64::INFINITY, rng)
+    }
 
-                    color = color * record.material.opacity + inner_color * (1.0 - record.material.opacity);
-                }
-                else
-                {
-                    let random_dir = Vec3::random_half_sphere(&mut self.rng, -record.normal);
-                    let dir = (random_dir * (1.0 - record.material.reflectivity as f64) + refract_dir * record.material.reflectivity as f64).normalized();
+    /// The closest surface `ray` hits before `max_dist`, like `hit`.
+    pub fn hit_before(&self, ray: Ray, max_dist: f64, rng: &mut Rng) -> Option<HitRecord<'_>>
+    {
+        self.bvh.hit(ray, (0.001, max_dist), |index, ray, ray_range| {
+            self.hit_object(index, ray, ray_range, rng)
+        })
+    }
 
-                    let inner_color = self.trace_ray(Ray::new(hit_point, dir), true, rem_bounces - 1);
+    /// The closest hit with object `index` that is not cut away by its material's opacity,
+    /// which lets a matching fraction of rays through.
+    fn hit_object(&self, index: usize, ray: Ray, ray_range: (f64, f64), rng: &mut Rng) -> Option<HitRecord<'_>>
+    {
+        let mut range = ray_range;
 
-                    color = color * record.material.opacity + inner_color * (1.0 - record.material.opacity);
-                }
+        loop
+        {
+            let record = self.objects[index].hit(ray, range)?;
+            let opacity = record.material.opacity_at(&record);
+
+            if opacity >= 1.0 || rng.gen::<f32>() < opacity
+            {
+                return Some(record);
             }
 
-            color * record.material.color
+            range.0 = record.offset;
         }
-        else
+    }
+
+    /// The closest light surface `ray` hits before `max_dist`, if any, with the distance to it
+    /// and its radiance.
+    pub fn hit_light(&self, ray: Ray, max_dist: f64) -> Option<(&Light, f64, RGB)>
+    {
+        let mut closest = None;
+        let mut max_dist = max_dist;
+
+        for light in self.lights.iter()
         {
-            self.sky
+            if let Some((dist, radiance)) = light.hit(ray, (0.001, max_dist))
+            {
+                max_dist = dist;
+                closest = Some((light, dist, radiance));
+            }
         }
+
+        closest
     }
 
-    fn hit(&self, ray: Ray) -> Option<HitRecord>
+    /// The atmosphere and the stretch of `ray` before `max_dist` it is in.
+    ///
+    /// The atmosphere fills a sphere around the objects, the lights and the camera, up to its
+    /// height if it has one, so light from the sky and distant lights makes it into the scene.
+    pub fn atmosphere_span(&self, ray: Ray, max_dist: f64) -> Option<(&Volume, (f64, f64))>
     {
-        let mut nearest = std::f64::INFINITY;
-        let mut current_record = None;
+        let atmosphere = self.atmosphere.as_ref()?;
+
+        let bounds = self.bounds.grow(self.camera.pos);
+        let center = bounds.center();
+        let radius = (bounds.extent().length() * 0.5).max(1e-3);
 
-        for object in self.objects.iter()
+        let oc = ray.origin - center;
+        let b = oc.dot(ray.dir);
+        let c = oc.dot(oc) - radius * radius;
+        let discriminant = b * b - c;
+
+        if discriminant <= 0.0
         {
-            if let Some(record) = object.hit(ray, (0.001, std::f64::INFINITY))
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        let (mut start, mut end) = ((-b - root).max(0.0), (-b + root).min(max_dist));
+
+        if let Some(height) = atmosphere.height
+        {
+            let crossing = (height - ray.origin.y) / ray.dir.y;
+
+            if ray.dir.y > 0.0
             {
-                if record.offset < nearest
-                {
-                    nearest = record.offset;
-                    current_record = Some(record);
-                }
+                end = end.min(crossing);
+            }
+            else if ray.dir.y < 0.0
+            {
+                start = start.max(crossing);
+            }
+            else if ray.origin.y >= height
+            {
+                return None;
             }
         }
 
-        current_record
+        if start < end { Some((&atmosphere.volume, (start, end))) } else { None }
     }
-}
\ No newline at end of file
+
+    /// Whether anything blocks `ray` before `max_dist`.
+    pub fn occluded(&self, ray: Ray, max_dist: f64, rng: &mut Rng) -> bool
+    {
+        self.bvh.any_hit(ray, (0.001, max_dist), |index, ray, ray_range| {
+            self.hit_object(index, ray, ray_range, rng).is_some()
+        })
+    }
+}
@@ -0,0 +1,2175 @@
+//! The versioned TOML scene description.
+//!
+//! A scene file is parsed into a `SceneDesc`, which mirrors the file one to one and can be
+//! written back out with `SceneDesc::to_toml`. `SceneDesc::build` turns it into a `Scene`.
+
+use crate::{
+    Scene,
+    Camera,
+    camera::{ Lens, Projection, FisheyeMapping },
+    Sphere,
+    Triangle,
+    Mesh,
+    Object,
+    Light,
+    Sky,
+    sky::{ Environment, PhysicalSky },
+    Material,
+    material::{ TextureMaps, BumpMap },
+    texture::{ Texture, ImageTexture, Wrap, Mapping, TextureSpace, Checker, Gradient, Noise, Voronoi, VoronoiMode },
+    bsdf::{ Bsdf, Conductor, Dielectric, Metal, Principled },
+    medium::{ Atmosphere, Volume },
+    RGB,
+    math::Vec3,
+    light,
+    render::{ RenderSettings, PixelSampler },
+    filter::Filter,
+    integrator::Integrator,
+    object::{ MeshTriangle, VertexBuffers },
+    import::{ self, ImportError },
+};
+
+use serde::{ Serialize, Deserialize };
+use toml::Spanned;
+use toml_edit::{ ImDocument, Item };
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+
+/// The newest scene format version this build understands.
+pub const VERSION: u32 = 2;
+
+/// The source of the demo scene, `assets/scenes/demo.toml`.
+pub const DEMO: &str = include_str!("../assets/scenes/demo.toml");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDesc
+{
+    pub version: u32,
+    pub camera: CameraDesc,
+    pub sky: SkyDesc,
+    /// Fog or haze filling the space around the objects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atmosphere: Option<AtmosphereDesc>,
+    #[serde(default, skip_serializing_if = "RenderDesc::is_empty")]
+    pub render: RenderDesc,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub textures: BTreeMap<String, TextureDesc>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, MaterialDesc>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDesc>,
+    #[serde(default)]
+    pub lights: Vec<LightDesc>,
+    /// Directory relative paths in the scene are resolved against.
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDesc
+{
+    pub position: [f64; 3],
+    pub target: [f64; 3],
+    #[serde(default = "default_up")]
+    pub up: [f64; 3],
+    /// Vertical field of view in degrees.
+    #[serde(default = "default_fov")]
+    pub fov: f64,
+    /// Radius of the lens aperture, zero for a pinhole camera.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub aperture: f64,
+    /// Distance to the plane in focus, defaults to the distance to `target`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_distance: Option<f64>,
+    /// Focus on whatever is in the center of the image instead.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub autofocus: bool,
+    /// Number of aperture blades, zero for a round aperture.
+    #[serde(default, skip_serializing_if = "is_zero_u32")]
+    pub blades: u32,
+    /// Rotation of the aperture blades in degrees.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub blade_rotation: f64,
+    #[serde(default, skip_serializing_if = "ProjectionDesc::is_perspective")]
+    pub projection: ProjectionDesc,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ProjectionDesc
+{
+    /// Uses the camera's `fov`.
+    #[default]
+    Perspective,
+    Orthographic {
+        /// Height of the view in scene units.
+        height: f64,
+    },
+    Fisheye {
+        /// Angle across the image circle in degrees.
+        #[serde(default = "default_fisheye_fov")]
+        fov: f64,
+        #[serde(default)]
+        mapping: FisheyeMappingDesc,
+    },
+    Equirectangular,
+    Cubemap,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FisheyeMappingDesc
+{
+    #[default]
+    Equidistant,
+    Equisolid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SkyDesc
+{
+    Color { color: [f64; 3] },
+    /// An equirectangular `.hdr` or `.pfm` image that also lights the scene.
+    Environment {
+        path: PathBuf,
+        /// Rotation around the vertical axis in degrees.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        rotation: f64,
+        #[serde(default = "default_one")]
+        intensity: f64,
+    },
+    /// The Preetham daylight model.
+    Physical {
+        /// Degrees above the horizon.
+        sun_elevation: f64,
+        /// Degrees from +z towards +x.
+        #[serde(default)]
+        sun_azimuth: f64,
+        #[serde(default = "default_turbidity")]
+        turbidity: f64,
+        #[serde(default = "default_one")]
+        intensity: f64,
+        /// Adds the matching sun light to the scene's lights.
+        #[serde(default = "default_true")]
+        sun: bool,
+        /// Angular diameter of the sun disk in degrees.
+        #[serde(default = "default_sun_diameter")]
+        sun_diameter: f64,
+    },
+}
+
+/// Render settings stored with the scene. Every field is optional and only overrides the
+/// defaults, command line options in turn override these.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderDesc
+{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounces: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+    pub sampler: Option<PixelSampler>,
+    /// Filter kind, with its default parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+    pub filter: Option<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter_radius: Option<f64>,
+    /// Integrator kind, with its default parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+    pub integrator: Option<Integrator>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ao_distance: Option<f64>,
+    /// Photons sent per pass by the photon mapper.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub photons: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub photon_radius: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub photon_alpha: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDesc
+{
+    Diffuse {
+        #[serde(default = "default_white")]
+        color: [f64; 3],
+        #[serde(default, skip_serializing_if = "is_black")]
+        emission: [f64; 3],
+        #[serde(default, skip_serializing_if = "MapsDesc::is_empty")]
+        maps: MapsDesc,
+    },
+    /// A metal, given either as a measured `metal`, a complex index of refraction `eta` and `k`,
+    /// or the `color` it reflects head-on.
+    Conductor {
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+        metal: Option<Metal>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        eta: Option<[f64; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        k: Option<[f64; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<[f64; 3]>,
+        #[serde(default)]
+        roughness: f64,
+        #[serde(default, skip_serializing_if = "is_black")]
+        emission: [f64; 3],
+        #[serde(default, skip_serializing_if = "MapsDesc::is_empty")]
+        maps: MapsDesc,
+    },
+    /// Glass, water and the like.
+    Dielectric {
+        #[serde(default = "default_r_index")]
+        r_index: f64,
+        #[serde(default)]
+        roughness: f64,
+        /// Tint of the light passing through.
+        #[serde(default = "default_white", skip_serializing_if = "is_white")]
+        color: [f64; 3],
+        #[serde(default, skip_serializing_if = "is_black")]
+        emission: [f64; 3],
+        #[serde(default, skip_serializing_if = "MapsDesc::is_empty")]
+        maps: MapsDesc,
+    },
+    /// The Disney-style uber material, every parameter is optional.
+    Principled(PrincipledDesc),
+    /// No surface at all, for objects that only hold a `volume`.
+    Interface {},
+    /// A material of a glTF asset, picked by `name`, or by its index for unnamed ones.
+    Gltf {
+        path: PathBuf,
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrincipledDesc
+{
+    pub base_color: [f64; 3],
+    pub metallic: f64,
+    pub roughness: f64,
+    pub specular: f64,
+    pub specular_tint: f64,
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
+    pub transmission: f64,
+    pub r_index: f64,
+    #[serde(skip_serializing_if = "is_black")]
+    pub emission: [f64; 3],
+    #[serde(skip_serializing_if = "MapsDesc::is_empty")]
+    pub maps: MapsDesc,
+}
+
+impl Default for PrincipledDesc
+{
+    fn default() -> PrincipledDesc
+    {
+        let defaults = Principled::default();
+
+        PrincipledDesc {
+            base_color: [defaults.base_color.r as f64, defaults.base_color.g as f64, defaults.base_color.b as f64],
+            metallic: defaults.metallic as f64,
+            roughness: defaults.roughness as f64,
+            specular: defaults.specular as f64,
+            specular_tint: defaults.specular_tint as f64,
+            sheen: defaults.sheen as f64,
+            sheen_tint: defaults.sheen_tint as f64,
+            clearcoat: defaults.clearcoat as f64,
+            clearcoat_roughness: defaults.clearcoat_roughness as f64,
+            transmission: defaults.transmission as f64,
+            r_index: defaults.r_index as f64,
+            emission: [0.0; 3],
+            maps: MapsDesc::default(),
+        }
+    }
+}
+
+/// A homogeneous volume, its coefficients are fractions of light per unit of distance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VolumeDesc
+{
+    #[serde(default, skip_serializing_if = "is_black")]
+    pub absorption: [f64; 3],
+    #[serde(default, skip_serializing_if = "is_black")]
+    pub scattering: [f64; 3],
+    /// Between -1 and 1, positive values scatter light forwards.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub anisotropy: f64,
+}
+
+/// A volume filling the space around the objects, like `VolumeDesc`. `height` is the altitude
+/// along +y it ends at, for fog lying on the ground.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AtmosphereDesc
+{
+    #[serde(default, skip_serializing_if = "is_black")]
+    pub absorption: [f64; 3],
+    #[serde(default, skip_serializing_if = "is_black")]
+    pub scattering: [f64; 3],
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub anisotropy: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f64>,
+}
+
+/// Textures driving material parameters, replacing the constant values where given.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MapsDesc
+{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<TextureRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roughness: Option<TextureRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<TextureRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emission: Option<TextureRef>,
+    /// A tangent space normal map, usually an image with `raw` set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normal: Option<TextureRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bump: Option<BumpDesc>,
+}
+
+/// A height map, `height` being the offset in world units for a texture value of 1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BumpDesc
+{
+    pub texture: TextureRef,
+    pub height: f64,
+}
+
+/// Procedural patterns are evaluated at the texture coordinates times `scale` unless their
+/// `space` is `world`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDesc
+{
+    /// A PNG, JPEG, HDR or PFM image. `raw` keeps 8-bit values as stored, for data like
+    /// roughness, instead of gamma decoding them.
+    Image {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+        wrap: Option<Wrap>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        raw: bool,
+        #[serde(default = "default_one")]
+        scale: f64,
+    },
+    Checker {
+        even: [f64; 3],
+        odd: [f64; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+        space: Option<TextureSpace>,
+        #[serde(default = "default_one")]
+        scale: f64,
+    },
+    Gradient {
+        from: [f64; 3],
+        to: [f64; 3],
+        #[serde(default = "default_gradient_axis")]
+        axis: [f64; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+        space: Option<TextureSpace>,
+        #[serde(default = "default_one")]
+        scale: f64,
+    },
+    Noise {
+        low: [f64; 3],
+        high: [f64; 3],
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default = "default_gain")]
+        gain: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+        space: Option<TextureSpace>,
+        #[serde(default = "default_one")]
+        scale: f64,
+    },
+    Voronoi {
+        low: [f64; 3],
+        high: [f64; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+        mode: Option<VoronoiMode>,
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "by_name")]
+        space: Option<TextureSpace>,
+        #[serde(default = "default_one")]
+        scale: f64,
+    },
+}
+
+/// A texture given either inline or by name from the `[textures]` table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TextureRef
+{
+    Named(String),
+    Inline(TextureDesc),
+}
+
+/// A material given either inline or by name from the `[materials]` table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MaterialRef
+{
+    Named(String),
+    Inline(Box<MaterialDesc>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDesc
+{
+    /// `volume` fills the sphere, which needs a material light passes through to be seen.
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: MaterialRef,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        volume: Option<VolumeDesc>,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        normals: Option<[[f64; 3]; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uvs: Option<[[f64; 2]; 3]>,
+        material: MaterialRef,
+    },
+    /// An indexed mesh, `normals` and `uvs` are indexed like `positions` if given. `volume`
+    /// fills it like a sphere's, for closed meshes.
+    Mesh {
+        positions: Vec<[f64; 3]>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        normals: Vec<[f64; 3]>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        uvs: Vec<[f64; 2]>,
+        indices: Vec<[usize; 3]>,
+        material: MaterialRef,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        volume: Option<VolumeDesc>,
+    },
+    /// A Wavefront OBJ file, `material` overrides the materials from its MTL libraries.
+    Obj {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<MaterialRef>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LightDesc
+{
+    Hemi {
+        direction: [f64; 3],
+        color: [f64; 3],
+    },
+    Point {
+        position: [f64; 3],
+        color: [f64; 3],
+        #[serde(default = "default_one")]
+        intensity: f64,
+    },
+    Sun {
+        direction: [f64; 3],
+        color: [f64; 3],
+        /// In degrees.
+        #[serde(default = "default_sun_diameter")]
+        angular_diameter: f64,
+    },
+    /// A parallelogram spanned by `edge_u` and `edge_v` from `corner`, lit on the side
+    /// `edge_u × edge_v` points to.
+    Rect {
+        corner: [f64; 3],
+        edge_u: [f64; 3],
+        edge_v: [f64; 3],
+        color: [f64; 3],
+        #[serde(default = "default_one")]
+        intensity: f64,
+    },
+    Disk {
+        center: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        color: [f64; 3],
+        #[serde(default = "default_one")]
+        intensity: f64,
+    },
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        color: [f64; 3],
+        #[serde(default = "default_one")]
+        intensity: f64,
+    },
+}
+
+#[derive(Debug)]
+pub enum SceneError
+{
+    Io(PathBuf, std::io::Error),
+    Import(ImportError),
+    /// Malformed or invalid input, `line` and `column` are 1-based.
+    Syntax {
+        path: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A description put together in code, without a source to point into, that `parse`
+    /// would have rejected.
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            SceneError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            SceneError::Import(err) => write!(f, "{}", err),
+            SceneError::Invalid(message) => write!(f, "{}", message),
+            SceneError::Syntax { path, line, column, message } => {
+                if let Some(path) = path
+                {
+                    write!(f, "{}:", path.display())?;
+                }
+
+                write!(f, "{}:{}: {}", line, column, message)
+            },
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl SceneDesc
+{
+    /// The demo scene shipped with the renderer.
+    pub fn demo() -> SceneDesc
+    {
+        SceneDesc::parse(DEMO).expect("the demo scene is valid")
+    }
+
+    /// Loads and validates a scene file.
+    pub fn load(path: &Path) -> Result<SceneDesc, SceneError>
+    {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| SceneError::Io(path.to_path_buf(), err))?;
+
+        let mut desc = SceneDesc::parse(&source).map_err(|err| match err
+        {
+            SceneError::Syntax { line, column, message, .. } => SceneError::Syntax {
+                path: Some(path.to_path_buf()),
+                line,
+                column,
+                message,
+            },
+            err => err,
+        })?;
+
+        desc.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        Ok(desc)
+    }
+
+    /// Parses and validates a scene from TOML source.
+    pub fn parse(source: &str) -> Result<SceneDesc, SceneError>
+    {
+        // Check the version on its own first, so files from newer builds get a useful error
+        // instead of complaints about whatever changed in the format.
+        #[derive(Deserialize)]
+        struct Header
+        {
+            version: Option<Spanned<u32>>,
+        }
+
+        let header: Header = toml::from_str(source).map_err(|err| toml_error(source, err))?;
+
+        match header.version
+        {
+            None => return Err(syntax_error(source, 0, "missing field `version`".to_string())),
+            Some(version) if *version.get_ref() == 1 => {
+                return Err(syntax_error(source, version.span().start, "scene version 1 is no longer \
+                    supported, materials now have a `type` of diffuse, conductor or dielectric instead \
+                    of `reflectivity` and `opacity`".to_string()));
+            },
+            Some(version) if *version.get_ref() == 0 || *version.get_ref() > VERSION => {
+                return Err(syntax_error(source, version.span().start, format!(
+                    "unsupported scene version {}, this build supports versions up to {}",
+                    version.get_ref(), VERSION,
+                )));
+            },
+            Some(_) => (),
+        }
+
+        let desc: SceneDesc = toml::from_str(source).map_err(|err| toml_error(source, err))?;
+
+        check_render_desc(source, &desc.render)?;
+        check_camera_desc(source, &desc.camera)?;
+        check_sky_desc(source, &desc.sky)?;
+
+        for (name, material) in desc.materials.iter()
+        {
+            let path = [Key::Name("materials"), Key::Name(name)];
+
+            check_material_desc(source, &path, material)?;
+            desc.check_maps(source, &path, material.maps())?;
+        }
+
+        if let Some(atmosphere) = &desc.atmosphere
+        {
+            check_volume_desc(source, &[Key::Name("atmosphere")], &atmosphere.volume_desc())?;
+        }
+
+        for (i, object) in desc.objects.iter().enumerate()
+        {
+            let at = |key: &'static str| [Key::Name("objects"), Key::Index(i), Key::Name(key)];
+
+            match object
+            {
+                ObjectDesc::Sphere { radius, material, volume, .. } => {
+                    if *radius <= 0.0
+                    {
+                        return Err(error_at(source, &at("radius"), "a sphere's `radius` must be positive".to_string()));
+                    }
+
+                    desc.check_material(source, &at("material"), material)?;
+                    desc.check_volume(source, &at("volume"), material, volume)?;
+                },
+                ObjectDesc::Triangle { vertices, material, .. } => {
+                    if spans_no_area(vec3(vertices[1]) - vec3(vertices[0]), vec3(vertices[2]) - vec3(vertices[0]))
+                    {
+                        return Err(error_at(source, &at("vertices"), "a triangle's `vertices` must not lie on one line".to_string()));
+                    }
+
+                    desc.check_material(source, &at("material"), material)?;
+                },
+                ObjectDesc::Mesh { positions, normals, uvs, indices, material, volume } => {
+                    desc.check_material(source, &at("material"), material)?;
+                    desc.check_volume(source, &at("volume"), material, volume)?;
+
+                    let check_len = |name: &'static str, len: usize| {
+                        if len != 0 && len != positions.len()
+                        {
+                            Err(error_at(source, &at(name), format!(
+                                "mesh has {} {} but {} positions", len, name, positions.len(),
+                            )))
+                        }
+                        else
+                        {
+                            Ok(())
+                        }
+                    };
+
+                    check_len("normals", normals.len())?;
+                    check_len("uvs", uvs.len())?;
+
+                    if let Some((triangle, index)) = indices
+                        .iter()
+                        .enumerate()
+                        .find_map(|(t, triangle)| triangle.iter().find(|&&i| i >= positions.len()).map(|&i| (t, i)))
+                    {
+                        let path = [Key::Name("objects"), Key::Index(i), Key::Name("indices"), Key::Index(triangle)];
+
+                        return Err(error_at(source, &path, format!(
+                            "mesh index {} is out of range for {} positions", index, positions.len(),
+                        )));
+                    }
+
+                    if let Some(triangle) = indices.iter().position(|&[a, b, c]| {
+                        spans_no_area(vec3(positions[b]) - vec3(positions[a]), vec3(positions[c]) - vec3(positions[a]))
+                    })
+                    {
+                        let path = [Key::Name("objects"), Key::Index(i), Key::Name("indices"), Key::Index(triangle)];
+
+                        return Err(error_at(source, &path, format!("mesh triangle {} has no area", triangle)));
+                    }
+                },
+                ObjectDesc::Obj { material: Some(material), .. } => desc.check_material(source, &at("material"), material)?,
+                ObjectDesc::Obj { material: None, .. } => (),
+            }
+        }
+
+        for (i, light) in desc.lights.iter().enumerate()
+        {
+            check_light_desc(source, &[Key::Name("lights"), Key::Index(i)], light)?;
+        }
+
+        Ok(desc)
+    }
+
+    /// Serializes the scene back into TOML.
+    pub fn to_toml(&self) -> String
+    {
+        toml::to_string(self).expect("scene descriptions are always representable in TOML")
+    }
+
+    /// Creates the `Scene` described by this description, loading any referenced files.
+    ///
+    /// The camera is created with the default render size, `render::render` and the viewer
+    /// resize it to whatever they render at. Warnings about referenced files are dropped, see
+    /// `build_with_warnings`.
+    pub fn build(&self) -> Result<Scene, SceneError>
+    {
+        self.build_with_warnings().map(|(scene, _)| scene)
+    }
+
+    /// Like `build`, also returning the problems with referenced files that did not stop the
+    /// scene from building, like OBJ files whose MTL libraries are missing.
+    pub fn build_with_warnings(&self) -> Result<(Scene, Vec<String>), SceneError>
+    {
+        let defaults = RenderSettings::default();
+
+        let camera = Camera::look_at(
+            vec3(self.camera.position),
+            vec3(self.camera.target),
+            vec3(self.camera.up),
+            self.camera.fov,
+            self.render.width.unwrap_or(defaults.width),
+            self.render.height.unwrap_or(defaults.height),
+        ).with_lens(Lens {
+            aperture: self.camera.aperture,
+            focus_distance: self.camera.focus_distance.unwrap_or_else(|| {
+                (vec3(self.camera.target) - vec3(self.camera.position)).length()
+            }),
+            blades: self.camera.blades,
+            blade_rotation: self.camera.blade_rotation.to_radians(),
+            autofocus: self.camera.autofocus,
+        }).with_projection(match self.camera.projection
+        {
+            ProjectionDesc::Perspective => Projection::Perspective,
+            ProjectionDesc::Orthographic { height } => Projection::Orthographic { height },
+            ProjectionDesc::Fisheye { fov, mapping } => Projection::Fisheye {
+                fov: fov.to_radians(),
+                mapping: match mapping
+                {
+                    FisheyeMappingDesc::Equidistant => FisheyeMapping::Equidistant,
+                    FisheyeMappingDesc::Equisolid => FisheyeMapping::Equisolid,
+                },
+            },
+            ProjectionDesc::Equirectangular => Projection::Equirectangular,
+            ProjectionDesc::Cubemap => Projection::Cubemap,
+        });
+
+        let sky = match &self.sky
+        {
+            SkyDesc::Color { color } => Sky::Color(rgb(*color)),
+            SkyDesc::Environment { path, rotation, intensity } => {
+                let path = self.base_dir.join(path);
+
+                Sky::Environment(
+                    Environment::load(&path, *rotation, *intensity as f32)
+                        .map_err(|err| SceneError::Io(path, err))?
+                )
+            },
+            SkyDesc::Physical { sun_elevation, sun_azimuth, turbidity, intensity, sun_diameter, sun } => {
+                let sky = PhysicalSky::new(
+                    *sun_elevation,
+                    *sun_azimuth,
+                    *turbidity,
+                    *intensity as f32,
+                    *sun_diameter,
+                );
+
+                Sky::Physical(if *sun { sky.with_sun_light() } else { sky })
+            },
+        };
+
+        let textures = self.textures
+            .iter()
+            .map(|(name, desc)| Ok((name.clone(), self.texture(desc)?)))
+            .collect::<Result<BTreeMap<_, _>, SceneError>>()?;
+
+        let mut objects: Vec<Box<dyn Object>> = vec![];
+        let mut warnings = vec![];
+
+        for object in self.objects.iter()
+        {
+            let object: Box<dyn Object> = match object
+            {
+                ObjectDesc::Sphere { center, radius, material, volume } => Box::from(Sphere::new(
+                    vec3(*center),
+                    *radius,
+                    self.filled_material(material, volume, &textures)?,
+                )),
+                ObjectDesc::Triangle { vertices, normals, uvs, material } => {
+                    let mut triangle = Triangle::new(
+                        [vec3(vertices[0]), vec3(vertices[1]), vec3(vertices[2])],
+                        self.material(material, &textures)?,
+                    );
+
+                    if let Some(n) = normals
+                    {
+                        triangle = triangle.with_normals([vec3(n[0]), vec3(n[1]), vec3(n[2])]);
+                    }
+
+                    if let Some(uv) = uvs
+                    {
+                        triangle = triangle.with_uvs([
+                            (uv[0][0], uv[0][1]),
+                            (uv[1][0], uv[1][1]),
+                            (uv[2][0], uv[2][1]),
+                        ]);
+                    }
+
+                    Box::from(triangle)
+                },
+                ObjectDesc::Mesh { positions, normals, uvs, indices, material, volume } => {
+                    let buffers = VertexBuffers {
+                        positions: positions.iter().map(|&p| vec3(p)).collect(),
+                        normals: normals.iter().map(|&n| vec3(n).normalized()).collect(),
+                        uvs: uvs.iter().map(|uv| (uv[0], uv[1])).collect(),
+                    };
+
+                    let triangles = indices
+                        .iter()
+                        .map(|&indices| MeshTriangle::indexed(indices, &buffers))
+                        .collect();
+
+                    let material = self.filled_material(material, volume, &textures)?;
+
+                    Box::from(Mesh::new(Arc::new(buffers), triangles, material))
+                },
+                ObjectDesc::Obj { path, material } => {
+                    let obj = import::obj::load(&self.base_dir.join(path)).map_err(SceneError::Import)?;
+
+                    warnings.extend(obj.warnings.iter().cloned());
+
+                    match material
+                    {
+                        Some(material) => Box::from(obj.mesh_with_material(self.material(material, &textures)?)),
+                        None => {
+                            for mesh in obj.meshes(import::obj::default_material())
+                            {
+                                objects.push(Box::from(mesh));
+                            }
+
+                            continue;
+                        },
+                    }
+                },
+            };
+
+            objects.push(object);
+        }
+
+        let mut lights: Vec<Light> = self.lights
+            .iter()
+            .map(|light| match light
+            {
+                LightDesc::Hemi { direction, color } => Light::Hemi(light::Hemi::new(
+                    vec3(*direction),
+                    rgb(*color),
+                )),
+                LightDesc::Point { position, color, intensity } => Light::Point(light::Point::new(
+                    vec3(*position),
+                    rgb(*color),
+                    *intensity as f32,
+                )),
+                LightDesc::Sun { direction, color, angular_diameter } => Light::Sun(light::Sun::new(
+                    vec3(*direction),
+                    rgb(*color),
+                    *angular_diameter,
+                )),
+                LightDesc::Rect { corner, edge_u, edge_v, color, intensity } => Light::Rect(light::Rect::new(
+                    vec3(*corner),
+                    vec3(*edge_u),
+                    vec3(*edge_v),
+                    rgb(*color) * *intensity as f32,
+                )),
+                LightDesc::Disk { center, normal, radius, color, intensity } => Light::Disk(light::Disk::new(
+                    vec3(*center),
+                    vec3(*normal),
+                    *radius,
+                    rgb(*color) * *intensity as f32,
+                )),
+                LightDesc::Sphere { center, radius, color, intensity } => Light::Sphere(light::Sphere::new(
+                    vec3(*center),
+                    *radius,
+                    rgb(*color) * *intensity as f32,
+                )),
+            })
+            .collect();
+
+        // The physical sky brings its own sun, so the two always agree.
+        if let Sky::Physical(physical) = &sky
+        {
+            if physical.sun_is_light()
+            {
+                lights.push(Light::Sun(physical.sun()));
+            }
+        }
+
+        let scene = Scene::new(sky, camera, objects, lights);
+
+        let scene = match &self.atmosphere
+        {
+            Some(atmosphere) => scene.with_atmosphere(Atmosphere {
+                volume: atmosphere.volume_desc().volume(),
+                height: atmosphere.height,
+            }),
+            None => scene,
+        };
+
+        Ok((scene, warnings))
+    }
+
+    /// Checks an object's material, `path` leads to it in the source.
+    fn check_material(&self, source: &str, path: &[Key], material: &MaterialRef) -> Result<(), SceneError>
+    {
+        match material
+        {
+            MaterialRef::Named(name) if !self.materials.contains_key(name) => {
+                Err(error_at(source, path, format!("unknown material '{}'", name)))
+            },
+            MaterialRef::Named(_) => Ok(()),
+            MaterialRef::Inline(desc) => {
+                check_material_desc(source, path, desc)?;
+                self.check_maps(source, path, desc.maps())
+            },
+        }
+    }
+
+    /// Checks an object's `volume`, which only materials light passes through let be seen.
+    /// `path` leads to it in the source.
+    fn check_volume(
+        &self,
+        source: &str,
+        path: &[Key],
+        material: &MaterialRef,
+        volume: &Option<VolumeDesc>,
+    ) -> Result<(), SceneError> {
+        let volume = match volume
+        {
+            Some(volume) => volume,
+            None => return Ok(()),
+        };
+
+        check_volume_desc(source, path, volume)?;
+
+        let desc = match material
+        {
+            MaterialRef::Named(name) => &self.materials[name],
+            MaterialRef::Inline(desc) => desc,
+        };
+
+        if !desc.is_transmissive()
+        {
+            return Err(error_at(source, path, "a `volume` needs a material light passes through, a \
+                dielectric, a principled material with transmission or an interface".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the maps of the material `path` leads to.
+    fn check_maps(&self, source: &str, path: &[Key], maps: &MapsDesc) -> Result<(), SceneError>
+    {
+        let bump = maps.bump.as_ref().map(|bump| ("bump", &bump.texture));
+        let maps = [
+            ("color", &maps.color),
+            ("roughness", &maps.roughness),
+            ("opacity", &maps.opacity),
+            ("emission", &maps.emission),
+            ("normal", &maps.normal),
+        ];
+
+        let textures = maps.iter().filter_map(|&(key, texture)| texture.as_ref().map(|texture| (key, texture)));
+
+        for (key, texture) in textures.chain(bump)
+        {
+            if let TextureRef::Named(name) = texture
+            {
+                if !self.textures.contains_key(name)
+                {
+                    let path = [path, &[Key::Name("maps"), Key::Name(key)]].concat();
+
+                    return Err(error_at(source, &path, format!("unknown texture '{}'", name)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn texture(&self, desc: &TextureDesc) -> Result<Texture, SceneError>
+    {
+        let mapping = |space: &Option<TextureSpace>, scale: &f64| {
+            Mapping::new(space.unwrap_or(TextureSpace::Uv), *scale)
+        };
+
+        let texture = match desc
+        {
+            TextureDesc::Image { path, wrap, raw, scale } => {
+                let path = self.base_dir.join(path);
+                let image = ImageTexture::load(&path, *raw, wrap.unwrap_or(Wrap::Repeat))
+                    .map_err(|err| SceneError::Io(path, err))?;
+
+                Texture::Image(image.with_scale(*scale))
+            },
+            TextureDesc::Checker { even, odd, space, scale } => {
+                Texture::Checker(Checker::new(rgb(*even), rgb(*odd), mapping(space, scale)))
+            },
+            TextureDesc::Gradient { from, to, axis, space, scale } => {
+                Texture::Gradient(Gradient::new(rgb(*from), rgb(*to), vec3(*axis), mapping(space, scale)))
+            },
+            TextureDesc::Noise { low, high, octaves, gain, space, scale } => {
+                Texture::Noise(Noise::new(rgb(*low), rgb(*high), *octaves, *gain, mapping(space, scale)))
+            },
+            TextureDesc::Voronoi { low, high, mode, space, scale } => Texture::Voronoi(Voronoi::new(
+                rgb(*low),
+                rgb(*high),
+                mode.unwrap_or(VoronoiMode::Distance),
+                mapping(space, scale),
+            )),
+        };
+
+        Ok(texture)
+    }
+
+    /// Named textures are loaded once up front and passed in as `textures`, inline ones are
+    /// loaded for every material using them. Named materials keep their name.
+    fn material(&self, material: &MaterialRef, textures: &BTreeMap<String, Texture>) -> Result<Material, SceneError>
+    {
+        match material
+        {
+            MaterialRef::Named(name) => {
+                let desc = self.materials
+                    .get(name)
+                    .ok_or_else(|| SceneError::Invalid(format!("unknown material '{}'", name)))?;
+
+                Ok(self.material_from(desc, textures)?.with_name(name))
+            },
+            MaterialRef::Inline(desc) => self.material_from(desc, textures),
+        }
+    }
+
+    fn material_from(&self, desc: &MaterialDesc, textures: &BTreeMap<String, Texture>) -> Result<Material, SceneError>
+    {
+        let (material, emission) = match desc
+        {
+            MaterialDesc::Diffuse { color, emission, .. } => (Material::diffuse(rgb(*color)), emission),
+            MaterialDesc::Conductor { metal, eta, k, color, roughness, emission, .. } => {
+                let roughness = *roughness as f32;
+
+                let material = match (metal, eta, k, color)
+                {
+                    (Some(metal), ..) => Material::metal(*metal, roughness),
+                    (_, Some(eta), Some(k), _) => {
+                        Material::new(Bsdf::Conductor(Conductor::new(rgb(*eta), rgb(*k), roughness)))
+                    },
+                    (.., Some(color)) => Material::conductor(rgb(*color), roughness),
+                    _ => return Err(SceneError::Invalid(
+                        "a conductor needs exactly one of `metal`, `eta` and `k`, or `color`".to_string(),
+                    )),
+                };
+
+                (material, emission)
+            },
+            MaterialDesc::Dielectric { r_index, roughness, color, emission, .. } => {
+                let dielectric = Dielectric {
+                    color: rgb(*color),
+                    ..Dielectric::new(*r_index as f32, *roughness as f32)
+                };
+
+                (Material::new(Bsdf::Dielectric(dielectric)), emission)
+            },
+            MaterialDesc::Interface {} => (Material::new(Bsdf::Interface), &[0.0; 3]),
+            // glTF materials bring their own emission and have no maps.
+            MaterialDesc::Gltf { path, name } => return self.gltf_material(path, name),
+            MaterialDesc::Principled(desc) => {
+                let principled = Principled {
+                    base_color: rgb(desc.base_color),
+                    metallic: desc.metallic as f32,
+                    roughness: desc.roughness as f32,
+                    specular: desc.specular as f32,
+                    specular_tint: desc.specular_tint as f32,
+                    sheen: desc.sheen as f32,
+                    sheen_tint: desc.sheen_tint as f32,
+                    clearcoat: desc.clearcoat as f32,
+                    clearcoat_roughness: desc.clearcoat_roughness as f32,
+                    transmission: desc.transmission as f32,
+                    r_index: desc.r_index as f32,
+                };
+
+                (Material::principled(principled), &desc.emission)
+            },
+        };
+
+        let mut material = material.with_emission(rgb(*emission));
+        let maps = desc.maps();
+
+        let texture = |texture: &TextureRef| match texture
+        {
+            TextureRef::Named(name) => textures
+                .get(name)
+                .cloned()
+                .ok_or_else(|| SceneError::Invalid(format!("unknown texture '{}'", name))),
+            TextureRef::Inline(desc) => self.texture(desc),
+        };
+
+        material.maps = TextureMaps {
+            color: maps.color.as_ref().map(texture).transpose()?,
+            roughness: maps.roughness.as_ref().map(texture).transpose()?,
+            opacity: maps.opacity.as_ref().map(texture).transpose()?,
+            emission: maps.emission.as_ref().map(texture).transpose()?,
+            normal: maps.normal.as_ref().map(texture).transpose()?,
+            bump: match &maps.bump
+            {
+                Some(bump) => Some(BumpMap {
+                    texture: texture(&bump.texture)?,
+                    height: bump.height,
+                }),
+                None => None,
+            },
+        };
+
+        Ok(material)
+    }
+
+    /// Loads the glTF material named `name`, the file is read again for every use.
+    fn gltf_material(&self, path: &Path, name: &str) -> Result<Material, SceneError>
+    {
+        let path = self.base_dir.join(path);
+
+        import::gltf::load(&path)
+            .map_err(SceneError::Import)?
+            .into_iter()
+            .find(|material| material.name == name)
+            .map(|material| material.to_material())
+            .ok_or_else(|| SceneError::Import(ImportError::Io(path, io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no material named '{}'", name),
+            ))))
+    }
+
+    /// The material of an object filled with `volume`.
+    fn filled_material(
+        &self,
+        material: &MaterialRef,
+        volume: &Option<VolumeDesc>,
+        textures: &BTreeMap<String, Texture>,
+    ) -> Result<Material, SceneError> {
+        let material = self.material(material, textures)?;
+
+        Ok(match volume
+        {
+            Some(volume) => material.with_volume(volume.volume()),
+            None => material,
+        })
+    }
+}
+
+impl MaterialDesc
+{
+    pub fn maps(&self) -> &MapsDesc
+    {
+        match self
+        {
+            MaterialDesc::Diffuse { maps, .. } => maps,
+            MaterialDesc::Conductor { maps, .. } => maps,
+            MaterialDesc::Dielectric { maps, .. } => maps,
+            MaterialDesc::Principled(desc) => &desc.maps,
+            MaterialDesc::Interface {} | MaterialDesc::Gltf { .. } => &NO_MAPS,
+        }
+    }
+
+    /// Whether light passes through the material, so the volume filling an object made of it
+    /// can be seen. glTF materials are only known once loaded and count as transmissive.
+    pub fn is_transmissive(&self) -> bool
+    {
+        match self
+        {
+            MaterialDesc::Dielectric { .. } | MaterialDesc::Interface {} | MaterialDesc::Gltf { .. } => true,
+            MaterialDesc::Principled(desc) => desc.transmission > 0.0,
+            _ => false,
+        }
+    }
+}
+
+/// The maps of materials that have none.
+static NO_MAPS: MapsDesc = MapsDesc {
+    color: None,
+    roughness: None,
+    opacity: None,
+    emission: None,
+    normal: None,
+    bump: None,
+};
+
+impl AtmosphereDesc
+{
+    pub fn volume_desc(&self) -> VolumeDesc
+    {
+        VolumeDesc {
+            absorption: self.absorption,
+            scattering: self.scattering,
+            anisotropy: self.anisotropy,
+        }
+    }
+}
+
+impl VolumeDesc
+{
+    pub fn volume(&self) -> Volume
+    {
+        Volume {
+            absorption: rgb(self.absorption),
+            scattering: rgb(self.scattering),
+            anisotropy: self.anisotropy as f32,
+        }
+    }
+}
+
+impl MapsDesc
+{
+    pub fn is_empty(&self) -> bool
+    {
+        self.color.is_none()
+            && self.roughness.is_none()
+            && self.opacity.is_none()
+            && self.emission.is_none()
+            && self.normal.is_none()
+            && self.bump.is_none()
+    }
+}
+
+impl ProjectionDesc
+{
+    pub fn is_perspective(&self) -> bool
+    {
+        matches!(self, ProjectionDesc::Perspective)
+    }
+}
+
+impl RenderDesc
+{
+    pub fn is_empty(&self) -> bool
+    {
+        self.width.is_none()
+            && self.height.is_none()
+            && self.samples.is_none()
+            && self.bounces.is_none()
+            && self.seed.is_none()
+            && self.threads.is_none()
+            && self.sampler.is_none()
+            && self.filter.is_none()
+            && self.filter_radius.is_none()
+            && self.integrator.is_none()
+            && self.ao_distance.is_none()
+            && self.photons.is_none()
+            && self.photon_radius.is_none()
+            && self.photon_alpha.is_none()
+    }
+
+    /// Overwrites the settings that are given in this description.
+    pub fn apply(&self, settings: &mut RenderSettings)
+    {
+        settings.width = self.width.unwrap_or(settings.width);
+        settings.height = self.height.unwrap_or(settings.height);
+        settings.samples = self.samples.unwrap_or(settings.samples);
+        settings.max_bounces = self.bounces.unwrap_or(settings.max_bounces);
+        settings.seed = self.seed.or(settings.seed);
+        settings.threads = self.threads.unwrap_or(settings.threads);
+        settings.sampler = self.sampler.unwrap_or(settings.sampler);
+        settings.filter = self.filter.unwrap_or(settings.filter);
+
+        if let Some(radius) = self.filter_radius
+        {
+            settings.filter = settings.filter.with_radius(radius);
+        }
+
+        settings.integrator = self.integrator.unwrap_or(settings.integrator);
+
+        if let Some(distance) = self.ao_distance
+        {
+            settings.integrator = settings.integrator.with_ao_distance(distance);
+        }
+
+        if let Some(photons) = self.photons
+        {
+            settings.integrator = settings.integrator.with_photons(photons);
+        }
+
+        if let Some(radius) = self.photon_radius
+        {
+            settings.integrator = settings.integrator.with_photon_radius(radius);
+        }
+
+        if let Some(alpha) = self.photon_alpha
+        {
+            settings.integrator = settings.integrator.with_photon_alpha(alpha);
+        }
+    }
+}
+
+/// (De)serializes optional settings by their name, through `Display` and `FromStr`.
+mod by_name
+{
+    use serde::{ Serializer, Deserializer, Deserialize, de::Error };
+
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where T: Display, S: Serializer
+    {
+        match value
+        {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where T: FromStr<Err = String>, D: Deserializer<'de>
+    {
+        match Option::<String>::deserialize(deserializer)?
+        {
+            Some(name) => name.parse().map(Some).map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Checks what the format itself cannot express, `path` leads to the material in the source.
+fn check_material_desc(source: &str, path: &[Key], desc: &MaterialDesc) -> Result<(), SceneError>
+{
+    let error = |key: Option<&'static str>, message: &str| {
+        let path = [path, key.map(Key::Name).as_slice()].concat();
+
+        Err(error_at(source, &path, message.to_string()))
+    };
+
+    if let MaterialDesc::Conductor { metal, eta, k, color, .. } = desc
+    {
+        let given = metal.is_some() as u32 + (eta.is_some() || k.is_some()) as u32 + color.is_some() as u32;
+
+        if given != 1
+        {
+            return error(None, "a conductor needs exactly one of `metal`, `eta` and `k`, or `color`");
+        }
+
+        if eta.is_some() != k.is_some()
+        {
+            return error(None, "a conductor's `eta` and `k` must be given together");
+        }
+    }
+
+    let colors = match desc
+    {
+        MaterialDesc::Diffuse { color, emission, .. } | MaterialDesc::Dielectric { color, emission, .. } => {
+            [("color", Some(color)), ("emission", Some(emission))]
+        },
+        MaterialDesc::Conductor { color, emission, .. } => [("color", color.as_ref()), ("emission", Some(emission))],
+        MaterialDesc::Principled(desc) => [("base_color", Some(&desc.base_color)), ("emission", Some(&desc.emission))],
+        MaterialDesc::Interface {} | MaterialDesc::Gltf { .. } => [("color", None), ("emission", None)],
+    };
+
+    if let Some(&(key, _)) = colors.iter().find(|(_, color)| color.is_some_and(is_negative))
+    {
+        return error(Some(key), &format!("a material's `{}` must not be negative", key));
+    }
+
+    if let MaterialDesc::Conductor { roughness, .. } | MaterialDesc::Dielectric { roughness, .. } = desc
+    {
+        if !(0.0..=1.0).contains(roughness)
+        {
+            return error(Some("roughness"), "a material's `roughness` must lie between 0 and 1");
+        }
+    }
+
+    if let MaterialDesc::Dielectric { r_index, .. } = desc
+    {
+        if *r_index <= 0.0
+        {
+            return error(Some("r_index"), "a dielectric's `r_index` must be positive");
+        }
+    }
+
+    if let MaterialDesc::Principled(desc) = desc
+    {
+        let factors = [
+            ("metallic", desc.metallic),
+            ("roughness", desc.roughness),
+            ("specular", desc.specular),
+            ("specular_tint", desc.specular_tint),
+            ("sheen", desc.sheen),
+            ("sheen_tint", desc.sheen_tint),
+            ("clearcoat", desc.clearcoat),
+            ("clearcoat_roughness", desc.clearcoat_roughness),
+            ("transmission", desc.transmission),
+        ];
+
+        if let Some(&(key, _)) = factors.iter().find(|&&(_, factor)| !(0.0..=1.0).contains(&factor))
+        {
+            return error(Some(key), "principled material parameters must lie between 0 and 1");
+        }
+
+        if desc.r_index <= 0.0
+        {
+            return error(Some("r_index"), "a principled material's `r_index` must be positive");
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the angles and sizes of the camera and its projection.
+fn check_camera_desc(source: &str, desc: &CameraDesc) -> Result<(), SceneError>
+{
+    let error = |path: &[Key], message: &str| Err(error_at(source, &[&[Key::Name("camera")], path].concat(), message.to_string()));
+
+    if !(desc.fov > 0.0 && desc.fov < 180.0)
+    {
+        return error(&[Key::Name("fov")], "the camera's `fov` must lie between 0 and 180 degrees");
+    }
+
+    if desc.aperture < 0.0
+    {
+        return error(&[Key::Name("aperture")], "the camera's `aperture` must not be negative");
+    }
+
+    if desc.focus_distance.is_some_and(|distance| distance <= 0.0)
+    {
+        return error(&[Key::Name("focus_distance")], "the camera's `focus_distance` must be positive");
+    }
+
+    match desc.projection
+    {
+        ProjectionDesc::Orthographic { height } if height <= 0.0 => {
+            error(&[Key::Name("projection"), Key::Name("height")], "an orthographic camera's `height` must be positive")
+        },
+        ProjectionDesc::Fisheye { fov, .. } if !(fov > 0.0 && fov <= 360.0) => {
+            error(&[Key::Name("projection"), Key::Name("fov")], "a fisheye camera's `fov` must lie above 0 and at most 360 degrees")
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks what the format itself cannot express about the sky.
+fn check_sky_desc(source: &str, desc: &SkyDesc) -> Result<(), SceneError>
+{
+    let error = |key: &'static str, message: &str| Err(error_at(source, &[Key::Name("sky"), Key::Name(key)], message.to_string()));
+
+    match desc
+    {
+        SkyDesc::Color { color } if is_negative(color) => error("color", "the sky's `color` must not be negative"),
+        SkyDesc::Color { .. } => Ok(()),
+        SkyDesc::Environment { intensity, .. } | SkyDesc::Physical { intensity, .. } if *intensity < 0.0 => {
+            error("intensity", "the sky's `intensity` must not be negative")
+        },
+        SkyDesc::Physical { sun_diameter, .. } if !(*sun_diameter > 0.0 && *sun_diameter < 180.0) => {
+            error("sun_diameter", "the sky's `sun_diameter` must lie between 0 and 180 degrees")
+        },
+        SkyDesc::Physical { turbidity, .. } if *turbidity <= 0.0 => error("turbidity", "the sky's `turbidity` must be positive"),
+        _ => Ok(()),
+    }
+}
+
+/// Checks the colors, sizes and orientation of a light, `path` leads to it in the source.
+fn check_light_desc(source: &str, path: &[Key], desc: &LightDesc) -> Result<(), SceneError>
+{
+    let error = |key: &'static str, message: &str| Err(error_at(source, &[path, &[Key::Name(key)]].concat(), message.to_string()));
+
+    let color = match desc
+    {
+        LightDesc::Hemi { color, .. }
+        | LightDesc::Point { color, .. }
+        | LightDesc::Sun { color, .. }
+        | LightDesc::Rect { color, .. }
+        | LightDesc::Disk { color, .. }
+        | LightDesc::Sphere { color, .. } => color,
+    };
+
+    if is_negative(color)
+    {
+        return error("color", "a light's `color` must not be negative");
+    }
+
+    match desc
+    {
+        LightDesc::Hemi { direction, .. } | LightDesc::Sun { direction, .. } if vec3(*direction).length() == 0.0 => {
+            error("direction", "a light's `direction` must not be zero")
+        },
+        LightDesc::Disk { normal, .. } if vec3(*normal).length() == 0.0 => error("normal", "a disk's `normal` must not be zero"),
+        LightDesc::Rect { edge_u, edge_v, .. } if spans_no_area(vec3(*edge_u), vec3(*edge_v)) => {
+            error("edge_v", "a rect's `edge_u` and `edge_v` must be neither zero nor parallel")
+        },
+        LightDesc::Disk { radius, .. } | LightDesc::Sphere { radius, .. } if *radius <= 0.0 => {
+            error("radius", "a light's `radius` must be positive")
+        },
+        LightDesc::Sun { angular_diameter, .. } if !(*angular_diameter >= 0.0 && *angular_diameter < 180.0) => {
+            error("angular_diameter", "a sun's `angular_diameter` must lie between 0 and 180 degrees")
+        },
+        LightDesc::Point { intensity, .. }
+        | LightDesc::Rect { intensity, .. }
+        | LightDesc::Disk { intensity, .. }
+        | LightDesc::Sphere { intensity, .. } if *intensity < 0.0 => {
+            error("intensity", "a light's `intensity` must not be negative")
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks the render settings against the same bounds the command line enforces.
+fn check_render_desc(source: &str, desc: &RenderDesc) -> Result<(), SceneError>
+{
+    let error = |key: &'static str, message: &str| {
+        Err(error_at(source, &[Key::Name("render"), Key::Name(key)], format!("`{}` {}", key, message)))
+    };
+
+    let counts = [
+        ("width", desc.width.map(|width| width as u64), 2),
+        ("height", desc.height.map(|height| height as u64), 2),
+        ("samples", desc.samples.map(u64::from), 1),
+        ("bounces", desc.bounces.map(u64::from), 1),
+        ("threads", desc.threads.map(|threads| threads as u64), 1),
+        ("photons", desc.photons.map(u64::from), 1),
+    ];
+
+    for &(key, count, min) in counts.iter()
+    {
+        if count.is_some_and(|count| count < min)
+        {
+            return error(key, &format!("must be at least {}", min));
+        }
+    }
+
+    let distances = [
+        ("filter_radius", desc.filter_radius),
+        ("ao_distance", desc.ao_distance),
+        ("photon_radius", desc.photon_radius),
+    ];
+
+    for &(key, distance) in distances.iter()
+    {
+        if distance.is_some_and(|distance| !(distance > 0.0 && distance.is_finite()))
+        {
+            return error(key, "must be a positive number");
+        }
+    }
+
+    if desc.photon_alpha.is_some_and(|alpha| !(alpha > 0.0 && alpha <= 1.0))
+    {
+        return error("photon_alpha", "must lie above 0 and at most 1");
+    }
+
+    Ok(())
+}
+
+/// Checks the coefficients of a volume, `path` leads to it in the source.
+fn check_volume_desc(source: &str, path: &[Key], desc: &VolumeDesc) -> Result<(), SceneError>
+{
+    let error = |key: &'static str, message: &str| {
+        Err(error_at(source, &[path, &[Key::Name(key)]].concat(), message.to_string()))
+    };
+
+    if desc.absorption.iter().any(|&c| c < 0.0)
+    {
+        return error("absorption", "a volume's `absorption` and `scattering` must not be negative");
+    }
+
+    if desc.scattering.iter().any(|&c| c < 0.0)
+    {
+        return error("scattering", "a volume's `absorption` and `scattering` must not be negative");
+    }
+
+    if desc.anisotropy <= -1.0 || desc.anisotropy >= 1.0
+    {
+        return error("anisotropy", "a volume's `anisotropy` must lie between -1 and 1");
+    }
+
+    Ok(())
+}
+
+/// A step on the way to a value in the scene source, a table key or an array index.
+#[derive(Debug, Clone, Copy)]
+enum Key<'a>
+{
+    Name(&'a str),
+    Index(usize),
+}
+
+/// An error pointing at the value `path` leads to, or the closest enclosing one if it is not
+/// there. Serde loses the spans of values inside tagged tables, so they are looked up in the
+/// parsed document instead.
+fn error_at(source: &str, path: &[Key], message: String) -> SceneError
+{
+    let mut offset = 0;
+
+    if let Ok(document) = ImDocument::parse(source)
+    {
+        let mut item = Some(document.as_item());
+
+        for key in path.iter()
+        {
+            item = item.and_then(|item| match *key
+            {
+                Key::Name(name) => item.get(name),
+                Key::Index(index) => item.get(index),
+            });
+
+            match item.and_then(Item::span)
+            {
+                Some(span) => offset = span.start,
+                None if item.is_none() => break,
+                None => (),
+            }
+        }
+    }
+
+    syntax_error(source, offset, message)
+}
+
+fn toml_error(source: &str, err: toml::de::Error) -> SceneError
+{
+    let offset = err.span().map(|span| span.start).unwrap_or(0);
+
+    syntax_error(source, offset, err.message().to_string())
+}
+
+fn syntax_error(source: &str, offset: usize, message: String) -> SceneError
+{
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+
+    SceneError::Syntax {
+        path: None,
+        line,
+        column,
+        message,
+    }
+}
+
+fn vec3(v: [f64; 3]) -> Vec3
+{
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn rgb(c: [f64; 3]) -> RGB
+{
+    RGB::new(c[0] as f32, c[1] as f32, c[2] as f32)
+}
+
+fn is_negative(c: &[f64; 3]) -> bool
+{
+    c.iter().any(|&c| c < 0.0)
+}
+
+/// Whether `u` and `v` span no area, being parallel or either of them zero.
+fn spans_no_area(u: Vec3, v: Vec3) -> bool
+{
+    u.cross(v).length() <= 1e-9 * u.length() * v.length()
+}
+
+fn is_black(c: &[f64; 3]) -> bool
+{
+    c.iter().all(|&c| c == 0.0)
+}
+
+fn is_zero(value: &f64) -> bool
+{
+    *value == 0.0
+}
+
+fn is_zero_u32(value: &u32) -> bool
+{
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool
+{
+    !*value
+}
+
+fn default_up() -> [f64; 3]
+{
+    [0.0, 1.0, 0.0]
+}
+
+fn default_fov() -> f64
+{
+    60.0
+}
+
+fn default_fisheye_fov() -> f64
+{
+    180.0
+}
+
+fn is_white(c: &[f64; 3]) -> bool
+{
+    c.iter().all(|&c| c == 1.0)
+}
+
+fn default_white() -> [f64; 3]
+{
+    [1.0, 1.0, 1.0]
+}
+
+fn default_r_index() -> f64
+{
+    1.5
+}
+
+fn default_turbidity() -> f64
+{
+    3.0
+}
+
+fn default_true() -> bool
+{
+    true
+}
+
+fn default_sun_diameter() -> f64
+{
+    0.53
+}
+
+fn default_gradient_axis() -> [f64; 3]
+{
+    [1.0, 0.0, 0.0]
+}
+
+fn default_octaves() -> u32
+{
+    4
+}
+
+fn default_gain() -> f64
+{
+    0.5
+}
+
+fn default_one() -> f64
+{
+    1.0
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const HEADER: &str = "version = 2
+
+[camera]
+position = [0.0, 0.0, -5.0]
+target = [0.0, 0.0, 0.0]
+
+[sky]
+type = \"color\"
+color = [0.5, 0.5, 0.5]
+";
+
+    /// The line, column and message of the error parsing `HEADER` followed by `rest` gives.
+    fn error(rest: &str) -> (usize, usize, String)
+    {
+        match SceneDesc::parse(&format!("{}{}", HEADER, rest))
+        {
+            Err(SceneError::Syntax { line, column, message, .. }) => (line, column, message),
+            Err(err) => panic!("expected a syntax error, got {}", err),
+            Ok(_) => panic!("expected a syntax error"),
+        }
+    }
+
+    /// The line of `HEADER` followed by `rest` the first line of `rest` is on.
+    fn first_line() -> usize
+    {
+        HEADER.lines().count() + 1
+    }
+
+    #[test]
+    fn mesh_errors_point_at_their_own_mesh()
+    {
+        let mesh = "
+[[objects]]
+type = \"mesh\"
+positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+normals = [[0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0]]
+indices = [[0, 1, 2]]
+material = { type = \"diffuse\" }
+";
+        let bad_indices = mesh.replace("[[0, 1, 2]]", "[[0, 1, 2], [0, 2, 3]]");
+        let (line, column, message) = error(&format!("{}{}", mesh, bad_indices));
+
+        assert_eq!(line, first_line() + 12);
+        assert_eq!(column, 23);
+        assert!(message.contains("index 3"), "{}", message);
+
+        let bad_normals = mesh.replace("normals = [[0.0, 0.0, 1.0], ", "normals = [");
+        let (line, column, _) = error(&format!("{}{}", mesh, bad_normals));
+
+        assert_eq!(line, first_line() + 11);
+        assert_eq!(column, 11);
+    }
+
+    #[test]
+    fn unknown_names_point_at_their_use()
+    {
+        let (line, column, message) = error("
+[textures.wood]
+type = \"checker\"
+even = [0.0, 0.0, 0.0]
+odd = [1.0, 1.0, 1.0]
+
+[materials.wooden]
+type = \"diffuse\"
+maps = { color = \"wood\" }
+
+[materials.stone]
+type = \"diffuse\"
+maps = { color = \"stone\" }
+
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"wooden\"
+
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"wood\"
+");
+
+        assert_eq!((line, column), (first_line() + 12, 18));
+        assert_eq!(message, "unknown texture 'stone'");
+
+        let (line, column, message) = error("
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"wood\"
+");
+
+        assert_eq!((line, column), (first_line() + 5, 12));
+        assert_eq!(message, "unknown material 'wood'");
+    }
+
+    #[test]
+    fn material_errors_point_at_the_key()
+    {
+        let (line, column, _) = error("
+[materials.shiny]
+type = \"principled\"
+roughness = 0.5
+clearcoat = 2.0
+");
+
+        assert_eq!((line, column), (first_line() + 4, 13));
+
+        let (line, _, message) = error("
+[materials.metal]
+type = \"conductor\"
+color = [1.0, 1.0, 1.0]
+metal = \"gold\"
+");
+
+        assert_eq!(line, first_line() + 1);
+        assert!(message.contains("exactly one"), "{}", message);
+    }
+
+    #[test]
+    fn every_material_and_primitive_is_checked()
+    {
+        // Each snippet has its invalid value on its last line, after `key = `.
+        let cases = [
+            "[materials.glass]\ntype = \"dielectric\"\nr_index = 0.0",
+            "[materials.frosted]\ntype = \"dielectric\"\nroughness = -0.5",
+            "[materials.brushed]\ntype = \"conductor\"\nmetal = \"gold\"\nroughness = 1.5",
+            "[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nmaterial = { type = \"diffuse\" }\nradius = -1.0",
+            "[[lights]]\ntype = \"disk\"\ncenter = [0.0, 1.0, 0.0]\nnormal = [0.0, -1.0, 0.0]\ncolor = [1.0, 1.0, 1.0]\nradius = 0.0",
+            "[[lights]]\ntype = \"sun\"\ndirection = [0.0, -1.0, 0.0]\ncolor = [1.0, 1.0, 1.0]\nangular_diameter = 180.0",
+            "[camera.projection]\ntype = \"fisheye\"\nfov = 400.0",
+            "[camera.projection]\ntype = \"orthographic\"\nheight = 0.0",
+        ];
+
+        for case in cases.iter()
+        {
+            let (line, column, message) = error(&format!("\n{}\n", case));
+            let last = case.lines().last().unwrap();
+            let key = &last[..last.find(' ').unwrap()];
+
+            assert_eq!((line, column), (first_line() + case.lines().count(), key.len() + 4), "{}", case);
+            assert!(message.contains(key), "{}", message);
+        }
+    }
+
+    #[test]
+    fn degenerate_shapes_and_negative_colors_are_rejected()
+    {
+        // Each snippet has its invalid value on its last line, after `key = `.
+        let cases = [
+            "[[lights]]\ntype = \"sun\"\ncolor = [1.0, 1.0, 1.0]\ndirection = [0.0, 0.0, 0.0]",
+            "[[lights]]\ntype = \"hemi\"\ncolor = [1.0, 1.0, 1.0]\ndirection = [0.0, 0.0, 0.0]",
+            "[[lights]]\ntype = \"disk\"\ncenter = [0.0, 1.0, 0.0]\nradius = 1.0\ncolor = [1.0, 1.0, 1.0]\nnormal = [0.0, 0.0, 0.0]",
+            "[[lights]]\ntype = \"rect\"\ncorner = [0.0, 1.0, 0.0]\ncolor = [1.0, 1.0, 1.0]\nedge_u = [1.0, 0.0, 0.0]\nedge_v = [-2.0, 0.0, 0.0]",
+            "[[lights]]\ntype = \"rect\"\ncorner = [0.0, 1.0, 0.0]\ncolor = [1.0, 1.0, 1.0]\nedge_u = [1.0, 0.0, 0.0]\nedge_v = [0.0, 0.0, 0.0]",
+            "[[lights]]\ntype = \"point\"\nposition = [0.0, 1.0, 0.0]\ncolor = [1.0, -1.0, 1.0]",
+            "[[objects]]\ntype = \"triangle\"\nmaterial = { type = \"diffuse\" }\nvertices = [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [2.0, 2.0, 2.0]]",
+            "[materials.red]\ntype = \"diffuse\"\ncolor = [-1.0, 0.0, 0.0]",
+            "[materials.lamp]\ntype = \"principled\"\nemission = [0.0, -1.0, 0.0]",
+        ];
+
+        for case in cases.iter()
+        {
+            let (line, column, message) = error(&format!("\n{}\n", case));
+            let last = case.lines().last().unwrap();
+            let key = &last[..last.find(' ').unwrap()];
+
+            assert_eq!((line, column), (first_line() + case.lines().count(), key.len() + 4), "{}", case);
+            assert!(message.contains(key), "{}", message);
+        }
+
+        let (line, column, message) = error("
+[[objects]]
+type = \"mesh\"
+positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+material = { type = \"diffuse\" }
+indices = [[0, 1, 2], [0, 2, 2]]
+");
+
+        assert_eq!((line, column), (first_line() + 5, 23));
+        assert_eq!(message, "mesh triangle 1 has no area");
+
+        for (sky, key) in [
+            ("type = \"color\"\ncolor = [0.5, -0.5, 0.5]", "color"),
+            ("type = \"physical\"\nsun_elevation = 30.0\nturbidity = 0.0", "turbidity"),
+        ]
+        {
+            let source = HEADER.replace("type = \"color\"\ncolor = [0.5, 0.5, 0.5]", sky);
+
+            match SceneDesc::parse(&source)
+            {
+                Err(SceneError::Syntax { line, column, message, .. }) => {
+                    assert_eq!((line, column), (source.lines().count(), key.len() + 4), "{}", sky);
+                    assert!(message.contains(key), "{}", message);
+                },
+                result => panic!("unexpected {:?}", result.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn volume_errors_point_at_the_key()
+    {
+        let (line, column, _) = error("
+[atmosphere]
+scattering = [0.1, 0.1, 0.1]
+anisotropy = 1.5
+");
+
+        assert_eq!((line, column), (first_line() + 3, 14));
+
+        // Neither the comment nor the material's name may throw the location off.
+        let (line, column, message) = error("
+# A volume of smoke.
+[materials.volume]
+type = \"diffuse\"
+
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"volume\"
+
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"volume\"
+volume = { scattering = [1.0, 1.0, 1.0] }
+");
+
+        assert_eq!((line, column), (first_line() + 16, 10));
+        assert!(message.contains("passes through"), "{}", message);
+    }
+
+    #[test]
+    fn render_settings_are_checked()
+    {
+        for (setting, expected) in [("width = 0", "width"), ("samples = 0", "samples"), ("threads = 0", "threads")]
+        {
+            let (line, column, message) = error(&format!("\n[render]\nheight = 100\n{}\n", setting));
+
+            assert_eq!((line, column), (first_line() + 3, setting.len()));
+            assert!(message.starts_with(&format!("`{}`", expected)), "{}", message);
+        }
+
+        let (_, _, message) = error("\n[render]\nphoton_alpha = 1.5\n");
+
+        assert!(message.starts_with("`photon_alpha`"), "{}", message);
+
+        let desc = SceneDesc::parse(&format!("{}\n[render]\nwidth = 2\nsamples = 1\nthreads = 1\n", HEADER)).unwrap();
+
+        assert_eq!(desc.render.width, Some(2));
+    }
+
+    #[test]
+    fn descriptions_from_code_fail_to_build_instead_of_panicking()
+    {
+        let build = |rest: &str, change: &dyn Fn(&mut SceneDesc)| {
+            let mut desc = SceneDesc::parse(&format!("{}{}", HEADER, rest)).unwrap();
+
+            change(&mut desc);
+
+            match desc.build()
+            {
+                Err(SceneError::Invalid(message)) => message,
+                Err(err) => panic!("expected an invalid description, got {}", err),
+                Ok(_) => panic!("expected an invalid description"),
+            }
+        };
+
+        let sphere = "
+[materials.wood]
+type = \"diffuse\"
+maps = { color = \"grain\" }
+
+[textures.grain]
+type = \"checker\"
+even = [0.0, 0.0, 0.0]
+odd = [1.0, 1.0, 1.0]
+
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"wood\"
+";
+
+        let message = build(sphere, &|desc| { desc.materials.clear(); });
+
+        assert_eq!(message, "unknown material 'wood'");
+
+        let message = build(sphere, &|desc| { desc.textures.clear(); });
+
+        assert_eq!(message, "unknown texture 'grain'");
+
+        let message = build(sphere, &|desc| {
+            desc.materials.insert("wood".to_string(), MaterialDesc::Conductor {
+                metal: None,
+                eta: None,
+                k: None,
+                color: None,
+                roughness: 0.0,
+                emission: [0.0; 3],
+                maps: MapsDesc::default(),
+            });
+        });
+
+        assert!(message.contains("exactly one"), "{}", message);
+    }
+
+    #[test]
+    fn scenes_survive_a_round_trip()
+    {
+        let desc = SceneDesc::demo();
+        let again = SceneDesc::parse(&desc.to_toml()).unwrap();
+
+        assert_eq!(again.to_toml(), desc.to_toml());
+        assert_eq!(again.objects.len(), desc.objects.len());
+        assert_eq!(again.materials.len(), desc.materials.len());
+
+        let scene = again.build().unwrap();
+
+        assert_eq!(scene.objects().len(), desc.objects.len());
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected()
+    {
+        let newer = HEADER.replacen("version = 2", &format!("version = {}", VERSION + 1), 1);
+
+        match SceneDesc::parse(&newer)
+        {
+            Err(SceneError::Syntax { line: 1, column: 11, message, .. }) => {
+                assert!(message.contains("unsupported scene version"), "{}", message);
+            },
+            result => panic!("unexpected {:?}", result.map(|_| ())),
+        }
+
+        match SceneDesc::parse(&HEADER.replacen("version = 2\n", "", 1))
+        {
+            Err(SceneError::Syntax { message, .. }) => assert_eq!(message, "missing field `version`"),
+            result => panic!("unexpected {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected()
+    {
+        let (line, column, message) = error("\n[render]\nwidht = 100\n");
+
+        assert_eq!((line, column), (first_line() + 2, 1));
+        assert!(message.contains("unknown field `widht`"), "{}", message);
+    }
+
+    #[test]
+    fn gltf_materials_are_loaded_by_name()
+    {
+        let dir = std::env::temp_dir().join(format!("raytracer-gltf-{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("asset.gltf"), r#"{ "materials": [{ "name": "red", "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1] } }] }"#).unwrap();
+
+        let scene = |name: &str| {
+            let mut desc = SceneDesc::parse(&format!("{}
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = {{ type = \"gltf\", path = \"asset.gltf\", name = \"{}\" }}
+", HEADER, name)).unwrap();
+
+            desc.base_dir = dir.clone();
+            desc.build()
+        };
+
+        let red = scene("red");
+        let blue = scene("blue");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(red.is_ok());
+
+        match blue
+        {
+            Err(err) => assert!(err.to_string().contains("no material named 'blue'"), "{}", err),
+            Ok(_) => panic!("expected a missing material"),
+        }
+    }
+}
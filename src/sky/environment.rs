@@ -0,0 +1,161 @@
+use crate::{
+    RGB,
+    imageio,
+    math::{ Vec3, Rng, Distribution2D },
+};
+
+use rand::Rng as _;
+
+use std::f64::consts::PI;
+use std::io;
+use std::path::Path;
+
+/// An equirectangular environment map, longitude along the width and latitude along the
+/// height. The center of the image lies along +z, up along +y.
+///
+/// Directions are importance sampled by the luminance of the map, so small bright features
+/// like a sun are found quickly.
+pub struct Environment
+{
+    width: usize,
+    height: usize,
+    pixels: Vec<RGB>,
+    /// Rotation around the y axis in radians.
+    rotation: f64,
+    intensity: f32,
+    distribution: Distribution2D,
+}
+
+impl Environment
+{
+    /// `pixels` holds three linear `f32`s per pixel, row by row from the top.
+    pub fn new(width: usize, height: usize, pixels: &[f32], rotation: f64, intensity: f32) -> Environment
+    {
+        let pixels: Vec<RGB> = pixels.chunks(3).map(|p| RGB::new(p[0], p[1], p[2])).collect();
+
+        // Rows near the poles cover less of the sphere.
+        let weights: Vec<f64> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let latitude = (0.5 - ((i / width) as f64 + 0.5) / height as f64) * PI;
+
+                pixel.luminance() as f64 * latitude.cos()
+            })
+            .collect();
+
+        Environment {
+            width,
+            height,
+            distribution: Distribution2D::new(&weights, width),
+            pixels,
+            rotation,
+            intensity,
+        }
+    }
+
+    /// Loads a `.hdr` or `.pfm` environment map, `rotation` is given in degrees.
+    pub fn load(path: &Path, rotation: f64, intensity: f32) -> io::Result<Environment>
+    {
+        let (width, height, pixels) = imageio::load(path)?;
+
+        Ok(Environment::new(width, height, &pixels, rotation.to_radians(), intensity))
+    }
+
+    pub fn radiance(&self, direction: Vec3) -> RGB
+    {
+        let (u, v) = self.direction_to_uv(direction);
+
+        self.lookup(u, v) * self.intensity
+    }
+
+    /// A direction picked proportionally to the map's luminance, the radiance from it and
+    /// the density over solid angle it was picked with.
+    pub fn sample(&self, rng: &mut Rng) -> (Vec3, RGB, f64)
+    {
+        let ((u, v), pdf) = self.distribution.sample(rng.gen(), rng.gen());
+
+        let direction = self.uv_to_direction(u, v);
+        let cos_latitude = ((0.5 - v) * PI).cos();
+
+        if cos_latitude <= 0.0
+        {
+            return (direction, RGB::black(), 0.0);
+        }
+
+        (direction, self.lookup(u, v) * self.intensity, pdf / (2.0 * PI * PI * cos_latitude))
+    }
+
+    pub fn pdf(&self, direction: Vec3) -> f64
+    {
+        let (u, v) = self.direction_to_uv(direction);
+        let cos_latitude = ((0.5 - v) * PI).cos();
+
+        if cos_latitude <= 0.0
+        {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * cos_latitude)
+    }
+
+    fn direction_to_uv(&self, direction: Vec3) -> (f64, f64)
+    {
+        let (sin, cos) = self.rotation.sin_cos();
+
+        equirectangular_uv(Vec3::new(
+            direction.x * cos - direction.z * sin,
+            direction.y,
+            direction.x * sin + direction.z * cos,
+        ))
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3
+    {
+        let d = equirectangular_direction(u, v);
+        let (sin, cos) = self.rotation.sin_cos();
+
+        Vec3::new(d.x * cos + d.z * sin, d.y, -d.x * sin + d.z * cos)
+    }
+
+    /// Bilinearly filtered, wrapping around horizontally.
+    fn lookup(&self, u: f64, v: f64) -> RGB
+    {
+        let x = u * self.width as f64 - 0.5;
+        let y = (v * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = ((x - x0) as f32, (y - y0) as f32);
+
+        let column = |x: f64| (x as isize).rem_euclid(self.width as isize) as usize;
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let (y0, y1) = (y0 as usize, (y0 as usize + 1).min(self.height - 1));
+
+        let pixel = |x: usize, y: usize| self.pixels[x + y * self.width];
+
+        (pixel(x0, y0) * (1.0 - fx) + pixel(x1, y0) * fx) * (1.0 - fy)
+            + (pixel(x0, y1) * (1.0 - fx) + pixel(x1, y1) * fx) * fy
+    }
+}
+
+/// The point on an unrotated equirectangular map that `direction` maps to.
+pub fn equirectangular_uv(direction: Vec3) -> (f64, f64)
+{
+    let longitude = direction.x.atan2(direction.z);
+    let latitude = direction.y.clamp(-1.0, 1.0).asin();
+
+    (0.5 + longitude / (2.0 * PI), 0.5 - latitude / PI)
+}
+
+/// The direction a point on an unrotated equirectangular map shows.
+pub fn equirectangular_direction(u: f64, v: f64) -> Vec3
+{
+    let longitude = (u - 0.5) * 2.0 * PI;
+    let latitude = (0.5 - v) * PI;
+
+    Vec3::new(
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+        latitude.cos() * longitude.cos(),
+    )
+}
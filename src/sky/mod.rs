@@ -0,0 +1,133 @@
+mod environment;
+mod physical;
+
+pub use environment::Environment;
+pub use physical::PhysicalSky;
+
+use crate::{
+    RGB,
+    light::{ self, LightSample, Emission },
+    math::{ Vec3, Rng },
+};
+
+use std::f64::consts::PI;
+
+/// What rays that leave the scene see.
+// Scenes have a single sky, boxing the physical one would gain nothing.
+#[allow(clippy::large_enum_variant)]
+pub enum Sky
+{
+    /// The same color in every direction.
+    Color(RGB),
+    /// An equirectangular image around the scene, which also lights it.
+    Environment(Environment),
+    /// An analytic daylight sky, usually paired with its `PhysicalSky::sun`.
+    Physical(PhysicalSky),
+}
+
+impl Sky
+{
+    /// The radiance arriving from `direction`.
+    pub fn radiance(&self, direction: Vec3) -> RGB
+    {
+        match self
+        {
+            Sky::Color(color) => *color,
+            Sky::Environment(environment) => environment.radiance(direction),
+            Sky::Physical(sky) => sky.radiance(direction),
+        }
+    }
+
+    /// The radiance `sample` and `pdf` describe, which leaves out a physical sky's sun disk when
+    /// the sun is a light of its own. Rays leaving a point the lights were sampled from only
+    /// see this much, the rest was counted by the sun light.
+    pub fn sampled_radiance(&self, direction: Vec3) -> RGB
+    {
+        match self
+        {
+            Sky::Physical(sky) => sky.sampled_radiance(direction),
+            _ => self.radiance(direction),
+        }
+    }
+
+    /// Whether the sky is sampled like a light, in which case diffuse bounces must not count
+    /// it again when they miss.
+    pub fn is_sampled(&self) -> bool
+    {
+        match self
+        {
+            Sky::Color(_) => false,
+            Sky::Environment(_) | Sky::Physical(_) => true,
+        }
+    }
+
+    /// Picks a direction to sample the sky in, for skies that are sampled like lights.
+    pub fn sample(&self, rng: &mut Rng) -> Option<LightSample>
+    {
+        let (direction, radiance, pdf) = match self
+        {
+            Sky::Color(_) => return None,
+            Sky::Environment(environment) => environment.sample(rng),
+            Sky::Physical(sky) => sky.sample(rng),
+        };
+
+        if pdf <= 0.0
+        {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            color: radiance / pdf as f32,
+            pdf,
+        })
+    }
+
+    /// The density, over solid angle, of `sample` picking `direction`.
+    pub fn pdf(&self, direction: Vec3) -> f64
+    {
+        match self
+        {
+            Sky::Color(_) => 0.0,
+            Sky::Environment(environment) => environment.pdf(direction),
+            Sky::Physical(sky) => sky.pdf(direction),
+        }
+    }
+
+    /// Like `sample`, but skies that are not sampled like lights pick a uniformly random
+    /// direction, for treating every sky as a light.
+    pub fn sample_emitted(&self, rng: &mut Rng) -> Option<LightSample>
+    {
+        if self.is_sampled()
+        {
+            return self.sample(rng);
+        }
+
+        let direction = Vec3::random_unit(rng);
+
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            color: self.radiance(direction) * (4.0 * PI) as f32,
+            pdf: 1.0 / (4.0 * PI),
+        })
+    }
+
+    /// Picks a ray of light from the sky, entering the sphere around the scene at `center`.
+    pub fn emit(&self, center: Vec3, radius: f64, rng: &mut Rng) -> Option<Emission>
+    {
+        let sample = self.sample_emitted(rng)?;
+
+        Some(Emission {
+            pdf_dir: sample.pdf,
+            ..light::distant_emission(-sample.direction, self.sampled_radiance(sample.direction), center, radius, rng)
+        })
+    }
+
+    /// The density, over solid angle, of `sample_emitted` picking `direction`.
+    pub fn emitted_pdf(&self, direction: Vec3) -> f64
+    {
+        if self.is_sampled() { self.pdf(direction) } else { 1.0 / (4.0 * PI) }
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{
+    RGB,
+    light::Sun,
+    math::{ Vec3, Rng },
+    sky::{ Environment, environment::equirectangular_direction },
+};
+
+use rand::Rng as _;
+
+use std::f64::consts::PI;
+
+/// Maps the model's luminance, in kcd/m², to the range the other lights work in.
+const LUMINANCE_SCALE: f64 = 0.05;
+
+/// Illuminance from the sun outside the atmosphere, in klx.
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+/// Rayleigh optical depth of the whole atmosphere at 680, 550 and 440 nm.
+const RAYLEIGH_DEPTH: [f64; 3] = [0.0464, 0.108, 0.265];
+
+/// The same wavelengths in micrometers, for the aerosol optical depth.
+const WAVELENGTHS: [f64; 3] = [0.68, 0.55, 0.44];
+
+/// Size of the table the sky is importance sampled from.
+const TABLE_SIZE: (usize, usize) = (128, 64);
+
+/// How often `PhysicalSky::sample` picks the sun disk, unless the sun is a light of its own.
+const SUN_PROBABILITY: f64 = 0.5;
+
+/// The Preetham et al. analytic daylight model, with the sun it is lit by.
+///
+/// The sky is dark below the horizon, outdoor scenes are expected to have a ground.
+pub struct PhysicalSky
+{
+    /// Unit vector towards the sun.
+    sun_direction: Vec3,
+    /// Zenith angle of the sun.
+    theta_sun: f64,
+    /// Perez coefficients `A` to `E` for `Y`, `x` and `y`.
+    perez: [[f64; 5]; 3],
+    /// `Y`, `x` and `y` at the zenith.
+    zenith: [f64; 3],
+    intensity: f32,
+    sun_color: RGB,
+    sun_angular_diameter: f64,
+    /// Whether the sun is added to the scene as a light, and left out when sampling the sky.
+    sun_is_light: bool,
+    /// The sky without the sun, tabulated for importance sampling.
+    table: Environment,
+}
+
+impl PhysicalSky
+{
+    /// The sun's `elevation` above the horizon and `azimuth` from +z towards +x are given in
+    /// degrees, as is `sun_diameter`. `turbidity` ranges from about 2 for a clear sky to 10 for
+    /// a hazy one.
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64, intensity: f32, sun_diameter: f64) -> PhysicalSky
+    {
+        let elevation = elevation.clamp(0.0, 90.0).to_radians();
+        let azimuth = azimuth.to_radians();
+        let t = turbidity.clamp(1.0, 20.0);
+
+        let theta_sun = PI / 2.0 - elevation;
+
+        let perez = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let cubic = |c: [f64; 4]| c[0] * theta_sun.powi(3) + c[1] * theta_sun.powi(2) + c[2] * theta_sun + c[3];
+
+        let zenith = [
+            (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192,
+            t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+                + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+                + cubic([0.11693, -0.21196, 0.06052, 0.25886]),
+            t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+                + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+                + cubic([0.15346, -0.26756, 0.06670, 0.26688]),
+        ];
+
+        // Sunlight is attenuated by Rayleigh scattering and by aerosols, whose amount follows
+        // the turbidity, along the path through the atmosphere (Kasten and Young's air mass).
+        let air_mass = 1.0 / (theta_sun.cos() + 0.50572 * (96.07995 - theta_sun.to_degrees()).powf(-1.6364));
+        let aerosol = 0.04608 * t - 0.04586;
+        let transmittance: Vec<f64> = (0..3)
+            .map(|i| (-air_mass * (RAYLEIGH_DEPTH[i] + aerosol * WAVELENGTHS[i].powf(-1.3))).exp())
+            .collect();
+        let sun_scale = (SOLAR_ILLUMINANCE * LUMINANCE_SCALE) as f32 * intensity;
+
+        let mut sky = PhysicalSky {
+            sun_direction: Vec3::new(elevation.cos() * azimuth.sin(), elevation.sin(), elevation.cos() * azimuth.cos()),
+            theta_sun,
+            perez,
+            zenith,
+            intensity,
+            sun_color: RGB::new(transmittance[0] as f32, transmittance[1] as f32, transmittance[2] as f32) * sun_scale,
+            sun_angular_diameter: sun_diameter.to_radians(),
+            sun_is_light: false,
+            table: Environment::new(1, 1, &[1.0; 3], 0.0, 1.0),
+        };
+
+        let (width, height) = TABLE_SIZE;
+        let mut pixels = Vec::with_capacity(width * height * 3);
+
+        for y in 0..height
+        {
+            for x in 0..width
+            {
+                let direction = equirectangular_direction((x as f64 + 0.5) / width as f64, (y as f64 + 0.5) / height as f64);
+                let radiance = sky.sky_radiance(direction);
+
+                pixels.extend_from_slice(&[radiance.r, radiance.g, radiance.b]);
+            }
+        }
+
+        sky.table = Environment::new(width, height, &pixels, 0.0, 1.0);
+
+        sky
+    }
+
+    /// Leaves the sun disk out of `sample`, `pdf` and `sampled_radiance`, for scenes that add
+    /// `sun` as a light of their own.
+    pub fn with_sun_light(mut self) -> PhysicalSky
+    {
+        self.sun_is_light = true;
+        self
+    }
+
+    /// Whether the sun is a light of its own, see `with_sun_light`.
+    pub fn sun_is_light(&self) -> bool
+    {
+        self.sun_is_light
+    }
+
+    /// The light of the sun this sky is lit by.
+    pub fn sun(&self) -> Sun
+    {
+        Sun::new(-self.sun_direction, self.sun_color, self.sun_angular_diameter.to_degrees())
+    }
+
+    /// The radiance of the sky, including the sun disk.
+    pub fn radiance(&self, direction: Vec3) -> RGB
+    {
+        if self.in_sun(direction) && direction.y > 0.0
+        {
+            // Spread the sun's light evenly over its disk.
+            return self.sun_color * self.sun_pdf() as f32;
+        }
+
+        self.sky_radiance(direction)
+    }
+
+    /// The radiance of the sky without the sun disk.
+    pub fn sky_radiance(&self, direction: Vec3) -> RGB
+    {
+        if direction.y <= 0.0
+        {
+            return RGB::black();
+        }
+
+        let cos_theta = direction.y.min(1.0);
+        let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let value = |i: usize| {
+            self.zenith[i] * perez(self.perez[i], cos_theta, gamma) / perez(self.perez[i], 1.0, self.theta_sun)
+        };
+
+        let (luminance, x, y) = (value(0) * LUMINANCE_SCALE, value(1), value(2));
+
+        // xyY to XYZ to linear sRGB.
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+
+        RGB::new(
+            (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0) as f32,
+            (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0) as f32,
+            (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0) as f32,
+        ) * self.intensity
+    }
+
+    /// The radiance `sample` and `pdf` describe, which leaves out the sun disk if the sun is a
+    /// light of its own.
+    pub fn sampled_radiance(&self, direction: Vec3) -> RGB
+    {
+        if self.sun_is_light { self.sky_radiance(direction) } else { self.radiance(direction) }
+    }
+
+    /// A direction picked proportionally to the sky's luminance, or within the sun disk, the
+    /// radiance from it and the density over solid angle it was picked with.
+    pub fn sample(&self, rng: &mut Rng) -> (Vec3, RGB, f64)
+    {
+        let direction = if !self.sun_is_light && rng.gen::<f64>() < SUN_PROBABILITY
+        {
+            Vec3::random_in_cone(rng, self.sun_direction, self.cos_sun_radius())
+        }
+        else
+        {
+            self.table.sample(rng).0
+        };
+
+        (direction, self.sampled_radiance(direction), self.pdf(direction))
+    }
+
+    pub fn pdf(&self, direction: Vec3) -> f64
+    {
+        if self.sun_is_light
+        {
+            return self.table.pdf(direction);
+        }
+
+        let sun_pdf = if self.in_sun(direction) { self.sun_pdf() } else { 0.0 };
+
+        SUN_PROBABILITY * sun_pdf + (1.0 - SUN_PROBABILITY) * self.table.pdf(direction)
+    }
+
+    fn cos_sun_radius(&self) -> f64
+    {
+        (self.sun_angular_diameter * 0.5).cos()
+    }
+
+    fn in_sun(&self, direction: Vec3) -> bool
+    {
+        direction.dot(self.sun_direction) >= self.cos_sun_radius()
+    }
+
+    /// The density of directions picked uniformly within the sun disk, one over its solid angle.
+    fn sun_pdf(&self) -> f64
+    {
+        1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius()))
+    }
+}
+
+/// The Perez et al. sky luminance distribution for a view `gamma` radians from the sun.
+fn perez(c: [f64; 5], cos_theta: f64, gamma: f64) -> f64
+{
+    (1.0 + c[0] * (c[1] / cos_theta.max(0.01)).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos() * gamma.cos())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use rand::SeedableRng;
+
+    const SAMPLES: usize = 100_000;
+
+    fn sky() -> PhysicalSky
+    {
+        PhysicalSky::new(40.0, 30.0, 3.0, 1.0, 2.0)
+    }
+
+    /// The light arriving from the whole sky, estimated by sampling it.
+    fn sampled_total(sky: &PhysicalSky) -> RGB
+    {
+        let mut rng = Rng::seed_from_u64(1);
+        let mut total = RGB::black();
+
+        for _ in 0..SAMPLES
+        {
+            let (direction, radiance, pdf) = sky.sample(&mut rng);
+
+            assert_eq!(pdf, sky.pdf(direction));
+            assert_eq!(radiance, sky.sampled_radiance(direction));
+
+            if pdf > 0.0
+            {
+                total += radiance / (pdf * SAMPLES as f64) as f32;
+            }
+        }
+
+        total
+    }
+
+    fn assert_close(a: RGB, b: RGB)
+    {
+        assert!((a.luminance() - b.luminance()).abs() < 0.02 * b.luminance(), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn the_table_matches_the_sky()
+    {
+        let sky = sky().with_sun_light();
+        let mut rng = Rng::seed_from_u64(2);
+        let mut uniform = RGB::black();
+
+        for _ in 0..SAMPLES
+        {
+            let direction = Vec3::random_unit(&mut rng);
+
+            uniform += sky.sky_radiance(direction) * (4.0 * PI / SAMPLES as f64) as f32;
+        }
+
+        assert_close(sampled_total(&sky), uniform);
+    }
+
+    #[test]
+    fn the_sun_is_sampled_unless_it_is_a_light()
+    {
+        let with_sun = sampled_total(&sky());
+        let without_sun = sampled_total(&sky().with_sun_light());
+
+        assert_close(with_sun, without_sun + sky().sun().color);
+    }
+}
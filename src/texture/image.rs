@@ -0,0 +1,154 @@
+use crate::{
+    RGB,
+    imageio,
+};
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How texture coordinates outside of `[0, 1)` are mapped onto the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wrap
+{
+    /// Tiles the image.
+    Repeat,
+    /// Tiles the image, flipping every other tile so the edges line up.
+    Mirror,
+    /// Extends the edge pixels.
+    Clamp,
+}
+
+impl fmt::Display for Wrap
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Wrap::Repeat => write!(f, "repeat"),
+            Wrap::Mirror => write!(f, "mirror"),
+            Wrap::Clamp => write!(f, "clamp"),
+        }
+    }
+}
+
+impl FromStr for Wrap
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Wrap, String>
+    {
+        match s
+        {
+            "repeat" => Ok(Wrap::Repeat),
+            "mirror" => Ok(Wrap::Mirror),
+            "clamp" => Ok(Wrap::Clamp),
+            _ => Err(format!("unknown wrap mode '{}', expected repeat, mirror or clamp", s)),
+        }
+    }
+}
+
+impl Wrap
+{
+    /// Maps pixel index `i` into `0..size`.
+    fn apply(self, i: i64, size: usize) -> usize
+    {
+        let size = size as i64;
+
+        let i = match self
+        {
+            Wrap::Repeat => i.rem_euclid(size),
+            Wrap::Mirror => {
+                let i = i.rem_euclid(2 * size);
+
+                if i < size { i } else { 2 * size - 1 - i }
+            },
+            Wrap::Clamp => i.clamp(0, size - 1),
+        };
+
+        i as usize
+    }
+}
+
+/// An image mapped onto the texture coordinates, `v` going up from the bottom row, and
+/// filtered bilinearly.
+#[derive(Debug, Clone)]
+pub struct ImageTexture
+{
+    width: usize,
+    height: usize,
+    /// Shared between all materials using the same image.
+    pixels: Arc<Vec<f32>>,
+    pub wrap: Wrap,
+    /// How many times the image repeats per unit of texture coordinates.
+    pub scale: f64,
+}
+
+impl ImageTexture
+{
+    /// `pixels` holds three linear values per pixel, row by row from the top.
+    pub fn new(width: usize, height: usize, pixels: Vec<f32>, wrap: Wrap) -> ImageTexture
+    {
+        assert_eq!(pixels.len(), width * height * 3);
+
+        ImageTexture {
+            width,
+            height,
+            pixels: Arc::new(pixels),
+            wrap,
+            scale: 1.0,
+        }
+    }
+
+    /// Loads a PNG, JPEG, HDR or PFM image. 8-bit images are gamma decoded unless `raw` is
+    /// set, which data like roughness maps needs.
+    pub fn load(path: &Path, raw: bool, wrap: Wrap) -> io::Result<ImageTexture>
+    {
+        let (width, height, pixels) = if raw { imageio::load_raw(path)? } else { imageio::load(path)? };
+
+        if width == 0 || height == 0
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty image"));
+        }
+
+        Ok(ImageTexture::new(width, height, pixels, wrap))
+    }
+
+    pub fn with_scale(mut self, scale: f64) -> ImageTexture
+    {
+        self.scale = scale;
+        self
+    }
+
+    pub fn size(&self) -> (usize, usize)
+    {
+        (self.width, self.height)
+    }
+
+    /// The bilinearly interpolated color at texture coordinates `uv`.
+    pub fn lookup(&self, uv: (f64, f64)) -> RGB
+    {
+        let x = uv.0 * self.scale * self.width as f64 - 0.5;
+        let y = (1.0 - uv.1 * self.scale) * self.height as f64 - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = ((x - x0) as f32, (y - y0) as f32);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        self.pixel(x0, y0) * ((1.0 - fx) * (1.0 - fy))
+            + self.pixel(x0 + 1, y0) * (fx * (1.0 - fy))
+            + self.pixel(x0, y0 + 1) * ((1.0 - fx) * fy)
+            + self.pixel(x0 + 1, y0 + 1) * (fx * fy)
+    }
+
+    fn pixel(&self, x: i64, y: i64) -> RGB
+    {
+        let x = self.wrap.apply(x, self.width);
+        let y = self.wrap.apply(y, self.height);
+        let i = (y * self.width + x) * 3;
+
+        RGB::new(self.pixels[i], self.pixels[i + 1], self.pixels[i + 2])
+    }
+}
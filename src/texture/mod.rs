@@ -0,0 +1,135 @@
+//! Textures varying material parameters over a surface.
+//!
+//! A texture is looked up with the texture coordinates and the point of a hit. Image textures
+//! use the coordinates, procedural patterns either of them depending on their `Mapping`.
+
+mod image;
+mod procedural;
+mod noise;
+
+pub use image::{ ImageTexture, Wrap };
+pub use procedural::{ Checker, Gradient, Noise, Voronoi, VoronoiMode };
+
+use crate::{
+    RGB,
+    HitRecord,
+    math::Vec3,
+};
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+pub enum Texture
+{
+    Image(ImageTexture),
+    Checker(Checker),
+    Gradient(Gradient),
+    Noise(Noise),
+    Voronoi(Voronoi),
+}
+
+impl Texture
+{
+    /// The texture's color at a hit.
+    pub fn color(&self, hit: &HitRecord) -> RGB
+    {
+        match self
+        {
+            Texture::Image(texture) => texture.lookup(hit.uv),
+            Texture::Checker(texture) => texture.color(texture.mapping.point(hit)),
+            Texture::Gradient(texture) => texture.color(texture.mapping.point(hit)),
+            Texture::Noise(texture) => texture.color(texture.mapping.point(hit)),
+            Texture::Voronoi(texture) => texture.color(texture.mapping.point(hit)),
+        }
+    }
+
+    /// The texture's value at a hit, for textures driving a single parameter.
+    pub fn value(&self, hit: &HitRecord) -> f32
+    {
+        self.color(hit).luminance()
+    }
+}
+
+/// Where on a surface a procedural pattern is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureSpace
+{
+    /// The texture coordinates, as `(u, v, 0)`.
+    Uv,
+    /// The hit point in world space, like a solid block the object is carved from.
+    World,
+}
+
+impl fmt::Display for TextureSpace
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            TextureSpace::Uv => write!(f, "uv"),
+            TextureSpace::World => write!(f, "world"),
+        }
+    }
+}
+
+impl FromStr for TextureSpace
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TextureSpace, String>
+    {
+        match s
+        {
+            "uv" => Ok(TextureSpace::Uv),
+            "world" => Ok(TextureSpace::World),
+            _ => Err(format!("unknown texture space '{}', expected uv or world", s)),
+        }
+    }
+}
+
+/// Maps a hit to the point a procedural pattern is evaluated at.
+#[derive(Debug, Clone, Copy)]
+pub struct Mapping
+{
+    pub space: TextureSpace,
+    /// How many times the pattern repeats per unit, its frequency.
+    pub scale: f64,
+}
+
+impl Mapping
+{
+    pub fn new(space: TextureSpace, scale: f64) -> Mapping
+    {
+        Mapping {
+            space,
+            scale,
+        }
+    }
+
+    pub fn point(&self, hit: &HitRecord) -> Vec3
+    {
+        let point = match self.space
+        {
+            TextureSpace::Uv => Vec3::new(hit.uv.0, hit.uv.1, 0.0),
+            TextureSpace::World => hit.point,
+        };
+
+        point * self.scale
+    }
+}
+
+impl Default for Mapping
+{
+    fn default() -> Mapping
+    {
+        Mapping::new(TextureSpace::Uv, 1.0)
+    }
+}
+
+fn mix(a: RGB, b: RGB, t: f64) -> RGB
+{
+    let t = t.clamp(0.0, 1.0) as f32;
+
+    a * (1.0 - t) + b * t
+}
@@ -0,0 +1,133 @@
+//! Lattice noise functions, hashing the lattice points instead of using permutation tables.
+
+use crate::math::Vec3;
+
+/// Perlin's improved gradient noise, roughly from -1 to 1 and 0 at every lattice point.
+pub fn perlin(p: Vec3) -> f64
+{
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let (fx, fy, fz) = (p.x - x0, p.y - y0, p.z - z0);
+    let (x0, y0, z0) = (x0 as i64, y0 as i64, z0 as i64);
+
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        gradient(hash(x0 + dx, y0 + dy, z0 + dz), fx - dx as f64, fy - dy as f64, fz - dz as f64)
+    };
+
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+
+    lerp(w,
+        lerp(v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0))),
+        lerp(v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1))))
+}
+
+/// Fractional Brownian motion: `octaves` layers of Perlin noise, each at twice the frequency
+/// and `gain` times the amplitude of the one before, normalized to roughly -1 to 1.
+pub fn fbm(p: Vec3, octaves: u32, gain: f64) -> f64
+{
+    let mut sum = 0.0;
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+
+    for _ in 0..octaves.max(1)
+    {
+        sum += amplitude * perlin(p * frequency);
+        total += amplitude;
+        amplitude *= gain;
+        frequency *= 2.0;
+    }
+
+    sum / total
+}
+
+/// Worley's cellular noise: the distance to the closest of the feature points scattered one
+/// per unit cell, and a random number from 0 to 1 identifying that point's cell.
+pub fn worley(p: Vec3) -> (f64, f64)
+{
+    let (x0, y0, z0) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+
+    let mut closest = (f64::INFINITY, 0.0);
+
+    for dz in -1..=1
+    {
+        for dy in -1..=1
+        {
+            for dx in -1..=1
+            {
+                let (x, y, z) = (x0 + dx, y0 + dy, z0 + dz);
+                let h = hash(x, y, z);
+
+                let feature = Vec3::new(
+                    x as f64 + unit(h),
+                    y as f64 + unit(h.rotate_left(21)),
+                    z as f64 + unit(h.rotate_left(42)),
+                );
+
+                let distance = (feature - p).length();
+
+                if distance < closest.0
+                {
+                    closest = (distance, unit(mix(h)));
+                }
+            }
+        }
+    }
+
+    closest
+}
+
+fn hash(x: i64, y: i64, z: i64) -> u64
+{
+    mix((x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (z as u64).wrapping_mul(0x1656_67b1_9e37_79f9))
+}
+
+/// The SplitMix64 finalizer.
+fn mix(mut h: u64) -> u64
+{
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    h ^ (h >> 31)
+}
+
+/// The low 21 bits of `h` as a number from 0 to 1.
+fn unit(h: u64) -> f64
+{
+    (h & 0x1f_ffff) as f64 / (1u64 << 21) as f64
+}
+
+/// The dot product with one of the twelve gradients towards the edges of a cube.
+fn gradient(h: u64, x: f64, y: f64, z: f64) -> f64
+{
+    match h % 12
+    {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+fn fade(t: f64) -> f64
+{
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64
+{
+    a + t * (b - a)
+}
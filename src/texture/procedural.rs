@@ -0,0 +1,167 @@
+use crate::{
+    RGB,
+    math::Vec3,
+};
+use super::{ Mapping, mix, noise };
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Alternating cubes of two colors, squares in texture space.
+#[derive(Debug, Clone, Copy)]
+pub struct Checker
+{
+    pub even: RGB,
+    pub odd: RGB,
+    pub mapping: Mapping,
+}
+
+impl Checker
+{
+    pub fn new(even: RGB, odd: RGB, mapping: Mapping) -> Checker
+    {
+        Checker {
+            even,
+            odd,
+            mapping,
+        }
+    }
+
+    pub fn color(&self, p: Vec3) -> RGB
+    {
+        let sum = p.x.floor() as i64 + p.y.floor() as i64 + p.z.floor() as i64;
+
+        if sum.rem_euclid(2) == 0 { self.even } else { self.odd }
+    }
+}
+
+/// A linear blend from `from` to `to` along `axis`, over one unit.
+#[derive(Debug, Clone, Copy)]
+pub struct Gradient
+{
+    pub from: RGB,
+    pub to: RGB,
+    pub axis: Vec3,
+    pub mapping: Mapping,
+}
+
+impl Gradient
+{
+    pub fn new(from: RGB, to: RGB, axis: Vec3, mapping: Mapping) -> Gradient
+    {
+        Gradient {
+            from,
+            to,
+            axis,
+            mapping,
+        }
+    }
+
+    pub fn color(&self, p: Vec3) -> RGB
+    {
+        mix(self.from, self.to, p.dot(self.axis))
+    }
+}
+
+/// Fractal Perlin noise blending between two colors, for clouds, marble and dirt.
+#[derive(Debug, Clone, Copy)]
+pub struct Noise
+{
+    pub low: RGB,
+    pub high: RGB,
+    /// The number of layers of ever finer noise.
+    pub octaves: u32,
+    /// How much each layer is weakened compared to the one before, about 0.5.
+    pub gain: f64,
+    pub mapping: Mapping,
+}
+
+impl Noise
+{
+    pub fn new(low: RGB, high: RGB, octaves: u32, gain: f64, mapping: Mapping) -> Noise
+    {
+        Noise {
+            low,
+            high,
+            octaves,
+            gain,
+            mapping,
+        }
+    }
+
+    pub fn color(&self, p: Vec3) -> RGB
+    {
+        mix(self.low, self.high, 0.5 + 0.5 * noise::fbm(p, self.octaves, self.gain))
+    }
+}
+
+/// What a `Voronoi` pattern shows of the cells.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoronoiMode
+{
+    /// Blends by the distance to the closest cell center, dark spots in bright cracks.
+    Distance,
+    /// Fills each cell with a random blend of the two colors, like flagstones.
+    Cells,
+}
+
+impl fmt::Display for VoronoiMode
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            VoronoiMode::Distance => write!(f, "distance"),
+            VoronoiMode::Cells => write!(f, "cells"),
+        }
+    }
+}
+
+impl FromStr for VoronoiMode
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<VoronoiMode, String>
+    {
+        match s
+        {
+            "distance" => Ok(VoronoiMode::Distance),
+            "cells" => Ok(VoronoiMode::Cells),
+            _ => Err(format!("unknown voronoi mode '{}', expected distance or cells", s)),
+        }
+    }
+}
+
+/// Worley's cellular pattern, with one randomly placed cell per unit cube.
+#[derive(Debug, Clone, Copy)]
+pub struct Voronoi
+{
+    pub low: RGB,
+    pub high: RGB,
+    pub mode: VoronoiMode,
+    pub mapping: Mapping,
+}
+
+impl Voronoi
+{
+    pub fn new(low: RGB, high: RGB, mode: VoronoiMode, mapping: Mapping) -> Voronoi
+    {
+        Voronoi {
+            low,
+            high,
+            mode,
+            mapping,
+        }
+    }
+
+    pub fn color(&self, p: Vec3) -> RGB
+    {
+        let (distance, cell) = noise::worley(p);
+
+        match self.mode
+        {
+            VoronoiMode::Distance => mix(self.low, self.high, distance),
+            VoronoiMode::Cells => mix(self.low, self.high, cell),
+        }
+    }
+}
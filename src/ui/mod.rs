@@ -1,3 +1,11 @@
+use crate::{
+    Scene,
+    RGB,
+    imageio,
+    render::{ self, RenderSettings, Pass },
+    film::{ Film, FilmSample },
+};
+
 use glium::{
     glutin,
     texture::Texture2d,
@@ -6,11 +14,111 @@ use glium::{
     Surface,
 };
 
-pub fn ui_main<F>(mut render: F)
-where F: FnMut((u32, u32), &mut [f32]) -> bool
+/// Opens a window and renders `scene` into it progressively, a few lines per thread each frame,
+/// until `settings.samples` passes are done or the window is closed. Pressing `I` switches to
+/// the next integrator and starts over.
+pub fn view(mut scene: Scene, mut settings: RenderSettings)
+{
+    const LINES_PER_THREAD: usize = 8;
+
+    let seed = settings.seed_or_random();
+    let lines_per_frame = LINES_PER_THREAD * settings.threads.max(1);
+
+    let mut iteration = 0;
+    let mut line = 0;
+    let mut film = Film::new(settings.width, settings.height, settings.filter);
+    let mut samples = vec![];
+    let mut splats = vec![];
+    let mut pass = None;
+
+    ui_main((settings.width as u32, settings.height as u32), |(w, h), keys, pixels| {
+        let (w, h) = (w as usize, h as usize);
+        let mut restart = false;
+
+        if keys.contains(&glutin::VirtualKeyCode::I)
+        {
+            settings.integrator = settings.integrator.next();
+            restart = true;
+
+            println!("integrator: {}", settings.integrator);
+        }
+
+        if scene.camera().width() != w || scene.camera().height() != h
+        {
+            scene.camera().set_w_h((w, h));
+            restart = true;
+        }
+
+        if restart
+        {
+            film = Film::new(w, h, settings.filter);
+            iteration = 0;
+            line = 0;
+            pass = None;
+        }
+
+        if iteration >= settings.samples
+        {
+            return false;
+        }
+
+        let lines = line..(line + lines_per_frame).min(h);
+
+        samples.resize(lines.len() * w, FilmSample { x: 0.0, y: 0.0, color: RGB::black() });
+        let current = pass.get_or_insert_with(|| Pass::new(&scene, &settings, iteration, seed));
+
+        splats.clear();
+        render::trace_lines(&scene, lines.clone(), current, &settings, &mut samples, &mut splats);
+        film.add_samples(&samples);
+        film.add_splats(&splats);
+
+        // The filter spreads samples onto neighbouring lines as well, and light traced from
+        // the lights lands anywhere.
+        let radius = settings.filter.radius().ceil() as usize;
+        let redraw = if splats.is_empty()
+        {
+            lines.start.saturating_sub(radius)..(lines.end + radius).min(h)
+        }
+        else
+        {
+            0..h
+        };
+
+        for y in redraw
+        {
+            for x in 0..w
+            {
+                let color = film.pixel(x, y).clamp().gamma(imageio::GAMMA);
+                let start = (x + y * w) * 3;
+
+                pixels[start]     = color.r;
+                pixels[start + 1] = color.g;
+                pixels[start + 2] = color.b;
+            }
+        }
+
+        if lines.end >= h
+        {
+            line = 0;
+            iteration += 1;
+            pass = None;
+        }
+        else
+        {
+            line = lines.end;
+        }
+
+        true
+    });
+}
+
+/// Opens a window of the given size and calls `render` every frame with the current size,
+/// the keys pressed since the last frame and the pixel buffer to draw. The frame is only
+/// redrawn when `render` returns `true`.
+pub fn ui_main<F>(size: (u32, u32), mut render: F)
+where F: FnMut((u32, u32), &[glutin::VirtualKeyCode], &mut [f32]) -> bool
 {
-    let mut width  = 640;
-    let mut height = 480;
+    let (mut width, mut height) = size;
 
     let mut events_loop = glutin::EventsLoop::new();
     let wb = glutin::WindowBuilder::new()
@@ -20,30 +128,38 @@ where F: FnMut((u32, u32), &mut [f32]) -> bool
 
     let display = glium::Display::new(wb, cb, &events_loop).unwrap();
 
-    let mut pixels = vec![];
+    let mut pixels = vec![0.0; width as usize * height as usize * 3];
     let mut tex_frame = FrameBufferTexture::empty(&display, width, height);
 
     let mut close_requested = false;
     while !close_requested
     {
         let mut new_size = None;
+        let mut keys = vec![];
 
         events_loop.poll_events(|event| {
-            match event
+            if let glutin::Event::WindowEvent { event, .. } = event
             {
-                glutin::Event::WindowEvent { event, .. } => {
-                    match event
-                    {
-                        glutin::WindowEvent::CloseRequested => {
-                            close_requested = true;
+                match event
+                {
+                    glutin::WindowEvent::CloseRequested => {
+                        close_requested = true;
+                    },
+                    glutin::WindowEvent::Resized(size) => {
+                        new_size = Some(size.into());
+                    },
+                    glutin::WindowEvent::KeyboardInput {
+                        input: glutin::KeyboardInput {
+                            state: glutin::ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
                         },
-                        glutin::WindowEvent::Resized(size) => {
-                            new_size = Some(size.into());
-                        },
-                        _ => (),
-                    }
-                },
-                _ => (),
+                        ..
+                    } => {
+                        keys.push(key);
+                    },
+                    _ => (),
+                }
             }
         });
 
@@ -57,7 +173,7 @@ where F: FnMut((u32, u32), &mut [f32]) -> bool
             tex_frame = FrameBufferTexture::empty(&display, width, height);
         }
 
-        if render((width, height), &mut pixels)
+        if render((width, height), &keys, &mut pixels)
         {
             let target = display.draw();
 
@@ -65,8 +181,8 @@ where F: FnMut((u32, u32), &mut [f32]) -> bool
                 glium::Rect {
                     left: 0,
                     bottom: 0,
-                    width: width,
-                    height: height,
+                    width,
+                    height,
                 },
                 RawImage2d::from_raw_rgb_reversed(
                     &pixels,
@@ -126,7 +242,7 @@ impl<'a> Drop for FrameBufferTexture<'a>
     {
         unsafe
         {
-            Box::from_raw(self.texture);
+            drop(Box::from_raw(self.texture));
         }
     }
 }
\ No newline at end of file